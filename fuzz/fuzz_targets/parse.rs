@@ -0,0 +1,8 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+// 任意 UTF-8 文本只跑解析阶段：允许返回错误，但不允许 panic
+fuzz_target!(|tex: &str| {
+    let _ = formula_render::fuzzing::parse_only(tex);
+});
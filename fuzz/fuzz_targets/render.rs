@@ -0,0 +1,10 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+// 完整渲染流水线：解析、排版、SVG 输出都不允许 panic
+fuzz_target!(|tex: &str| {
+    if let Err(formula_render::RenderError::UnexpectedPanic) = formula_render::render_formula(tex) {
+        panic!("渲染过程中发生 panic: {tex:?}");
+    }
+});
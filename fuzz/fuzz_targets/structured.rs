@@ -0,0 +1,13 @@
+#![no_main]
+
+use formula_render_fuzz::LatexInput;
+use libfuzzer_sys::fuzz_target;
+
+// 由结构化生成器拼出接近真实 LaTeX 的输入，更容易深入各条解析规则
+fuzz_target!(|input: LatexInput| {
+    let tex = input.to_latex();
+    if let Err(formula_render::RenderError::UnexpectedPanic) = formula_render::render_formula(&tex)
+    {
+        panic!("渲染过程中发生 panic: {tex:?}");
+    }
+});
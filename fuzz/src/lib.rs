@@ -0,0 +1,380 @@
+//! 结构化输入生成器：把任意字节映射成“长得像 LaTeX”的公式字符串，
+//! 让模糊测试更多地走进命令、环境与定界符等解析分支，而不是停在词法阶段
+
+use arbitrary::{Arbitrary, Result, Unstructured};
+
+/// 生成时的最大嵌套层数，超过后只输出叶子节点
+const MAX_GEN_DEPTH: usize = 12;
+
+/// 无参数命令：符号、函数名、间距与大型运算符
+const BARE_COMMANDS: &[&str] = &[
+    "alpha",
+    "beta",
+    "Delta",
+    "pm",
+    "leq",
+    "neq",
+    "infty",
+    "cdots",
+    "ldots",
+    "to",
+    "sum",
+    "prod",
+    "int",
+    "oint",
+    "lim",
+    "max",
+    "min",
+    "sin",
+    "log",
+    "bigcup",
+    "argmax",
+    "bigl",
+    "Bigr",
+    "biggm",
+    "middle",
+    "over",
+    "choose",
+    "atop",
+    "hline",
+    "notag",
+    "nonumber",
+    ",",
+    ";",
+    ":",
+    "!",
+    "quad",
+    "qquad",
+    " ",
+    "displaystyle",
+    "\\",
+    "&",
+    "{",
+    "}",
+    "",
+];
+
+/// 接收一个花括号参数的命令
+const UNARY_COMMANDS: &[&str] = &[
+    "text",
+    "tag",
+    "label",
+    "mathbf",
+    "mathit",
+    "mathrm",
+    "mathsf",
+    "mathtt",
+    "mathbb",
+    "mathcal",
+    "mathfrak",
+    "overline",
+    "underline",
+    "hat",
+    "bar",
+    "tilde",
+    "vec",
+    "widehat",
+    "widetilde",
+    "overrightarrow",
+    "underleftarrow",
+    "dot",
+    "ddot",
+    "acute",
+    "breve",
+    "mathring",
+    "overbrace",
+    "underbrace",
+    "overbracket",
+    "underparen",
+    "xrightarrow",
+    "xleftarrow",
+    "xRightarrow",
+    "sqrt",
+    "matrix",
+    "substack",
+    "ce",
+    "pu",
+    "bra",
+    "ket",
+    "braket",
+    "expval",
+    "abs",
+    "norm",
+    "dv",
+    "num",
+    "si",
+    "boxed",
+    "fbox",
+    "cancel",
+    "bcancel",
+    "xcancel",
+    "sout",
+    "phantom",
+    "hphantom",
+    "vphantom",
+    "smash",
+    "hspace",
+];
+
+/// 接收两个花括号参数的命令
+const BINARY_COMMANDS: &[&str] = &[
+    "frac", "dfrac", "tfrac", "cfrac", "binom", "dbinom", "tbinom", "overset", "underset",
+    "stackrel", "comm", "acomm", "ketbra", "dv", "pdv", "SI", "cancelto", "rule", "raisebox",
+];
+
+/// 可用于 `\left` / `\right` 的定界符
+const DELIMITERS: &[&str] = &[
+    "(",
+    ")",
+    "[",
+    "]",
+    "|",
+    ".",
+    "\\{",
+    "\\}",
+    "\\langle",
+    "\\rangle",
+    "\\lceil",
+    "\\rfloor",
+    "\\Vert",
+    "\\unknown",
+    "",
+];
+
+/// 环境名称，故意包含一个不存在的环境
+const ENVIRONMENTS: &[&str] = &[
+    "matrix",
+    "pmatrix",
+    "bmatrix",
+    "Bmatrix",
+    "vmatrix",
+    "Vmatrix",
+    "cases",
+    "cases*",
+    "dcases",
+    "rcases",
+    "numcases",
+    "CD",
+    "aligned",
+    "align",
+    "align*",
+    "alignat",
+    "split",
+    "gather",
+    "multline",
+    "equation",
+    "smallmatrix",
+    "pmatrix*",
+    "subarray",
+    "array",
+    "bogus",
+];
+
+/// 叶子文本可选的字符，包含多字节字符与会触发特殊分支的符号
+const TEXT_CHARS: &[char] = &[
+    'a', 'x', 'y', 'Z', '0', '1', '9', '+', '-', '=', '<', '>', '(', ')', '[', ']', '|', '&', ',',
+    '.', ' ', '\'', '@', 'V', 'α', '∑', '中', '\u{0}', '\u{200b}',
+];
+
+/// 由 libFuzzer 字节流构造的完整输入
+#[derive(Debug)]
+pub struct LatexInput {
+    nodes: Vec<Fragment>,
+}
+
+impl LatexInput {
+    /// 拼接成最终的 LaTeX 字符串
+    pub fn to_latex(&self) -> String {
+        let mut out = String::new();
+        for node in &self.nodes {
+            node.write(&mut out);
+        }
+        out
+    }
+}
+
+impl<'a> Arbitrary<'a> for LatexInput {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        let mut nodes = Vec::new();
+        while !u.is_empty() && nodes.len() < 32 {
+            nodes.push(Fragment::generate(u, 0)?);
+        }
+        Ok(Self { nodes })
+    }
+}
+
+#[derive(Debug)]
+enum Fragment {
+    Text(String),
+    Bare(&'static str),
+    Unary(&'static str, Box<Fragment>),
+    Binary(&'static str, Box<Fragment>, Box<Fragment>),
+    Group(Vec<Fragment>),
+    Superscript(Box<Fragment>),
+    Subscript(Box<Fragment>),
+    SqrtIndex(Box<Fragment>, Box<Fragment>),
+    LeftRight(&'static str, Vec<Fragment>, &'static str),
+    Environment(&'static str, Vec<Vec<Fragment>>),
+    /// 故意不闭合的结构，用于覆盖错误路径
+    Unbalanced(&'static str),
+}
+
+impl Fragment {
+    fn generate(u: &mut Unstructured<'_>, depth: usize) -> Result<Self> {
+        if depth >= MAX_GEN_DEPTH {
+            return Ok(Fragment::Text(generate_text(u)?));
+        }
+        let next = depth + 1;
+        let fragment = match u.int_in_range(0u8..=10)? {
+            0 => Fragment::Text(generate_text(u)?),
+            1 => Fragment::Bare(u.choose(BARE_COMMANDS)?),
+            2 => Fragment::Unary(
+                u.choose(UNARY_COMMANDS)?,
+                Box::new(Self::generate(u, next)?),
+            ),
+            3 => Fragment::Binary(
+                u.choose(BINARY_COMMANDS)?,
+                Box::new(Self::generate(u, next)?),
+                Box::new(Self::generate(u, next)?),
+            ),
+            4 => Fragment::Group(generate_list(u, next, 4)?),
+            5 => Fragment::Superscript(Box::new(Self::generate(u, next)?)),
+            6 => Fragment::Subscript(Box::new(Self::generate(u, next)?)),
+            7 => Fragment::SqrtIndex(
+                Box::new(Self::generate(u, next)?),
+                Box::new(Self::generate(u, next)?),
+            ),
+            8 => Fragment::LeftRight(
+                u.choose(DELIMITERS)?,
+                generate_list(u, next, 4)?,
+                u.choose(DELIMITERS)?,
+            ),
+            9 => {
+                let row_count = u.int_in_range(0usize..=3)?;
+                let mut rows = Vec::with_capacity(row_count);
+                for _ in 0..row_count {
+                    rows.push(generate_list(u, next, 3)?);
+                }
+                Fragment::Environment(u.choose(ENVIRONMENTS)?, rows)
+            }
+            _ => Fragment::Unbalanced(u.choose(&[
+                "{",
+                "}",
+                "\\left(",
+                "\\right)",
+                "\\begin{matrix}",
+                "\\end{matrix}",
+                "\\sqrt[",
+                "^",
+                "_",
+                "\\",
+                "\\\\",
+                "\\frac{",
+            ])?),
+        };
+        Ok(fragment)
+    }
+
+    fn write(&self, out: &mut String) {
+        match self {
+            Fragment::Text(text) => out.push_str(text),
+            Fragment::Bare(name) => {
+                out.push('\\');
+                out.push_str(name);
+                out.push(' ');
+            }
+            Fragment::Unary(name, arg) => {
+                out.push('\\');
+                out.push_str(name);
+                write_braced(arg, out);
+            }
+            Fragment::Binary(name, first, second) => {
+                out.push('\\');
+                out.push_str(name);
+                write_braced(first, out);
+                write_braced(second, out);
+            }
+            Fragment::Group(children) => {
+                out.push('{');
+                children.iter().for_each(|child| child.write(out));
+                out.push('}');
+            }
+            Fragment::Superscript(script) => {
+                out.push('^');
+                write_braced(script, out);
+            }
+            Fragment::Subscript(script) => {
+                out.push('_');
+                write_braced(script, out);
+            }
+            Fragment::SqrtIndex(index, value) => {
+                out.push_str("\\sqrt[");
+                index.write(out);
+                out.push(']');
+                write_braced(value, out);
+            }
+            Fragment::LeftRight(left, inner, right) => {
+                out.push_str("\\left");
+                out.push_str(left);
+                inner.iter().for_each(|child| child.write(out));
+                out.push_str("\\right");
+                out.push_str(right);
+            }
+            Fragment::Environment(name, rows) => {
+                out.push_str("\\begin{");
+                out.push_str(name);
+                out.push('}');
+                if *name == "array" {
+                    out.push_str("{|c||r@{:}l|} \\hline ");
+                } else if *name == "subarray" {
+                    out.push_str("{l} ");
+                } else if *name == "numcases" {
+                    out.push_str("{f(x)=} ");
+                } else if *name == "alignat" {
+                    out.push_str("{2} ");
+                }
+                // 交换图的对象之间用箭头分隔
+                let cell_separator = if *name == "CD" { " @>>> " } else { " & " };
+                for (row_idx, row) in rows.iter().enumerate() {
+                    if row_idx > 0 {
+                        out.push_str(" \\\\ ");
+                    }
+                    for (cell_idx, cell) in row.iter().enumerate() {
+                        if cell_idx > 0 {
+                            out.push_str(cell_separator);
+                        }
+                        cell.write(out);
+                    }
+                }
+                out.push_str("\\end{");
+                out.push_str(name);
+                out.push('}');
+            }
+            Fragment::Unbalanced(raw) => out.push_str(raw),
+        }
+    }
+}
+
+fn write_braced(fragment: &Fragment, out: &mut String) {
+    out.push('{');
+    fragment.write(out);
+    out.push('}');
+}
+
+fn generate_text(u: &mut Unstructured<'_>) -> Result<String> {
+    let len = u.int_in_range(1usize..=4)?;
+    let mut text = String::with_capacity(len);
+    for _ in 0..len {
+        text.push(*u.choose(TEXT_CHARS)?);
+    }
+    Ok(text)
+}
+
+fn generate_list(u: &mut Unstructured<'_>, depth: usize, max_len: usize) -> Result<Vec<Fragment>> {
+    let len = u.int_in_range(0..=max_len)?;
+    let mut list = Vec::with_capacity(len);
+    for _ in 0..len {
+        list.push(Fragment::generate(u, depth)?);
+    }
+    Ok(list)
+}
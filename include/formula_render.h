@@ -0,0 +1,41 @@
+/*
+ * formula_render 共享库的 C 接口声明，与 src/ffi.rs 保持一致。
+ *
+ * 所有渲染函数都返回以 '\0' 结尾的 SVG 字符串，调用方用完后必须交给 free_svg 释放。
+ * 渲染失败时同样返回一段红字提示的 SVG，而不是空指针，提示文字与原因对应如下：
+ *
+ *   Empty Formula         输入为空
+ *   Formula Too Complex   超出复杂度上限或工作预算：嵌套层数、节点数、矩阵单元格数、
+ *                         SVG 体积、布局盒数量、绘制元素数量，或渲染超时
+ *   Invalid Formula       空指针或非 UTF-8 输入、超过 5KB 的公式、语法错误及其他失败
+ */
+#ifndef FORMULA_RENDER_H
+#define FORMULA_RENDER_H
+
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/* 按当前默认上限渲染公式 */
+char *render_svg(const char *tex);
+
+/*
+ * 带工作预算渲染：各参数为 0 时沿用当前配置的默认值；timeout_ms 为 0 时
+ * 沿用已配置的超时，未配置时不限时。
+ * 超出布局盒数量、绘制元素数量或超时都返回 "Formula Too Complex"。
+ */
+char *render_svg_with_budget(const char *tex,
+                             uint64_t max_layout_boxes,
+                             uint64_t max_render_items,
+                             uint64_t timeout_ms);
+
+/* 释放上述函数返回的字符串，传入空指针时什么也不做 */
+void free_svg(char *ptr);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* FORMULA_RENDER_H */
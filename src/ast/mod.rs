@@ -4,12 +4,11 @@ use crate::error::RenderError;
 pub enum AstNode {
     Text(String),
     Group(Vec<AstNode>),
-    Fraction {
-        numerator: Box<AstNode>,
-        denominator: Box<AstNode>,
-    },
+    Fraction(FractionNode),
     Sqrt {
         value: Box<AstNode>,
+        /// 根指数，如 `\sqrt[3]{x}` 中的 3
+        index: Option<Box<AstNode>>,
     },
     Delimited {
         left: Delimiter,
@@ -18,7 +17,8 @@ pub enum AstNode {
     },
     LargeOperator(LargeOperatorNode),
     Symbol(SpecialSymbol),
-    Matrix(Vec<Vec<AstNode>>),
+    /// 表格：`array`、矩阵与 align 家族环境共用，括号等定界符由环境在外层包裹
+    Array(ArrayNode),
     Decorated {
         base: Box<AstNode>,
         decoration: DecorationKind,
@@ -28,6 +28,83 @@ pub enum AstNode {
         superscript: Option<Box<AstNode>>,
         subscript: Option<Box<AstNode>>,
     },
+    /// 在主体正上方/正下方居中堆叠脚标尺寸的注释，如 `\overset`、`\underset`
+    UnderOver {
+        base: Box<AstNode>,
+        over: Option<Box<AstNode>>,
+        under: Option<Box<AstNode>>,
+    },
+    /// `\middle` 定界符，只出现在 `\left...\right` 内部，高度与外层定界符一致
+    Middle(Delimiter),
+    /// `\big` 系列手动指定高度的单个定界符
+    SizedDelimiter(SizedDelimiterNode),
+    /// 交换图（`CD` 环境）中的箭头，在表格中伸缩到所在列的宽度
+    CdArrow(CdArrowNode),
+    /// 边框、底色与删除线，如 `\boxed`、`\colorbox`、`\cancel`，主体基线保持不变
+    Enclosed(EnclosureNode),
+    /// 只改变主体占用的尺寸：`\phantom` 系列不绘制主体，`\smash` 忽略高度或深度，
+    /// `\raisebox` 整体上下平移
+    Adjusted {
+        body: Box<AstNode>,
+        adjust: BoxAdjust,
+    },
+    /// 显式水平间距（以字号为单位，可为负），如 `\kern`、`\hspace`
+    Kern(f32),
+    /// `\rule` 画出的实心矩形
+    Rule(RuleNode),
+}
+
+impl AstNode {
+    /// 统计以当前节点为根的子树节点总数，用于复杂度限制
+    pub fn node_count(&self) -> usize {
+        let children: usize = match self {
+            AstNode::Text(_)
+            | AstNode::LargeOperator(_)
+            | AstNode::Symbol(_)
+            | AstNode::Middle(_)
+            | AstNode::SizedDelimiter(_)
+            | AstNode::Kern(_)
+            | AstNode::Rule(_) => 0,
+            AstNode::Group(children) => children.iter().map(AstNode::node_count).sum(),
+            AstNode::Fraction(fraction) => {
+                fraction.numerator.node_count() + fraction.denominator.node_count()
+            }
+            AstNode::Sqrt { value, index } => {
+                value.node_count() + index.as_ref().map_or(0, |node| node.node_count())
+            }
+            AstNode::Delimited { inner, .. } => inner.node_count(),
+            AstNode::Array(array) => array.cell_node_count(),
+            AstNode::Decorated { base, .. } => base.node_count(),
+            AstNode::Scripts {
+                base,
+                superscript,
+                subscript,
+            } => {
+                base.node_count()
+                    + superscript.as_ref().map_or(0, |node| node.node_count())
+                    + subscript.as_ref().map_or(0, |node| node.node_count())
+            }
+            AstNode::UnderOver { base, over, under } => {
+                base.node_count()
+                    + over.as_ref().map_or(0, |node| node.node_count())
+                    + under.as_ref().map_or(0, |node| node.node_count())
+            }
+            AstNode::CdArrow(arrow) => [&arrow.before, &arrow.after]
+                .into_iter()
+                .flatten()
+                .map(|node| node.node_count())
+                .sum(),
+            AstNode::Enclosed(enclosure) => {
+                enclosure.body.node_count()
+                    + enclosure
+                        .target
+                        .as_ref()
+                        .map_or(0, |node| node.node_count())
+            }
+            AstNode::Adjusted { body, .. } => body.node_count(),
+        };
+        children + 1
+    }
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -35,13 +112,36 @@ pub enum DecorationKind {
     Overline,
     Underline,
     Hat,
+    Check,
     Bar,
     Tilde,
+    Acute,
+    Grave,
+    Breve,
     Vector,
     Dot,
     Ddot,
+    Dddot,
+    Ddddot,
+    MathRing,
     Overbrace,
     Underbrace,
+    Overbracket,
+    Underbracket,
+    Overparen,
+    Underparen,
+    WideHat,
+    WideTilde,
+    WideCheck,
+    OverRightArrow,
+    OverLeftArrow,
+    OverLeftRightArrow,
+    OverLeftHarpoon,
+    OverRightHarpoon,
+    UnderRightArrow,
+    UnderLeftArrow,
+    UnderLeftRightArrow,
+    UnderTilde,
 }
 
 #[derive(Debug, Clone)]
@@ -55,6 +155,180 @@ pub struct Delimiter {
     pub glyph: Option<String>,
 }
 
+/// 广义分数：覆盖 `\frac`、`\dfrac`、`\tfrac`、`\cfrac`、`\genfrac`、`\binom` 系列与中缀 `\over` 等写法
+#[derive(Debug, Clone)]
+pub struct FractionNode {
+    pub numerator: Box<AstNode>,
+    pub denominator: Box<AstNode>,
+    /// 分数线粗细（以字号为单位）；`None` 使用默认粗细，0 表示不画线
+    pub rule_thickness: Option<f32>,
+    /// 强制的分子分母样式；`None` 沿用当前字号
+    pub style: Option<MathStyle>,
+    pub left: Delimiter,
+    pub right: Delimiter,
+    /// 分子的水平对齐方式，`\cfrac[l]` / `\cfrac[r]` 使用
+    pub align: FractionAlign,
+}
+
+impl FractionNode {
+    /// 普通 `\frac`：默认分数线、无定界符、居中对齐
+    pub fn new(numerator: AstNode, denominator: AstNode) -> Self {
+        Self {
+            numerator: Box::new(numerator),
+            denominator: Box::new(denominator),
+            rule_thickness: None,
+            style: None,
+            left: Delimiter { glyph: None },
+            right: Delimiter { glyph: None },
+            align: FractionAlign::Center,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ArrayNode {
+    pub rows: Vec<Vec<AstNode>>,
+    pub columns: Vec<ColumnAlign>,
+    /// 列边界：第 i 项位于第 i 列左侧，最后一项位于最后一列右侧，长度为列数加一
+    pub column_separators: Vec<ColumnSeparator>,
+    /// 行边界：第 i 项位于第 i 行上方，最后一项位于最后一行下方，长度为行数加一
+    pub row_separators: Vec<RowSeparator>,
+    /// 行高倍数，对应 `\arraystretch`
+    pub row_stretch: f32,
+    /// 逐行覆盖列对齐（`multline` 首行靠左、末行靠右）；为空表示全部按列格式对齐
+    pub row_aligns: Vec<Option<ColumnAlign>>,
+    /// 每列两侧的留白（以单元格字号为单位），对应 `\arraycolsep`
+    pub column_padding: f32,
+    /// 单元格样式；`smallmatrix` 等取脚标样式，`None` 沿用当前字号
+    pub cell_style: Option<MathStyle>,
+}
+
+impl ArrayNode {
+    /// 单元格与 `@{...}` 分隔内容的节点总数
+    pub fn cell_node_count(&self) -> usize {
+        self.rows
+            .iter()
+            .flatten()
+            .map(AstNode::node_count)
+            .sum::<usize>()
+            + self
+                .column_separators
+                .iter()
+                .filter_map(|separator| separator.custom.as_ref())
+                .map(|node| node.node_count())
+                .sum::<usize>()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColumnAlign {
+    Left,
+    Center,
+    Right,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ColumnSeparator {
+    /// 竖线数量，`||` 为 2
+    pub rules: usize,
+    /// `@{...}` 指定的分隔内容，存在时取代两侧的列间距
+    pub custom: Option<Box<AstNode>>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct RowSeparator {
+    /// `\hline` 数量
+    pub hlines: usize,
+    /// `\cline{i-j}` 覆盖的列范围（从 0 起算，含两端）
+    pub clines: Vec<(usize, usize)>,
+    /// 上一行 `\\[..]` 指定的额外行距（以字号为单位）
+    pub extra_space: f32,
+}
+
+/// TeX 的四种数学样式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MathStyle {
+    Display,
+    Text,
+    Script,
+    ScriptScript,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FractionAlign {
+    Center,
+    Left,
+    Right,
+}
+
+#[derive(Debug, Clone)]
+pub struct SizedDelimiterNode {
+    pub delimiter: Delimiter,
+    /// 定界符高度（以字号为单位）
+    pub height: f32,
+    /// 两侧附加的间距（以字号为单位），`\bigm` 按关系符留白
+    pub spacing: f32,
+}
+
+/// 交换图箭头：`@>a>b>` 等横向箭头的标签在上下，`@VaVbV` 等纵向箭头的标签在左右
+#[derive(Debug, Clone)]
+pub struct CdArrowNode {
+    /// 伸缩的字形：`→`、`←`、`=`、`↓`、`↑` 或 `‖`
+    pub glyph: char,
+    pub vertical: bool,
+    /// 横向箭头上方或纵向箭头左侧的标签
+    pub before: Option<Box<AstNode>>,
+    /// 横向箭头下方或纵向箭头右侧的标签
+    pub after: Option<Box<AstNode>>,
+}
+
+/// 包围或划掉主体的方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EnclosureKind {
+    /// 矩形边框或底色：`\boxed`、`\fbox`、`\colorbox`、`\fcolorbox`
+    Frame,
+    /// 圆角边框：`\ovalbox`
+    RoundedFrame,
+    /// 左下到右上的斜线：`\cancel`、`\cancelto`
+    Cancel,
+    /// 左上到右下的斜线：`\bcancel`
+    BackCancel,
+    /// 交叉的两条斜线：`\xcancel`
+    CrossCancel,
+    /// 穿过数学轴的横线：`\sout`
+    StrikeOut,
+}
+
+#[derive(Debug, Clone)]
+pub struct EnclosureNode {
+    pub body: Box<AstNode>,
+    pub kind: EnclosureKind,
+    /// 边框颜色（SVG 颜色值），`None` 表示不画边框；删除线总是黑色
+    pub stroke: Option<&'static str>,
+    /// 底色，`None` 表示透明
+    pub fill: Option<&'static str>,
+    /// `\cancelto` 的目标值，以脚标字号画在箭头末端
+    pub target: Option<Box<AstNode>>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BoxAdjust {
+    /// 不绘制主体；`width`/`height` 为 false 时对应方向的尺寸记为 0
+    Phantom { width: bool, height: bool },
+    /// 照常绘制主体，但把基线以上（`top`）或以下（`bottom`）的尺寸记为 0
+    Smash { top: bool, bottom: bool },
+    /// 把主体抬高的距离（以字号为单位，负值表示下沉）
+    Raise(f32),
+}
+
+/// `\rule[抬高]{宽}{高}`，各尺寸均以字号为单位
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RuleNode {
+    pub width: f32,
+    pub height: f32,
+    pub raise: f32,
+}
+
 #[derive(Debug, Clone, Copy)]
 pub enum SpecialSymbol {
     Sum,
@@ -62,14 +336,89 @@ pub enum SpecialSymbol {
     Integral,
 }
 
+/// 一行中出现的编号命令：`\tag`、`\notag`/`\nonumber` 与 `\label`
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct EquationMarks {
+    /// `\tag{..}` 的内容，以及是否为不加括号的 `\tag*`
+    pub tag: Option<(String, bool)>,
+    /// 出现过 `\notag` 或 `\nonumber`
+    pub suppressed: bool,
+    pub label: Option<String>,
+}
+
+impl EquationMarks {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// 合并嵌套内容（单元格、`split` 等）中的编号命令，同一行只允许一个 `\tag` 与一个 `\label`
+    pub fn merge(&mut self, other: EquationMarks) -> ParseResult<()> {
+        if let Some(tag) = other.tag {
+            if self.tag.is_some() {
+                return Err(RenderError::ParseError("同一行只能有一个 \\tag".into()));
+            }
+            self.tag = Some(tag);
+        }
+        if let Some(label) = other.label {
+            if self.label.is_some() {
+                return Err(RenderError::ParseError("同一行只能有一个 \\label".into()));
+            }
+            self.label = Some(label);
+        }
+        self.suppressed |= other.suppressed;
+        Ok(())
+    }
+
+    /// 按所在环境是否自动编号，得到该行最终的编号
+    pub fn resolve(self, numbered: bool) -> RowTag {
+        let tag = match self.tag {
+            Some((text, starred)) => EquationTag::Custom { text, starred },
+            None if numbered && !self.suppressed => EquationTag::Auto,
+            None => EquationTag::None,
+        };
+        RowTag {
+            tag,
+            label: self.label,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum EquationTag {
+    None,
+    /// 由计数器分配编号
+    Auto,
+    /// `\tag` 指定的编号，`starred` 时原样显示、不加括号
+    Custom {
+        text: String,
+        starred: bool,
+    },
+}
+
+/// 一行公式的编号与标签
+#[derive(Debug, Clone, PartialEq)]
+pub struct RowTag {
+    pub tag: EquationTag,
+    pub label: Option<String>,
+}
+
 #[derive(Debug, Clone)]
 pub struct ParsedFormula {
     pub ast: AstNode,
+    /// 编号：`ast` 为顶层 align 等编号环境时与表格各行一一对应，否则至多一项，属于整条公式
+    pub tags: Vec<RowTag>,
+    /// 顶层 numcases 环境的表格：此时 `ast` 只是左侧公式，表格带左花括号接在其后，
+    /// `tags` 与表格各行对应
+    pub numcases: Option<ArrayNode>,
 }
 
 impl ParsedFormula {
-    pub fn new(ast: AstNode) -> Self {
-        Self { ast }
+    pub fn new(ast: AstNode, tags: Vec<RowTag>, numcases: Option<ArrayNode>) -> Self {
+        Self {
+            ast,
+            tags,
+            numcases,
+        }
     }
 }
 
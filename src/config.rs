@@ -1,9 +1,13 @@
-//! 渲染配置模块：管理 SVG 输出模式等可调参数
+//! 渲染配置模块：管理 SVG 输出模式、复杂度上限等可调参数
 
 use once_cell::sync::Lazy; // 延迟读取环境变量
 use std::env; // 读取环境变量
 use std::sync::atomic::{AtomicBool, Ordering as BoolOrdering};
 use std::sync::atomic::{AtomicU8, Ordering}; // 存储全局覆盖开关
+use std::sync::RwLock; // 存储复杂度上限覆盖值
+use std::time::{Duration, Instant}; // 渲染超时与截止时间
+
+use crate::error::RenderError;
 
 /// SVG 输出模式：保留 `<text>` 还是转换为矢量路径
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
@@ -67,3 +71,134 @@ pub fn should_embed_font() -> bool {
 pub fn override_embed_font(enable: bool) {
     EMBED_FONT_OVERRIDE.store(enable, BoolOrdering::Relaxed);
 }
+
+/// 公式编号显示在哪一侧
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Default)]
+pub enum TagSide {
+    #[default]
+    Right,
+    Left,
+}
+
+/// `\num`、`\SI` 等数值命令输出的小数点符号
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Default)]
+pub enum DecimalMarker {
+    /// 英文习惯的小数点 `1.5`
+    #[default]
+    Point,
+    /// 德、法等欧陆习惯的小数逗号 `1,5`
+    Comma,
+}
+
+impl DecimalMarker {
+    pub fn symbol(self) -> char {
+        match self {
+            DecimalMarker::Point => '.',
+            DecimalMarker::Comma => ',',
+        }
+    }
+}
+
+/// 单次渲染的设置：自动编号的起始值、编号的摆放位置与数值的小数点符号
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct EquationOptions {
+    /// 第一个自动编号的值，便于调用方跨公式延续计数
+    pub first_number: u32,
+    /// 显示宽度（像素，含四周留白）：公式在其中居中，编号贴齐一侧；
+    /// `None` 时编号紧随公式之后，相隔 1em
+    pub display_width: Option<f32>,
+    pub tag_side: TagSide,
+    /// siunitx 数值命令默认使用的小数点符号，可被命令自身的 `output-decimal-marker` 选项覆盖
+    pub decimal_marker: DecimalMarker,
+}
+
+impl Default for EquationOptions {
+    fn default() -> Self {
+        Self {
+            first_number: 1,
+            display_width: None,
+            tag_side: TagSide::Right,
+            decimal_marker: DecimalMarker::Point,
+        }
+    }
+}
+
+/// 单次渲染的复杂度上限，防止恶意或异常公式耗尽栈空间与内存
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct RenderLimits {
+    /// 大括号、环境、`\left...\right` 等结构的最大嵌套层数
+    pub max_depth: usize,
+    /// 解析后语法树允许的最大节点数
+    pub max_nodes: usize,
+    /// 单个矩阵/多行环境允许的最大单元格数
+    pub max_matrix_cells: usize,
+    /// 最终 SVG 文本允许的最大字节数
+    pub max_output_bytes: usize,
+    /// 排版阶段允许生成的最大布局盒数量
+    pub max_layout_boxes: usize,
+    /// 单个布局盒内允许累积的最大绘制元素（文字、线条、路径）数量
+    pub max_render_items: usize,
+    /// 单次渲染的墙钟时间上限，`None` 表示不限时
+    pub timeout: Option<Duration>,
+}
+
+impl Default for RenderLimits {
+    fn default() -> Self {
+        Self {
+            max_depth: 64,
+            max_nodes: 4096,
+            max_matrix_cells: 1024,
+            max_output_bytes: 4 * 1024 * 1024,
+            max_layout_boxes: 16384,
+            max_render_items: 16384,
+            timeout: None,
+        }
+    }
+}
+
+static LIMITS_OVERRIDE: RwLock<Option<RenderLimits>> = RwLock::new(None);
+
+/// 获取当前生效的复杂度上限（未覆盖时使用默认值）
+pub fn render_limits() -> RenderLimits {
+    match LIMITS_OVERRIDE.read() {
+        Ok(guard) => guard.unwrap_or_default(),
+        Err(poisoned) => poisoned.into_inner().unwrap_or_default(),
+    }
+}
+
+/// 允许在运行时覆盖复杂度上限；`None` 表示还原为默认设置
+pub fn override_render_limits(limits: Option<RenderLimits>) {
+    match LIMITS_OVERRIDE.write() {
+        Ok(mut guard) => *guard = limits,
+        Err(poisoned) => *poisoned.into_inner() = limits,
+    }
+}
+
+/// 单次渲染的工作预算：复杂度上限加上由超时换算出的截止时间
+#[derive(Copy, Clone, Debug)]
+pub(crate) struct RenderBudget {
+    pub limits: RenderLimits,
+    pub deadline: Option<Instant>,
+}
+
+impl RenderBudget {
+    /// 以当前时刻为起点创建预算
+    pub fn start(limits: RenderLimits) -> Self {
+        Self {
+            limits,
+            deadline: limits
+                .timeout
+                .and_then(|timeout| Instant::now().checked_add(timeout)),
+        }
+    }
+
+    /// 协作式检查是否已超过截止时间，供解析与排版循环调用
+    pub fn check_deadline(&self) -> Result<(), RenderError> {
+        match self.deadline {
+            Some(deadline) if Instant::now() >= deadline => Err(RenderError::LimitExceeded(
+                format!("渲染耗时超过 {:?}", self.limits.timeout.unwrap_or_default()),
+            )),
+            _ => Ok(()),
+        }
+    }
+}
@@ -23,6 +23,9 @@ pub enum RenderError {
     /// 字符串包含非法的 UTF-8 编码
     #[error("公式不是合法的 UTF-8 文本")]
     InvalidUtf8, // 当跨语言传入的字符串编码不正确时使用
+    /// 公式嵌套层数、节点数量或输出体积超过配置上限
+    #[error("公式复杂度超出限制: {0}")]
+    LimitExceeded(String), // 保存触发的具体限制项
     /// 捕获 panic 后返回的通用错误
     #[error("内部渲染发生未知异常")]
     UnexpectedPanic, // 统一 panic 捕获后的错误
@@ -3,8 +3,11 @@
 use std::ffi::{CStr, CString}; // 引入 C 字符串转换相关类型
 use std::os::raw::c_char; // 引入 C 语言字符类型
 
+use std::time::Duration; // 超时参数换算
+
+use crate::config::{render_limits, RenderLimits}; // 引入复杂度上限配置
 use crate::error::RenderError; // 引入错误类型，便于做模式匹配
-use crate::render_formula; // 引入核心渲染函数
+use crate::{render_formula, render_formula_with_limits}; // 引入核心渲染函数
 
 /// 统一定义当渲染失败时返回的兜底 SVG
 const INVALID_SVG: &str = r#"<svg xmlns="http://www.w3.org/2000/svg"><text x="0" y="14" font-size="14" fill="red">Invalid Formula</text></svg>"#; // 简单的错误提示 SVG
@@ -21,6 +24,7 @@ fn error_to_svg(err: RenderError) -> *mut c_char {
     let message = match err { // 根据不同错误类型决定提示内容
         RenderError::EmptyInput => "<svg xmlns=\"http://www.w3.org/2000/svg\"><text x=\"0\" y=\"14\" font-size=\"14\" fill=\"red\">Empty Formula</text></svg>",
         RenderError::InvalidUtf8 => "<svg xmlns=\"http://www.w3.org/2000/svg\"><text x=\"0\" y=\"14\" font-size=\"14\" fill=\"red\">Invalid UTF-8</text></svg>",
+        RenderError::LimitExceeded(_) => "<svg xmlns=\"http://www.w3.org/2000/svg\"><text x=\"0\" y=\"14\" font-size=\"14\" fill=\"red\">Formula Too Complex</text></svg>",
         _ => INVALID_SVG, // 其他错误使用通用提示
     };
     string_to_c_pointer(message) // 返回对应的 SVG
@@ -28,6 +32,7 @@ fn error_to_svg(err: RenderError) -> *mut c_char {
 
 /// C 可调用的渲染入口
 #[no_mangle] // 确保函数名不被编译器修改
+#[allow(clippy::not_unsafe_ptr_arg_deref)] // 指针合法性由 C 调用方保证，保持 extern 签名稳定
 pub extern "C" fn render_svg(tex: *const c_char) -> *mut c_char {
     if tex.is_null() {
         // 判断指针是否为空
@@ -48,8 +53,51 @@ pub extern "C" fn render_svg(tex: *const c_char) -> *mut c_char {
     }
 }
 
+/// C 可调用的带预算渲染入口：各参数为 0 时沿用当前配置的默认值；
+/// `timeout_ms` 为 0 时沿用已配置的超时，未配置时不限时
+#[no_mangle]
+#[allow(clippy::not_unsafe_ptr_arg_deref)] // 指针合法性由 C 调用方保证，保持 extern 签名稳定
+pub extern "C" fn render_svg_with_budget(
+    tex: *const c_char,
+    max_layout_boxes: u64,
+    max_render_items: u64,
+    timeout_ms: u64,
+) -> *mut c_char {
+    if tex.is_null() {
+        return string_to_c_pointer(INVALID_SVG);
+    }
+
+    let input = unsafe { CStr::from_ptr(tex) };
+    let formula_str = match input.to_str() {
+        Ok(content) => content,
+        Err(_) => return string_to_c_pointer(INVALID_SVG),
+    };
+
+    let limits = budget_limits(max_layout_boxes, max_render_items, timeout_ms);
+    match render_formula_with_limits(formula_str, &limits) {
+        Ok(svg) => string_to_c_pointer(&svg),
+        Err(err) => error_to_svg(err),
+    }
+}
+
+/// 把 C 侧传入的预算参数合并到当前默认上限上
+fn budget_limits(max_layout_boxes: u64, max_render_items: u64, timeout_ms: u64) -> RenderLimits {
+    let mut limits = render_limits();
+    if max_layout_boxes > 0 {
+        limits.max_layout_boxes = usize::try_from(max_layout_boxes).unwrap_or(usize::MAX);
+    }
+    if max_render_items > 0 {
+        limits.max_render_items = usize::try_from(max_render_items).unwrap_or(usize::MAX);
+    }
+    if timeout_ms > 0 {
+        limits.timeout = Some(Duration::from_millis(timeout_ms));
+    }
+    limits
+}
+
 /// 供外部语言在使用完字符串后释放内存
 #[no_mangle] // 同样确保符号名稳定
+#[allow(clippy::not_unsafe_ptr_arg_deref)] // 只接受 render_svg 返回的指针
 pub extern "C" fn free_svg(ptr: *mut c_char) {
     if ptr.is_null() {
         // 避免对空指针重复释放
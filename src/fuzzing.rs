@@ -0,0 +1,11 @@
+//! 模糊测试入口：仅在 `fuzzing` 特性下编译，向 cargo-fuzz 暴露内部各阶段
+
+use crate::config::{render_limits, EquationOptions, RenderBudget};
+use crate::error::RenderError;
+use crate::parse;
+
+/// 只运行解析阶段，便于单独覆盖 lexer 与各条规则
+pub fn parse_only(tex: &str) -> Result<(), RenderError> {
+    let budget = RenderBudget::start(render_limits());
+    parse::parse(tex, &budget, &EquationOptions::default()).map(|_| ())
+}
@@ -2,6 +2,7 @@
 
 use fontdue::{Font, FontSettings}; // 引入 fontdue 中的字体类型与配置
 use once_cell::sync::Lazy; // 引入 Lazy，确保字体只会加载一次
+use ttf_parser::Face; // 解析 OpenType 表（MATH 常量、字形变体与轮廓）
 use usvg::fontdb::Database; // 引入字体数据库，供 usvg/resvg 使用
 
 use crate::error::RenderError; // 引入项目内自定义的错误类型
@@ -12,6 +13,7 @@ static CMATH_BYTES: &[u8] = include_bytes!("../fonts/latinmodern-math.otf"); //
 struct FontAssets {
     fontdue: Font,
     database: Database,
+    face: Face<'static>,
 }
 
 /// 用于保存懒加载后的字体对象，失败时记录错误
@@ -19,6 +21,9 @@ static FONT_ASSETS: Lazy<Result<FontAssets, RenderError>> = Lazy::new(|| {
     let font = Font::from_bytes(CMATH_BYTES, FontSettings::default())
         .map_err(|err| RenderError::FontLoadError(format!("无法解析字体: {err}")))?;
 
+    let face = Face::parse(CMATH_BYTES, 0)
+        .map_err(|err| RenderError::FontLoadError(format!("无法解析字体表: {err}")))?;
+
     let mut db = Database::new();
     db.load_font_data(CMATH_BYTES.to_vec());
     if db.is_empty() {
@@ -33,6 +38,7 @@ static FONT_ASSETS: Lazy<Result<FontAssets, RenderError>> = Lazy::new(|| {
     Ok(FontAssets {
         fontdue: font,
         database: db,
+        face,
     })
 });
 
@@ -52,6 +58,14 @@ pub fn default_font() -> Result<&'static Font, RenderError> {
     }
 }
 
+/// 提供 ttf-parser 字体对象，供读取 MATH 表与字形轮廓
+pub fn math_face() -> Result<&'static Face<'static>, RenderError> {
+    match &*FONT_ASSETS {
+        Ok(assets) => Ok(&assets.face),
+        Err(err) => Err(err.clone()),
+    }
+}
+
 /// 提供给 usvg/resvg 使用的字体数据库
 pub fn font_database() -> Result<&'static Database, RenderError> {
     match &*FONT_ASSETS {
@@ -1,13 +1,17 @@
 //! 排版模块：将语法树转换为可直接绘制的布局数据
 
 use crate::ast::{
-    AstNode, DecorationKind, Delimiter, LargeOperatorNode, ParsedFormula, SpecialSymbol,
+    ArrayNode, AstNode, BoxAdjust, CdArrowNode, ColumnAlign, DecorationKind, Delimiter,
+    EnclosureKind, EnclosureNode, FractionAlign, FractionNode, LargeOperatorNode, MathStyle,
+    ParsedFormula, RuleNode, SizedDelimiterNode, SpecialSymbol,
 };
+use crate::config::{EquationOptions, RenderBudget, TagSide}; // 单次渲染的工作预算与编号设置
 use crate::error::RenderError; // 引入统一错误类型
 use crate::init; // 字体初始化模块 // 引入语法树结构
+use crate::math_table::{self, Axis}; // MATH 表排版常量与伸缩字形
 
 use fontdue::{Font, Metrics as GlyphMetrics}; // 用于访问字体度量及字形指标
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
 use std::collections::HashMap;
 use std::thread_local;
 
@@ -55,14 +59,44 @@ pub struct LayoutPlan {
 }
 
 /// 对外入口：将解析结果转换为布局信息
-pub fn layout(parsed: &ParsedFormula) -> Result<LayoutPlan, RenderError> {
+/// `tags` 为结算后每行显示的编号，与 `parsed.tags` 一一对应
+pub(crate) fn layout(
+    parsed: &ParsedFormula,
+    tags: &[Option<String>],
+    options: &EquationOptions,
+    budget: &RenderBudget,
+) -> Result<LayoutPlan, RenderError> {
     let font = init::default_font()?; // 先确保字体加载成功
+    LAYOUT_BUDGET.with(|slot| slot.set(Some(LayoutBudget::new(budget))));
     let font_family = init::default_font_family();
     let base_font_size = init::default_font_size();
-
-    let root_box = layout_node(&parsed.ast, base_font_size, &font)?; // 递归生成布局盒
     let padding = base_font_size * 0.2; // 留出一定的边距，避免字符被裁剪
 
+    let root_box = match &parsed.ast {
+        // 顶层编号环境：每行编号与该行基线对齐
+        AstNode::Array(array)
+            if parsed.numcases.is_none() && !tags.is_empty() && tags.len() == array.rows.len() =>
+        {
+            charge_layout_box()?;
+            let (array_box, row_baselines) = layout_array_rows(array, base_font_size, font)?;
+            check_emitted_items(&array_box)?;
+            let rows = tags.iter().zip(row_baselines).collect::<Vec<_>>();
+            place_tags(array_box, &rows, options, padding, base_font_size, font)?
+        }
+        ast => {
+            let body = layout_node(ast, base_font_size, font)?; // 递归生成布局盒
+            let (body, baselines) = match &parsed.numcases {
+                Some(cases) => append_numcases(body, cases, base_font_size, font)?,
+                None => {
+                    let baselines = vec![body.baseline; tags.len()];
+                    (body, baselines)
+                }
+            };
+            let rows = tags.iter().zip(baselines).collect::<Vec<_>>();
+            place_tags(body, &rows, options, padding, base_font_size, font)?
+        }
+    };
+
     let mut items = root_box.items;
     offset_items(&mut items, padding, padding); // 整体平移，保证四周留白
 
@@ -82,6 +116,40 @@ pub fn layout(parsed: &ParsedFormula) -> Result<LayoutPlan, RenderError> {
     })
 }
 
+/// numcases：在左侧公式之后接上左花括号与表格，返回整体盒子与表格各行的基线；
+/// 定界符与横向拼接都保持基线对齐，各行基线按表格内的偏移换算到整体
+fn append_numcases(
+    lhs: LayoutBox,
+    cases: &ArrayNode,
+    font_size: f32,
+    font: &Font,
+) -> Result<(LayoutBox, Vec<f32>), RenderError> {
+    charge_layout_box()?;
+    let (array_box, row_baselines) = layout_array_rows(cases, font_size, font)?;
+    check_emitted_items(&array_box)?;
+    let array_baseline = array_box.baseline;
+    let target = array_box.height;
+    charge_layout_box()?;
+    let brace = Delimiter {
+        glyph: Some("{".into()),
+    };
+    let cases_box = wrap_with_delimiters(
+        &brace,
+        array_box,
+        target,
+        &Delimiter { glyph: None },
+        font_size,
+        font,
+    );
+    check_emitted_items(&cases_box)?;
+    let body = hstack_boxes(vec![lhs, cases_box], font_size * 0.1);
+    let baselines = row_baselines
+        .into_iter()
+        .map(|row| body.baseline + row - array_baseline)
+        .collect();
+    Ok((body, baselines))
+}
+
 /// 布局盒：中间计算过程中使用，包含尺寸和元素集合
 #[derive(Debug, Clone)]
 struct LayoutBox {
@@ -101,22 +169,32 @@ enum ScriptPolicy {
     Right,
     /// 用于求和、积分等大型算符，脚本需要居中放在上方/下方
     AboveBelow,
+    /// 用于横向花括号等伸缩装饰，脚本作为注释按墨迹间距居中堆叠
+    Stacked,
 }
 
 fn layout_node(node: &AstNode, font_size: f32, font: &Font) -> Result<LayoutBox, RenderError> {
+    charge_layout_box()?;
+    let laid_out = layout_node_inner(node, font_size, font)?;
+    check_emitted_items(&laid_out)?;
+    Ok(laid_out)
+}
+
+fn layout_node_inner(
+    node: &AstNode,
+    font_size: f32,
+    font: &Font,
+) -> Result<LayoutBox, RenderError> {
     match node {
         AstNode::Text(content) => layout_text(content, font_size, font),
         AstNode::Group(children) => layout_group(children, font_size, font),
-        AstNode::Fraction {
-            numerator,
-            denominator,
-        } => layout_fraction(numerator, denominator, font_size, font),
-        AstNode::Sqrt { value } => layout_sqrt(value, font_size, font),
+        AstNode::Fraction(fraction) => layout_fraction(fraction, font_size, font),
+        AstNode::Sqrt { value, index } => layout_sqrt(value, index.as_deref(), font_size, font),
         AstNode::Delimited { left, inner, right } => {
             layout_delimited(left, inner, right, font_size, font)
         }
         AstNode::LargeOperator(node) => layout_large_operator(node, font_size, font),
-        AstNode::Matrix(rows) => layout_matrix(rows, font_size, font),
+        AstNode::Array(array) => layout_array(array, font_size, font),
         AstNode::Decorated { base, decoration } => {
             layout_decorated(base, *decoration, font_size, font)
         }
@@ -131,7 +209,20 @@ fn layout_node(node: &AstNode, font_size: f32, font: &Font) -> Result<LayoutBox,
             font_size,
             font,
         ),
+        AstNode::UnderOver { base, over, under } => {
+            layout_under_over(base, over.as_deref(), under.as_deref(), font_size, font)
+        }
         AstNode::Symbol(symbol) => layout_symbol(*symbol, font_size, font),
+        AstNode::Middle(delimiter) => Ok(match delimiter.glyph.as_ref() {
+            Some(glyph) => make_delimiter_box(glyph, 0.0, font_size, font),
+            None => layout_text("", font_size, font)?,
+        }),
+        AstNode::SizedDelimiter(node) => layout_sized_delimiter(node, font_size, font),
+        AstNode::CdArrow(arrow) => layout_cd_arrow(arrow, 0.0, font_size, font),
+        AstNode::Enclosed(enclosure) => layout_enclosed(enclosure, font_size, font),
+        AstNode::Adjusted { body, adjust } => layout_adjusted(body, *adjust, font_size, font),
+        AstNode::Kern(width) => Ok(empty_box(width * font_size, 0.0, 0.0)),
+        AstNode::Rule(rule) => Ok(layout_rule(rule, font_size)),
     }
 }
 
@@ -233,7 +324,35 @@ fn layout_delimited(
     font_size: f32,
     font: &Font,
 ) -> Result<LayoutBox, RenderError> {
-    let inner_box = layout_node(inner, font_size, font)?;
+    let (inner_box, delimiter_target) = match inner {
+        AstNode::Group(children) if children.iter().any(|c| matches!(c, AstNode::Middle(_))) => {
+            layout_with_middles(children, font_size, font)?
+        }
+        _ => {
+            let inner_box = layout_node(inner, font_size, font)?;
+            let target = inner_box.height;
+            (inner_box, target)
+        }
+    };
+    Ok(wrap_with_delimiters(
+        left,
+        inner_box,
+        delimiter_target,
+        right,
+        font_size,
+        font,
+    ))
+}
+
+/// 在已排好的内部盒子两侧放置按 `delimiter_target` 高度放大的定界符
+fn wrap_with_delimiters(
+    left: &Delimiter,
+    inner_box: LayoutBox,
+    delimiter_target: f32,
+    right: &Delimiter,
+    font_size: f32,
+    font: &Font,
+) -> LayoutBox {
     let LayoutBox {
         width: inner_width,
         height: inner_height,
@@ -251,7 +370,7 @@ fn layout_delimited(
     let left_box = left
         .glyph
         .as_ref()
-        .map(|glyph| make_delimiter_box(glyph, inner_height, font_size, font));
+        .map(|glyph| make_delimiter_box(glyph, delimiter_target, font_size, font));
     if let Some(ref lb) = left_box {
         max_above = max_above.max(lb.baseline);
         max_below = max_below.max(lb.height - lb.baseline);
@@ -260,7 +379,7 @@ fn layout_delimited(
     let right_box = right
         .glyph
         .as_ref()
-        .map(|glyph| make_delimiter_box(glyph, inner_height, font_size, font));
+        .map(|glyph| make_delimiter_box(glyph, delimiter_target, font_size, font));
     if let Some(ref rb) = right_box {
         max_above = max_above.max(rb.baseline);
         max_below = max_below.max(rb.height - rb.baseline);
@@ -338,7 +457,7 @@ fn layout_delimited(
         cursor_x += rb.width;
     }
 
-    Ok(LayoutBox {
+    LayoutBox {
         width: cursor_x,
         height: total_height,
         baseline,
@@ -347,7 +466,7 @@ fn layout_delimited(
         items,
         lines,
         paths,
-    })
+    }
 }
 
 fn layout_group(
@@ -358,33 +477,85 @@ fn layout_group(
     if children.is_empty() {
         return layout_text("", font_size, font);
     }
+    let spacing = font_size * 0.1;
     let mut entries = Vec::with_capacity(children.len());
+    // 显式间距只作为水平偏移累加，并取代相邻盒子之间的默认间距
+    let mut kern = 0.0f32;
+    let mut gap = 0.0f32;
+    for child in children {
+        if let AstNode::Kern(width) = child {
+            kern += width * font_size;
+            gap = 0.0;
+            continue;
+        }
+        let child_box = layout_node(child, font_size, font)?;
+        if is_strut(child) {
+            entries.push((child_box, kern));
+        } else {
+            entries.push((child_box, gap + kern));
+            gap = spacing;
+        }
+        kern = 0.0;
+    }
+    Ok(hstack_with_offsets(entries, kern))
+}
+
+/// `\vphantom`、`\mathstrut` 这类零宽支柱只撑高度，不参与默认的水平间距
+fn is_strut(node: &AstNode) -> bool {
+    matches!(
+        node,
+        AstNode::Adjusted {
+            adjust: BoxAdjust::Phantom { width: false, .. },
+            ..
+        }
+    )
+}
+
+/// 按基线横向拼接盒子，相邻盒子之间留 `spacing` 间距
+fn hstack_boxes(boxes: Vec<LayoutBox>, spacing: f32) -> LayoutBox {
+    let entries = boxes
+        .into_iter()
+        .enumerate()
+        .map(|(index, child_box)| (child_box, if index == 0 { 0.0 } else { spacing }))
+        .collect();
+    hstack_with_offsets(entries, 0.0)
+}
+
+/// 按基线横向拼接盒子，每个盒子左侧先留出各自的偏移（可为负），末尾再留 `trailing`；
+/// 负偏移使内容越过左边缘时整体右移，宽度至少覆盖最右侧盒子的右边缘，保证墨迹都在盒子内
+fn hstack_with_offsets(boxes: Vec<(LayoutBox, f32)>, trailing: f32) -> LayoutBox {
+    let mut entries = Vec::with_capacity(boxes.len());
     let mut cursor_x = 0.0f32;
-    let spacing = font_size * 0.1;
 
     let mut max_above = 0.0f32;
     let mut max_below = 0.0f32;
 
-    for (index, child) in children.iter().enumerate() {
-        let child_box = layout_node(child, font_size, font)?;
-        let offset_x = if index == 0 { 0.0 } else { spacing };
+    for (child_box, offset_x) in boxes {
         cursor_x += offset_x;
         max_above = max_above.max(child_box.baseline);
         max_below = max_below.max(child_box.height - child_box.baseline);
+        let width = child_box.width;
         entries.push((child_box, cursor_x));
-        cursor_x += entries.last().unwrap().0.width;
+        cursor_x += width;
     }
 
     let baseline = max_above;
     let height = max_above + max_below;
-    let width = cursor_x;
-
-    let mut items = Vec::with_capacity(children.len());
-    let mut lines = Vec::with_capacity(children.len());
-    let mut paths = Vec::with_capacity(children.len());
+    let left = entries.iter().fold(0.0f32, |acc, (_, x)| acc.min(*x));
+    let right = entries
+        .iter()
+        .fold(cursor_x + trailing, |acc, (child_box, x)| {
+            acc.max(x + child_box.width)
+        });
+    let width = right - left;
+
+    let mut items = Vec::with_capacity(entries.len());
+    let mut lines = Vec::with_capacity(entries.len());
+    let mut paths = Vec::with_capacity(entries.len());
     let mut trailing_italic = 0.0f32;
     for (child_box, x) in entries {
         trailing_italic = child_box.italic_correction;
+        let x = x - left;
         items.extend(offset_items_owned(
             child_box.items,
             x,
@@ -401,8 +572,11 @@ fn layout_group(
             baseline - child_box.baseline,
         ));
     }
+    if trailing != 0.0 {
+        trailing_italic = 0.0;
+    }
 
-    Ok(LayoutBox {
+    LayoutBox {
         width,
         height,
         baseline,
@@ -411,160 +585,313 @@ fn layout_group(
         items,
         lines,
         paths,
-    })
+    }
 }
 
-fn layout_matrix(
-    rows: &[Vec<AstNode>],
+/// 布局含 `\middle` 的定界符内部：先排其余子节点得到整体高度，
+/// 再让每个 `\middle` 定界符伸展到该高度；返回值附带该高度供外层 `\left`/`\right` 共用
+fn layout_with_middles(
+    children: &[AstNode],
     font_size: f32,
     font: &Font,
-) -> Result<LayoutBox, RenderError> {
-    if rows.is_empty() {
-        return layout_text("", font_size, font);
+) -> Result<(LayoutBox, f32), RenderError> {
+    let mut laid_out = Vec::with_capacity(children.len());
+    let mut max_above = 0.0f32;
+    let mut max_below = 0.0f32;
+    for child in children {
+        if matches!(child, AstNode::Middle(_)) {
+            laid_out.push(None);
+            continue;
+        }
+        let child_box = layout_node(child, font_size, font)?;
+        max_above = max_above.max(child_box.baseline);
+        max_below = max_below.max(child_box.height - child_box.baseline);
+        laid_out.push(Some(child_box));
     }
-    let col_count = rows.iter().map(|r| r.len()).max().unwrap_or(0);
-    if col_count == 0 {
-        return layout_text("", font_size, font);
+    let target_height = max_above + max_below;
+
+    let mut boxes = Vec::with_capacity(children.len());
+    for (child, child_box) in children.iter().zip(laid_out) {
+        let child_box = match (child, child_box) {
+            (_, Some(child_box)) => child_box,
+            (AstNode::Middle(delimiter), None) => match delimiter.glyph.as_ref() {
+                Some(glyph) => make_delimiter_box(glyph, target_height, font_size, font),
+                None => layout_text("", font_size, font)?,
+            },
+            (_, None) => unreachable!("只有 \\middle 节点会延后布局"),
+        };
+        boxes.push(child_box);
     }
+    Ok((hstack_boxes(boxes, font_size * 0.1), target_height))
+}
+
+/// `array` 表格：按列格式对齐单元格，行高取 LaTeX 的支柱高度（随 `\arraystretch` 缩放），
+/// 在列边界画竖线、插入 `@{}` 内容，在行边界画 `\hline`/`\cline`；整体以数学轴居中
+fn layout_array(array: &ArrayNode, font_size: f32, font: &Font) -> Result<LayoutBox, RenderError> {
+    layout_array_rows(array, font_size, font).map(|(array_box, _)| array_box)
+}
 
-    let mut cell_boxes: Vec<Vec<LayoutBox>> = Vec::with_capacity(rows.len());
-    for row in rows {
+/// 排版表格，同时返回各行基线相对表格顶部的位置
+fn layout_array_rows(
+    array: &ArrayNode,
+    font_size: f32,
+    font: &Font,
+) -> Result<(LayoutBox, Vec<f32>), RenderError> {
+    let column_count = array.columns.len();
+    // smallmatrix 等以脚标字号排版单元格，列间距与行距随之收紧
+    let constants = math_table::constants();
+    let cell_size = match array.cell_style {
+        Some(MathStyle::Script) => font_size * constants.script_scale,
+        Some(MathStyle::ScriptScript) => font_size * constants.script_script_scale,
+        _ => font_size,
+    };
+    let padding = cell_size * array.column_padding;
+    let rule = (font_size * 0.04).max(1.0);
+    let double_rule_gap = font_size * 0.2;
+    // 支柱：LaTeX 行距 1.2em 按 7:3 分配到基线上下
+    let strut_above = cell_size * 0.84 * array.row_stretch;
+    let strut_below = cell_size * 0.36 * array.row_stretch;
+
+    let mut cell_boxes: Vec<Vec<LayoutBox>> = Vec::with_capacity(array.rows.len());
+    let mut column_widths = vec![0.0f32; column_count];
+    for row in &array.rows {
         let mut row_boxes = Vec::with_capacity(row.len());
-        for cell in row {
-            row_boxes.push(layout_node(cell, font_size, font)?);
+        for (cell, width) in row.iter().zip(column_widths.iter_mut()) {
+            let cell_box = layout_node(cell, cell_size, font)?;
+            *width = width.max(cell_box.width);
+            row_boxes.push(cell_box);
         }
         cell_boxes.push(row_boxes);
     }
-
-    let mut column_widths = vec![0.0f32; col_count];
-    for row_boxes in &cell_boxes {
-        for col_idx in 0..col_count {
-            if let Some(cell) = row_boxes.get(col_idx) {
-                column_widths[col_idx] = column_widths[col_idx].max(cell.width);
+    // 交换图的横向箭头伸缩到整列宽度
+    for (row, row_boxes) in array.rows.iter().zip(cell_boxes.iter_mut()) {
+        for ((cell, cell_box), &width) in row.iter().zip(row_boxes.iter_mut()).zip(&column_widths) {
+            if let AstNode::CdArrow(arrow) = cell {
+                if !arrow.vertical && cell_box.width < width {
+                    charge_layout_box()?;
+                    *cell_box = layout_cd_arrow(arrow, width, cell_size, font)?;
+                    check_emitted_items(cell_box)?;
+                }
             }
         }
     }
-
-    let col_gap = font_size * 0.4;
-    let row_gap = font_size * 0.35;
-    let cell_padding = font_size * 0.25;
-
-    let mut row_metrics = Vec::with_capacity(cell_boxes.len());
-    for row_boxes in &cell_boxes {
-        let mut max_above = 0.0f32;
-        let mut max_below = 0.0f32;
-        for cell in row_boxes {
-            max_above = max_above.max(cell.baseline);
-            max_below = max_below.max(cell.height - cell.baseline);
-        }
-        row_metrics.push((max_above, max_below));
-    }
-
-    let mut col_offsets = Vec::with_capacity(col_count);
+    let custom_boxes = array
+        .column_separators
+        .iter()
+        .map(|separator| {
+            separator
+                .custom
+                .as_ref()
+                .map(|node| layout_node(node, cell_size, font))
+                .transpose()
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    // 横向：列边界（竖线与 @ 内容）与列交替排列，@ 内容取代相邻一侧的列间距
+    let mut rule_xs = Vec::new();
+    let mut custom_xs = Vec::with_capacity(custom_boxes.len());
+    let mut column_edges = Vec::with_capacity(column_count);
+    let mut column_xs = Vec::with_capacity(column_count);
     let mut cursor_x = 0.0f32;
-    for (idx, width) in column_widths.iter().enumerate() {
-        col_offsets.push(cursor_x);
-        cursor_x += *width;
-        if idx + 1 < col_count {
-            cursor_x += col_gap;
+    for (index, separator) in array.column_separators.iter().enumerate() {
+        for rule_index in 0..separator.rules {
+            if rule_index > 0 {
+                cursor_x += double_rule_gap;
+            }
+            rule_xs.push(cursor_x + rule / 2.0);
+            cursor_x += rule;
         }
+        custom_xs.push(cursor_x);
+        if let Some(custom) = &custom_boxes[index] {
+            cursor_x += custom.width;
+        }
+        if index == column_count {
+            break;
+        }
+        let left_edge = cursor_x;
+        if separator.custom.is_none() {
+            cursor_x += padding;
+        }
+        column_xs.push(cursor_x);
+        cursor_x += column_widths[index];
+        if array.column_separators[index + 1].custom.is_none() {
+            cursor_x += padding;
+        }
+        column_edges.push((left_edge, cursor_x));
     }
-    let inner_width = cursor_x;
+    let total_width = cursor_x;
 
+    // 纵向：行边界（额外行距与横线）与行交替排列
     let mut items = Vec::new();
     let mut lines = Vec::new();
     let mut paths = Vec::new();
-
+    let mut row_baselines = Vec::with_capacity(array.rows.len());
     let mut cursor_y = 0.0f32;
-    for (row_idx, row_boxes) in cell_boxes.iter().enumerate() {
-        let (above, below) = row_metrics[row_idx];
-        let row_baseline = above;
-        let row_height = above + below;
-
-        for col_idx in 0..col_count {
-            if let Some(cell) = row_boxes.get(col_idx) {
-                let offset_x = col_offsets[col_idx] + (column_widths[col_idx] - cell.width) / 2.0;
-                let offset_y = cursor_y + row_baseline - cell.baseline;
-                items.extend(offset_items_owned(cell.items.clone(), offset_x, offset_y));
-                lines.extend(offset_lines_owned(cell.lines.clone(), offset_x, offset_y));
-                paths.extend(offset_paths_owned(cell.paths.clone(), offset_x, offset_y));
+    for (index, separator) in array.row_separators.iter().enumerate() {
+        cursor_y += separator.extra_space * font_size;
+        // \cline 画在同一边界的所有 \hline 之后，自成一道横线
+        let rule_count = separator.hlines + usize::from(!separator.clines.is_empty());
+        for rule_index in 0..rule_count {
+            if rule_index > 0 {
+                cursor_y += double_rule_gap;
+            }
+            let y = cursor_y + rule / 2.0;
+            let mut spans = Vec::new();
+            if rule_index < separator.hlines {
+                spans.push((0.0, total_width));
+            } else {
+                spans.extend(
+                    separator
+                        .clines
+                        .iter()
+                        .map(|&(start, end)| (column_edges[start].0, column_edges[end].1)),
+                );
             }
+            lines.extend(spans.into_iter().map(|(x1, x2)| RenderLine {
+                x1,
+                y1: y,
+                x2,
+                y2: y,
+                stroke_width: rule,
+            }));
+            cursor_y += rule;
         }
-
-        cursor_y += row_height;
-        if row_idx + 1 < cell_boxes.len() {
-            cursor_y += row_gap;
+        let Some(row_boxes) = cell_boxes.get(index) else {
+            break;
+        };
+
+        let above = row_boxes
+            .iter()
+            .chain(custom_boxes.iter().flatten())
+            .fold(strut_above, |acc, cell| acc.max(cell.baseline));
+        let below = row_boxes
+            .iter()
+            .chain(custom_boxes.iter().flatten())
+            .fold(strut_below, |acc, cell| {
+                acc.max(cell.height - cell.baseline)
+            });
+        let row_baseline = cursor_y + above;
+        row_baselines.push(row_baseline);
+        for (col_idx, cell) in row_boxes.iter().enumerate() {
+            let slack = column_widths[col_idx] - cell.width;
+            let align = array
+                .row_aligns
+                .get(index)
+                .copied()
+                .flatten()
+                .unwrap_or(array.columns[col_idx]);
+            let offset_x = column_xs[col_idx]
+                + match align {
+                    ColumnAlign::Left => 0.0,
+                    ColumnAlign::Center => slack / 2.0,
+                    ColumnAlign::Right => slack,
+                };
+            let offset_y = row_baseline - cell.baseline;
+            items.extend(offset_items_owned(cell.items.clone(), offset_x, offset_y));
+            lines.extend(offset_lines_owned(cell.lines.clone(), offset_x, offset_y));
+            paths.extend(offset_paths_owned(cell.paths.clone(), offset_x, offset_y));
         }
+        // @ 内容在每一行重复出现；`@{}` 这类空分隔只占位置，不输出元素
+        for (custom, &x) in custom_boxes.iter().zip(&custom_xs) {
+            if let Some(custom) = custom.as_ref().filter(|custom| custom.width > 0.0) {
+                let offset_y = row_baseline - custom.baseline;
+                items.extend(offset_items_owned(custom.items.clone(), x, offset_y));
+                lines.extend(offset_lines_owned(custom.lines.clone(), x, offset_y));
+                paths.extend(offset_paths_owned(custom.paths.clone(), x, offset_y));
+            }
+        }
+        cursor_y += above + below;
     }
+    let total_height = cursor_y;
+
+    lines.extend(rule_xs.into_iter().map(|x| RenderLine {
+        x1: x,
+        y1: 0.0,
+        x2: x,
+        y2: total_height,
+        stroke_width: rule,
+    }));
+
+    let axis = constants.axis_height * font_size;
+    let array_box = LayoutBox {
+        width: total_width,
+        height: total_height,
+        baseline: total_height / 2.0 + axis,
+        script_policy: ScriptPolicy::Right,
+        italic_correction: 0.0,
+        items,
+        lines,
+        paths,
+    };
+    Ok((array_box, row_baselines))
+}
 
-    let content_height = cursor_y;
-    let total_height = content_height + cell_padding * 2.0;
-    let hook_length = font_size * 0.35;
-    let bracket_stroke = (font_size * 0.06).max(1.0);
-    let side_padding = hook_length + bracket_stroke;
-    let total_width = inner_width + side_padding * 2.0;
-
-    offset_items(&mut items, side_padding, cell_padding);
-    offset_lines(&mut lines, side_padding, cell_padding);
-    offset_paths(&mut paths, side_padding, cell_padding);
-
-    // 绘制矩阵括号线条
-    let left_x = bracket_stroke / 2.0;
-    let right_x = total_width - bracket_stroke / 2.0;
-    let top_y = 0.0;
-    let bottom_y = total_height;
-
-    lines.push(RenderLine {
-        x1: left_x,
-        y1: top_y,
-        x2: left_x,
-        y2: bottom_y,
-        stroke_width: bracket_stroke,
-    });
-    lines.push(RenderLine {
-        x1: left_x,
-        y1: top_y,
-        x2: left_x + hook_length,
-        y2: top_y,
-        stroke_width: bracket_stroke,
-    });
-    lines.push(RenderLine {
-        x1: left_x,
-        y1: bottom_y,
-        x2: left_x + hook_length,
-        y2: bottom_y,
-        stroke_width: bracket_stroke,
-    });
-
-    lines.push(RenderLine {
-        x1: right_x,
-        y1: top_y,
-        x2: right_x,
-        y2: bottom_y,
-        stroke_width: bracket_stroke,
-    });
-    lines.push(RenderLine {
-        x1: right_x,
-        y1: top_y,
-        x2: right_x - hook_length,
-        y2: top_y,
-        stroke_width: bracket_stroke,
-    });
-    lines.push(RenderLine {
-        x1: right_x,
-        y1: bottom_y,
-        x2: right_x - hook_length,
-        y2: bottom_y,
-        stroke_width: bracket_stroke,
-    });
+/// 摆放公式编号：`rows` 给出每行的编号与基线位置。指定显示宽度时公式居中、编号贴齐一侧，
+/// 放不下时公式向另一侧让出位置，仍不够则加宽；未指定时编号紧随公式，相隔 1em
+fn place_tags(
+    body: LayoutBox,
+    rows: &[(&Option<String>, f32)],
+    options: &EquationOptions,
+    padding: f32,
+    font_size: f32,
+    font: &Font,
+) -> Result<LayoutBox, RenderError> {
+    let mut tags = Vec::new();
+    for (tag, baseline) in rows {
+        if let Some(text) = tag {
+            tags.push((layout_text(text, font_size, font)?, *baseline));
+        }
+    }
+    let display_width = options
+        .display_width
+        .map(|width| (width - padding * 2.0).max(0.0));
+    if tags.is_empty() && display_width.is_none() {
+        return Ok(body);
+    }
 
-    let baseline = cell_padding + content_height / 2.0;
+    let tag_width = tags.iter().fold(0.0f32, |acc, (tag, _)| acc.max(tag.width));
+    let gap = if tags.is_empty() { 0.0 } else { font_size };
+    let needed = body.width + gap + tag_width;
+    let width = display_width.unwrap_or(needed).max(needed);
+    // 以编号一侧为起点量取公式位置：居中优先，必要时向远离编号的一侧偏移
+    let centered = (width - body.width) / 2.0;
+    let body_from_tag_side = if display_width.is_some() {
+        centered.max(tag_width + gap)
+    } else {
+        tag_width + gap
+    };
+    let (body_x, tag_x) = match options.tag_side {
+        TagSide::Right => (width - body_from_tag_side - body.width, width - tag_width),
+        TagSide::Left => (body_from_tag_side, 0.0),
+    };
 
+    let top = tags
+        .iter()
+        .map(|(tag, baseline)| baseline - tag.baseline)
+        .fold(0.0f32, f32::min);
+    let bottom = tags
+        .iter()
+        .map(|(tag, baseline)| baseline - tag.baseline + tag.height)
+        .fold(body.height, f32::max);
+    let mut items = offset_items_owned(body.items, body_x, -top);
+    let mut lines = offset_lines_owned(body.lines, body_x, -top);
+    let mut paths = offset_paths_owned(body.paths, body_x, -top);
+    for (tag, baseline) in tags {
+        // 右侧编号右对齐，左侧编号左对齐
+        let x = match options.tag_side {
+            TagSide::Right => tag_x + tag_width - tag.width,
+            TagSide::Left => tag_x,
+        };
+        let y = baseline - tag.baseline - top;
+        items.extend(offset_items_owned(tag.items, x, y));
+        lines.extend(offset_lines_owned(tag.lines, x, y));
+        paths.extend(offset_paths_owned(tag.paths, x, y));
+    }
     Ok(LayoutBox {
-        width: total_width,
-        height: total_height,
-        baseline,
-        script_policy: ScriptPolicy::Right,
+        width,
+        height: bottom - top,
+        baseline: body.baseline - top,
+        script_policy: body.script_policy,
         italic_correction: 0.0,
         items,
         lines,
@@ -573,31 +900,47 @@ fn layout_matrix(
 }
 
 fn layout_fraction(
-    numerator: &AstNode,
-    denominator: &AstNode,
+    fraction: &FractionNode,
     font_size: f32,
     font: &Font,
 ) -> Result<LayoutBox, RenderError> {
-    let num_box = layout_node(numerator, font_size, font)?;
-    let den_box = layout_node(denominator, font_size, font)?;
-
-    let padding = font_size * 0.25;
-    let gap = font_size * 0.2;
-    let line_thickness = (font_size * 0.07).max(1.0);
+    let constants = math_table::constants();
+    // 文本样式分数的分子分母取脚标字号，脚标样式再缩一级
+    let part_size = match fraction.style {
+        None | Some(MathStyle::Display) => font_size,
+        Some(MathStyle::Text) => font_size * constants.script_scale,
+        Some(MathStyle::Script | MathStyle::ScriptScript) => {
+            font_size * constants.script_script_scale
+        }
+    };
+    let num_box = layout_node(&fraction.numerator, part_size, font)?;
+    let den_box = layout_node(&fraction.denominator, part_size, font)?;
+
+    let padding = part_size * 0.25;
+    let gap = part_size * 0.2;
+    let line_thickness = match fraction.rule_thickness {
+        None => (font_size * 0.07).max(1.0),
+        Some(thickness) => thickness * font_size,
+    };
 
     let inner_width = num_box.width.max(den_box.width);
     let total_width = inner_width + padding * 2.0;
 
-    let numerator_dx = padding + (inner_width - num_box.width) / 2.0;
+    let numerator_dx = padding
+        + match fraction.align {
+            FractionAlign::Center => (inner_width - num_box.width) / 2.0,
+            FractionAlign::Left => 0.0,
+            FractionAlign::Right => inner_width - num_box.width,
+        };
     let denominator_dx = padding + (inner_width - den_box.width) / 2.0;
 
     let numerator_top = padding;
     let line_y = numerator_top + num_box.height + gap;
     let denominator_top = line_y + line_thickness + gap;
-    let denominator_baseline_y = denominator_top + den_box.baseline;
 
     let total_height = denominator_top + den_box.height + padding;
-    let baseline = denominator_baseline_y;
+    // 分数线中心落在数学轴上，使分数与两侧内容（含连分数的外层）对齐
+    let baseline = line_y + line_thickness / 2.0 + constants.axis_height * font_size;
 
     let mut items = Vec::new();
     items.extend(offset_items_owned(
@@ -633,15 +976,18 @@ fn layout_fraction(
         denominator_dx,
         denominator_top,
     ));
-    lines.push(RenderLine {
-        x1: padding,
-        y1: line_y + line_thickness / 2.0,
-        x2: total_width - padding,
-        y2: line_y + line_thickness / 2.0,
-        stroke_width: line_thickness,
-    });
+    // 线宽为 0（如 \binom、\atop）时只保留间距，不画分数线
+    if line_thickness > 0.0 {
+        lines.push(RenderLine {
+            x1: padding,
+            y1: line_y + line_thickness / 2.0,
+            x2: total_width - padding,
+            y2: line_y + line_thickness / 2.0,
+            stroke_width: line_thickness,
+        });
+    }
 
-    Ok(LayoutBox {
+    let fraction_box = LayoutBox {
         width: total_width,
         height: total_height,
         baseline,
@@ -650,11 +996,122 @@ fn layout_fraction(
         items,
         lines,
         paths,
-    })
+    };
+    if fraction.left.glyph.is_none() && fraction.right.glyph.is_none() {
+        return Ok(fraction_box);
+    }
+    let target = fraction_box.height;
+    Ok(wrap_with_delimiters(
+        &fraction.left,
+        fraction_box,
+        target,
+        &fraction.right,
+        font_size,
+        font,
+    ))
 }
 
-fn layout_sqrt(value: &AstNode, font_size: f32, font: &Font) -> Result<LayoutBox, RenderError> {
+/// 根号：按 MATH 表的根号变体（不足时拼装）伸展到覆盖被开方式，
+/// 横线从字形顶端的连接处延伸到被开方式末尾；根指数以二级脚标字号放在弯折处
+fn layout_sqrt(
+    value: &AstNode,
+    index: Option<&AstNode>,
+    font_size: f32,
+    font: &Font,
+) -> Result<LayoutBox, RenderError> {
+    let constants = math_table::constants();
     let inner_box = layout_node(value, font_size, font)?;
+    let degree_box = index
+        .map(|node| layout_node(node, font_size * constants.script_script_scale, font))
+        .transpose()?;
+
+    let rule = constants.radical_rule_thickness * font_size;
+    let mut gap = constants.radical_vertical_gap * font_size;
+    let (ink_top, ink_bottom) = ink_extent(&inner_box, font);
+    let needed = ink_bottom - ink_top + gap + rule;
+    let Some(glyph) = math_table::stretch_glyph('√', Axis::Vertical, needed, font_size) else {
+        let radical = layout_text_radical(inner_box, font_size);
+        return Ok(match degree_box {
+            Some(degree) => hstack_boxes(vec![degree, radical], 0.0),
+            None => radical,
+        });
+    };
+    // 字形比所需更高时，多出的部分平分到横线上方与下方，被开方式保持居中
+    let glyph_height = glyph.ascent + glyph.descent;
+    if glyph_height > needed {
+        gap += (glyph_height - needed) / 2.0;
+    }
+
+    // 以被开方式盒子顶端为纵坐标原点
+    let bar_top = ink_top - gap - rule;
+    let glyph_bottom = bar_top + glyph_height;
+    let mut top = (bar_top - constants.radical_extra_ascender * font_size).min(0.0);
+    let mut bottom = glyph_bottom.max(inner_box.height);
+
+    let (mut degree_x, mut glyph_x) = (0.0f32, 0.0f32);
+    let mut degree_y = 0.0f32;
+    if let Some(degree) = degree_box.as_ref() {
+        let before = constants.radical_kern_before_degree * font_size;
+        let after = constants.radical_kern_after_degree * font_size;
+        // 负的右侧间距可能让根号越过盒子左边界，此时整体右移
+        let shift = (-(before + degree.width + after)).max(0.0);
+        degree_x = before + shift;
+        glyph_x = before + degree.width + after + shift;
+        let degree_baseline = glyph_bottom - constants.radical_degree_bottom_raise * glyph_height;
+        degree_y = degree_baseline - degree.baseline;
+        top = top.min(degree_y);
+        bottom = bottom.max(degree_y + degree.height);
+    }
+    let inner_x = glyph_x + glyph.width;
+    let width =
+        (inner_x + inner_box.width).max(degree_x + degree_box.as_ref().map_or(0.0, |d| d.width));
+
+    let mut items = offset_items_owned(inner_box.items, inner_x, -top);
+    let mut lines = offset_lines_owned(inner_box.lines, inner_x, -top);
+    let mut paths = offset_paths_owned(inner_box.paths, inner_x, -top);
+    if let Some(degree) = degree_box {
+        items.extend(offset_items_owned(degree.items, degree_x, degree_y - top));
+        lines.extend(offset_lines_owned(degree.lines, degree_x, degree_y - top));
+        paths.extend(offset_paths_owned(degree.paths, degree_x, degree_y - top));
+    }
+    paths.push(RenderPath {
+        d: glyph.d,
+        x: glyph_x,
+        y: bar_top + glyph.ascent - top,
+        fill: Some("#000000"),
+        stroke: Some("none"),
+        stroke_width: None,
+        stroke_linecap: None,
+        stroke_linejoin: None,
+    });
+    // 横线用填充矩形绘制，左端略微伸入字形顶端的连接段，避免接缝
+    let overlap = rule / 2.0;
+    let bar_length = inner_box.width + overlap;
+    paths.push(RenderPath {
+        d: format!("M0 0 L{bar_length:.2} 0 L{bar_length:.2} {rule:.2} L0 {rule:.2} Z"),
+        x: inner_x - overlap,
+        y: bar_top - top,
+        fill: Some("#000000"),
+        stroke: Some("none"),
+        stroke_width: None,
+        stroke_linecap: None,
+        stroke_linejoin: None,
+    });
+
+    Ok(LayoutBox {
+        width,
+        height: bottom - top,
+        baseline: inner_box.baseline - top,
+        script_policy: ScriptPolicy::Right,
+        italic_correction: 0.0,
+        items,
+        lines,
+        paths,
+    })
+}
+
+/// 字体缺少根号字形时的兜底：文本 `√` 加一条横线
+fn layout_text_radical(inner_box: LayoutBox, font_size: f32) -> LayoutBox {
     let padding = font_size * 0.15;
     let symbol_width = font_size * 0.6;
     let line_thickness = (font_size * 0.06).max(0.8);
@@ -683,7 +1140,7 @@ fn layout_sqrt(value: &AstNode, font_size: f32, font: &Font) -> Result<LayoutBox
         stroke_width: line_thickness,
     });
 
-    Ok(LayoutBox {
+    LayoutBox {
         width: total_width,
         height: total_height,
         baseline,
@@ -692,7 +1149,7 @@ fn layout_sqrt(value: &AstNode, font_size: f32, font: &Font) -> Result<LayoutBox
         items,
         lines,
         paths,
-    })
+    }
 }
 
 fn layout_scripts(
@@ -717,11 +1174,126 @@ fn layout_scripts(
     let rendered = match base_box.script_policy {
         ScriptPolicy::Right => layout_scripts_right(base_box, sup_box, sub_box, font_size),
         ScriptPolicy::AboveBelow => layout_scripts_vertical(base_box, sup_box, sub_box, font_size),
+        ScriptPolicy::Stacked => {
+            let constants = math_table::constants();
+            let gaps = StackGaps {
+                over_gap: constants.stretch_stack_gap_above_min * font_size,
+                over_rise: 0.0,
+                under_gap: constants.stretch_stack_gap_below_min * font_size,
+                under_drop: 0.0,
+            };
+            stack_annotations(base_box, sup_box, sub_box, gaps, font)
+        }
     };
 
     Ok(rendered)
 }
 
+/// 在主体正上方/正下方居中堆叠脚标尺寸的注释，间距取自 MATH 表的 limit 常量
+fn layout_under_over(
+    base: &AstNode,
+    over: Option<&AstNode>,
+    under: Option<&AstNode>,
+    font_size: f32,
+    font: &Font,
+) -> Result<LayoutBox, RenderError> {
+    let constants = math_table::constants();
+    let script_font_size = font_size * constants.script_scale;
+    let base_box = layout_node(base, font_size, font)?;
+    let over_box = match over {
+        Some(node) => Some(layout_node(node, script_font_size, font)?),
+        None => None,
+    };
+    let under_box = match under {
+        Some(node) => Some(layout_node(node, script_font_size, font)?),
+        None => None,
+    };
+    let gaps = StackGaps {
+        over_gap: constants.upper_limit_gap_min * font_size,
+        over_rise: constants.upper_limit_baseline_rise_min * font_size,
+        under_gap: constants.lower_limit_gap_min * font_size,
+        under_drop: constants.lower_limit_baseline_drop_min * font_size,
+    };
+    Ok(stack_annotations(base_box, over_box, under_box, gaps, font))
+}
+
+/// 上下堆叠的间距要求（像素）
+#[derive(Debug, Clone, Copy)]
+struct StackGaps {
+    /// 上方注释墨迹底部与主体墨迹顶部的最小间距
+    over_gap: f32,
+    /// 上方注释基线高出主体墨迹顶部的最小距离
+    over_rise: f32,
+    /// 下方注释墨迹顶部与主体墨迹底部的最小间距
+    under_gap: f32,
+    /// 下方注释基线低于主体墨迹底部的最小距离
+    under_drop: f32,
+}
+
+/// 把注释居中堆叠在主体上方/下方；间距按墨迹（而非行高）计算，
+/// 避免 `\stackrel{\text{def}}{=}` 这类注释悬得过高
+fn stack_annotations(
+    base_box: LayoutBox,
+    over_box: Option<LayoutBox>,
+    under_box: Option<LayoutBox>,
+    gaps: StackGaps,
+    font: &Font,
+) -> LayoutBox {
+    let (base_ink_top, base_ink_bottom) = ink_extent(&base_box, font);
+
+    // 各部分盒子顶端相对主体盒子顶端的纵向偏移
+    let over_dy = over_box.as_ref().map(|over| {
+        let (_, over_ink_bottom) = ink_extent(over, font);
+        let rise = (gaps.over_gap + over_ink_bottom - over.baseline).max(gaps.over_rise);
+        base_ink_top - rise - over.baseline
+    });
+    let under_dy = under_box.as_ref().map(|under| {
+        let (under_ink_top, _) = ink_extent(under, font);
+        let drop = (gaps.under_gap + under.baseline - under_ink_top).max(gaps.under_drop);
+        base_ink_bottom + drop - under.baseline
+    });
+
+    let top = over_dy.unwrap_or(0.0).min(0.0);
+    let bottom = under_box
+        .as_ref()
+        .zip(under_dy)
+        .map_or(base_box.height, |(under, dy)| {
+            (dy + under.height).max(base_box.height)
+        });
+    let width = [over_box.as_ref(), under_box.as_ref()]
+        .into_iter()
+        .flatten()
+        .map(|part| part.width)
+        .fold(base_box.width, f32::max);
+
+    let mut items = Vec::new();
+    let mut lines = Vec::new();
+    let mut paths = Vec::new();
+    let base_baseline = base_box.baseline;
+    let parts = [
+        Some((base_box, 0.0)),
+        over_box.zip(over_dy),
+        under_box.zip(under_dy),
+    ];
+    for (part, dy) in parts.into_iter().flatten() {
+        let dx = (width - part.width) / 2.0;
+        items.extend(offset_items_owned(part.items, dx, dy - top));
+        lines.extend(offset_lines_owned(part.lines, dx, dy - top));
+        paths.extend(offset_paths_owned(part.paths, dx, dy - top));
+    }
+
+    LayoutBox {
+        width,
+        height: bottom - top,
+        baseline: base_baseline - top,
+        script_policy: ScriptPolicy::Right,
+        italic_correction: 0.0,
+        items,
+        lines,
+        paths,
+    }
+}
+
 fn layout_decorated(
     base: &AstNode,
     decoration: DecorationKind,
@@ -729,6 +1301,44 @@ fn layout_decorated(
     font: &Font,
 ) -> Result<LayoutBox, RenderError> {
     let base_box = layout_node(base, font_size, font)?;
+    let stretchy = match decoration {
+        DecorationKind::Overbrace => Some(('⏞', true)),
+        DecorationKind::Underbrace => Some(('⏟', false)),
+        DecorationKind::Overbracket => Some(('⎴', true)),
+        DecorationKind::Underbracket => Some(('⎵', false)),
+        DecorationKind::Overparen => Some(('⏜', true)),
+        DecorationKind::Underparen => Some(('⏝', false)),
+        _ => None,
+    };
+    if let Some((ch, above)) = stretchy {
+        if let Some(glyph) =
+            math_table::stretch_glyph(ch, Axis::Horizontal, base_box.width, font_size)
+        {
+            return Ok(attach_stretchy_glyph(
+                base_box, glyph, above, font_size, font,
+            ));
+        }
+    }
+    if let Some((ch, width, above)) = accent_spec(decoration) {
+        let glyph = match width {
+            AccentWidth::Fixed => math_table::fit_horizontal(ch, 0.0, font_size),
+            AccentWidth::Widest => math_table::fit_horizontal(ch, base_box.width, font_size),
+            AccentWidth::Stretch => {
+                math_table::stretch_glyph(ch, Axis::Horizontal, base_box.width, font_size)
+            }
+        };
+        let Some(glyph) = glyph else {
+            return Ok(base_box);
+        };
+        let base_char = match base {
+            AstNode::Text(text) if text.chars().count() == 1 => text.chars().next(),
+            _ => None,
+        };
+        return Ok(attach_accent(
+            base_box, base_char, glyph, above, font_size, font,
+        ));
+    }
+
     let LayoutBox {
         width: base_width,
         height: base_height,
@@ -743,16 +1353,15 @@ fn layout_decorated(
 
     let (padding_top, padding_bottom) = match decoration {
         DecorationKind::Overline
-        | DecorationKind::Bar
-        | DecorationKind::Hat
-        | DecorationKind::Tilde
-        | DecorationKind::Vector
-        | DecorationKind::Dot
-        | DecorationKind::Ddot
-        | DecorationKind::Overbrace => (font_size * 0.25, font_size * 0.05),
-        DecorationKind::Underline | DecorationKind::Underbrace => {
-            (font_size * 0.05, font_size * 0.25)
-        }
+        | DecorationKind::Overbrace
+        | DecorationKind::Overbracket
+        | DecorationKind::Overparen => (font_size * 0.25, font_size * 0.05),
+        DecorationKind::Underline
+        | DecorationKind::Underbrace
+        | DecorationKind::Underbracket
+        | DecorationKind::Underparen => (font_size * 0.05, font_size * 0.25),
+        // 重音类装饰由 attach_accent 处理，不会走到这里
+        _ => (0.0, 0.0),
     };
 
     let baseline = padding_top + base_baseline;
@@ -767,7 +1376,11 @@ fn layout_decorated(
     paths.extend(offset_paths_owned(base_paths, 0.0, padding_top));
 
     match decoration {
-        DecorationKind::Overline | DecorationKind::Bar | DecorationKind::Overbrace => {
+        // 字体缺少伸缩字形时，花括号类装饰退化为直线
+        DecorationKind::Overline
+        | DecorationKind::Overbrace
+        | DecorationKind::Overbracket
+        | DecorationKind::Overparen => {
             let y = (padding_top - line_thickness / 2.0).max(line_thickness / 2.0);
             lines.push(RenderLine {
                 x1: 0.0,
@@ -777,7 +1390,10 @@ fn layout_decorated(
                 stroke_width: line_thickness,
             });
         }
-        DecorationKind::Underline | DecorationKind::Underbrace => {
+        DecorationKind::Underline
+        | DecorationKind::Underbrace
+        | DecorationKind::Underbracket
+        | DecorationKind::Underparen => {
             let y = padding_top + base_height + line_thickness / 2.0;
             lines.push(RenderLine {
                 x1: 0.0,
@@ -787,81 +1403,7 @@ fn layout_decorated(
                 stroke_width: line_thickness,
             });
         }
-        DecorationKind::Hat => {
-            let hat_font_size = font_size * 0.7;
-            let (hat_ascent, _, _) = line_metrics(font, hat_font_size);
-            let hat_width = measure_text_width("^", hat_font_size, font);
-            let hat_x = (base_width - hat_width) / 2.0;
-            let hat_y = (padding_top * 0.6).max(hat_ascent);
-            items.push(RenderItem {
-                text: "^".into(),
-                x: hat_x,
-                y: hat_y,
-                font_size: hat_font_size,
-            });
-        }
-        DecorationKind::Tilde => {
-            let tilde_font_size = font_size * 0.7;
-            let (tilde_ascent, _, _) = line_metrics(font, tilde_font_size);
-            let tilde_width = measure_text_width("~", tilde_font_size, font);
-            let tilde_x = (base_width - tilde_width) / 2.0;
-            let tilde_y = (padding_top * 0.6).max(tilde_ascent);
-            items.push(RenderItem {
-                text: "~".into(),
-                x: tilde_x,
-                y: tilde_y,
-                font_size: tilde_font_size,
-            });
-        }
-        DecorationKind::Vector => {
-            let arrow_font_size = font_size * 0.7;
-            let (arrow_ascent, _, _) = line_metrics(font, arrow_font_size);
-            let arrow_text = "→";
-            let arrow_width = measure_text_width(arrow_text, arrow_font_size, font);
-            let arrow_x = (base_width - arrow_width) / 2.0;
-            let arrow_y = (padding_top * 0.6).max(arrow_ascent);
-            items.push(RenderItem {
-                text: arrow_text.into(),
-                x: arrow_x,
-                y: arrow_y,
-                font_size: arrow_font_size,
-            });
-        }
-        DecorationKind::Dot => {
-            let dot_font_size = font_size * 0.6;
-            let (dot_ascent, _, _) = line_metrics(font, dot_font_size);
-            let dot_width = measure_text_width("·", dot_font_size, font);
-            let dot_x = (base_width - dot_width) / 2.0;
-            let dot_y = (padding_top * 0.5).max(dot_ascent);
-            items.push(RenderItem {
-                text: "·".into(),
-                x: dot_x,
-                y: dot_y,
-                font_size: dot_font_size,
-            });
-        }
-        DecorationKind::Ddot => {
-            let dot_font_size = font_size * 0.55;
-            let (dot_ascent, _, _) = line_metrics(font, dot_font_size);
-            let dot_width = measure_text_width("·", dot_font_size, font);
-            let spacing = dot_width.max(font_size * 0.2);
-            let center = base_width / 2.0;
-            let dot_y = (padding_top * 0.5).max(dot_ascent);
-            let left_x = center - spacing / 2.0 - dot_width / 2.0;
-            let right_x = center + spacing / 2.0 - dot_width / 2.0;
-            items.push(RenderItem {
-                text: "·".into(),
-                x: left_x,
-                y: dot_y,
-                font_size: dot_font_size,
-            });
-            items.push(RenderItem {
-                text: "·".into(),
-                x: right_x,
-                y: dot_y,
-                font_size: dot_font_size,
-            });
-        }
+        _ => {}
     }
 
     Ok(LayoutBox {
@@ -876,6 +1418,157 @@ fn layout_decorated(
     })
 }
 
+/// 把横向伸缩字形（花括号、方括号、圆括号）贴在主体上方或下方，
+/// 间距取自 MATH 表的 stretch stack 常量；其上/下标作为标签居中堆叠
+fn attach_stretchy_glyph(
+    base_box: LayoutBox,
+    glyph: math_table::StretchedGlyph,
+    above: bool,
+    font_size: f32,
+    font: &Font,
+) -> LayoutBox {
+    let constants = math_table::constants();
+    let (base_ink_top, base_ink_bottom) = ink_extent(&base_box, font);
+    let width = base_box.width.max(glyph.width);
+
+    // 字形基线相对主体盒子顶端的位置
+    let glyph_baseline = if above {
+        base_ink_top - constants.stretch_stack_gap_below_min * font_size - glyph.descent
+    } else {
+        base_ink_bottom + constants.stretch_stack_gap_above_min * font_size + glyph.ascent
+    };
+    let top = (glyph_baseline - glyph.ascent).min(0.0);
+    let bottom = (glyph_baseline + glyph.descent).max(base_box.height);
+
+    let base_dx = (width - base_box.width) / 2.0;
+    let items = offset_items_owned(base_box.items, base_dx, -top);
+    let lines = offset_lines_owned(base_box.lines, base_dx, -top);
+    let mut paths = offset_paths_owned(base_box.paths, base_dx, -top);
+    paths.push(RenderPath {
+        d: glyph.d,
+        x: (width - glyph.width) / 2.0,
+        y: glyph_baseline - top,
+        fill: Some("#000000"),
+        stroke: Some("none"),
+        stroke_width: None,
+        stroke_linecap: None,
+        stroke_linejoin: None,
+    });
+
+    LayoutBox {
+        width,
+        height: bottom - top,
+        baseline: base_box.baseline - top,
+        script_policy: ScriptPolicy::Stacked,
+        italic_correction: 0.0,
+        items,
+        lines,
+        paths,
+    }
+}
+
+/// 重音宽度的选取方式
+#[derive(Debug, Clone, Copy)]
+enum AccentWidth {
+    /// 固定使用原字形，如 `\hat`、`\vec`
+    Fixed,
+    /// 选取不超过主体宽度的最宽变体，如 `\widehat`
+    Widest,
+    /// 伸缩或拼装到覆盖主体宽度，如 `\overrightarrow`
+    Stretch,
+}
+
+/// 重音类装饰对应的组合字形、宽度规则以及是否位于主体上方
+fn accent_spec(decoration: DecorationKind) -> Option<(char, AccentWidth, bool)> {
+    let spec = match decoration {
+        DecorationKind::Hat => ('\u{302}', AccentWidth::Fixed, true),
+        DecorationKind::Check => ('\u{30C}', AccentWidth::Fixed, true),
+        DecorationKind::Tilde => ('\u{303}', AccentWidth::Fixed, true),
+        DecorationKind::Acute => ('\u{301}', AccentWidth::Fixed, true),
+        DecorationKind::Grave => ('\u{300}', AccentWidth::Fixed, true),
+        DecorationKind::Breve => ('\u{306}', AccentWidth::Fixed, true),
+        DecorationKind::Bar => ('\u{304}', AccentWidth::Fixed, true),
+        DecorationKind::Dot => ('\u{307}', AccentWidth::Fixed, true),
+        DecorationKind::Ddot => ('\u{308}', AccentWidth::Fixed, true),
+        DecorationKind::Dddot => ('\u{20DB}', AccentWidth::Fixed, true),
+        DecorationKind::Ddddot => ('\u{20DC}', AccentWidth::Fixed, true),
+        DecorationKind::MathRing => ('\u{30A}', AccentWidth::Fixed, true),
+        DecorationKind::Vector => ('\u{20D7}', AccentWidth::Fixed, true),
+        DecorationKind::WideHat => ('\u{302}', AccentWidth::Widest, true),
+        DecorationKind::WideTilde => ('\u{303}', AccentWidth::Widest, true),
+        DecorationKind::WideCheck => ('\u{30C}', AccentWidth::Widest, true),
+        DecorationKind::OverRightArrow => ('\u{20D7}', AccentWidth::Stretch, true),
+        DecorationKind::OverLeftArrow => ('\u{20D6}', AccentWidth::Stretch, true),
+        DecorationKind::OverLeftRightArrow => ('\u{20E1}', AccentWidth::Stretch, true),
+        DecorationKind::OverLeftHarpoon => ('\u{20D0}', AccentWidth::Stretch, true),
+        DecorationKind::OverRightHarpoon => ('\u{20D1}', AccentWidth::Stretch, true),
+        DecorationKind::UnderRightArrow => ('\u{20EF}', AccentWidth::Stretch, false),
+        DecorationKind::UnderLeftArrow => ('\u{20EE}', AccentWidth::Stretch, false),
+        DecorationKind::UnderLeftRightArrow => ('\u{34D}', AccentWidth::Stretch, false),
+        DecorationKind::UnderTilde => ('\u{330}', AccentWidth::Widest, false),
+        _ => return None,
+    };
+    Some(spec)
+}
+
+/// 放置重音字形：单个字符的主体按 MATH 表的顶部重音附着点对齐，其余居中；
+/// 主体高于 AccentBaseHeight 时上方重音随之上移，下方重音则避开主体的下伸部分
+fn attach_accent(
+    base_box: LayoutBox,
+    base_char: Option<char>,
+    glyph: math_table::StretchedGlyph,
+    above: bool,
+    font_size: f32,
+    font: &Font,
+) -> LayoutBox {
+    let (base_ink_top, base_ink_bottom) = ink_extent(&base_box, font);
+    let glyph_baseline = if above {
+        let accent_base_height = math_table::constants().accent_base_height * font_size;
+        base_box.baseline - (base_box.baseline - base_ink_top - accent_base_height).max(0.0)
+    } else {
+        base_box.baseline + (base_ink_bottom - base_box.baseline).max(0.0)
+    };
+
+    let base_attachment = base_char
+        .filter(|_| above)
+        .and_then(|ch| math_table::top_accent_attachment(ch, font_size));
+    let glyph_x = match (base_attachment, glyph.top_accent) {
+        (Some(base_point), Some(accent_point)) => base_point - accent_point,
+        _ => (base_box.width - glyph.width) / 2.0,
+    };
+
+    // 重音可能比主体更宽，整体平移以保证不越过盒子左边界
+    let left = glyph_x.min(0.0);
+    let right = (glyph_x + glyph.width).max(base_box.width);
+    let top = (glyph_baseline - glyph.ascent).min(0.0);
+    let bottom = (glyph_baseline + glyph.descent).max(base_box.height);
+
+    let items = offset_items_owned(base_box.items, -left, -top);
+    let lines = offset_lines_owned(base_box.lines, -left, -top);
+    let mut paths = offset_paths_owned(base_box.paths, -left, -top);
+    paths.push(RenderPath {
+        d: glyph.d,
+        x: glyph_x - left,
+        y: glyph_baseline - top,
+        fill: Some("#000000"),
+        stroke: Some("none"),
+        stroke_width: None,
+        stroke_linecap: None,
+        stroke_linejoin: None,
+    });
+
+    LayoutBox {
+        width: right - left,
+        height: bottom - top,
+        baseline: base_box.baseline - top,
+        script_policy: base_box.script_policy,
+        italic_correction: base_box.italic_correction,
+        items,
+        lines,
+        paths,
+    }
+}
+
 fn layout_scripts_right(
     base_box: LayoutBox,
     mut sup_box: Option<LayoutBox>,
@@ -1063,6 +1756,483 @@ fn line_metrics(font: &Font, font_size: f32) -> (f32, f32, f32) {
     }
 }
 
+/// amscd 的 `\minCDarrowwidth`：横向箭头至少 2.5pc，约 3em
+const CD_ARROW_MIN_WIDTH: f32 = 3.0;
+/// 纵向箭头的最小长度（以字号为单位）
+const CD_ARROW_MIN_HEIGHT: f32 = 2.0;
+
+/// 交换图箭头：横向箭头伸缩到至少 `width` 像素并覆盖上下标签，标签按 `\xrightarrow` 的间距堆叠；
+/// 纵向箭头以数学轴为中心，标签以脚标字号垂直居中摆在左右两侧
+fn layout_cd_arrow(
+    arrow: &CdArrowNode,
+    width: f32,
+    font_size: f32,
+    font: &Font,
+) -> Result<LayoutBox, RenderError> {
+    let constants = math_table::constants();
+    let label_size = font_size * constants.script_scale;
+    let before = match arrow.before.as_deref() {
+        Some(node) => Some(layout_node(node, label_size, font)?),
+        None => None,
+    };
+    let after = match arrow.after.as_deref() {
+        Some(node) => Some(layout_node(node, label_size, font)?),
+        None => None,
+    };
+    if arrow.vertical {
+        return layout_vertical_cd_arrow(arrow.glyph, before, after, font_size, font);
+    }
+
+    // 标签两侧各留 0.5em，箭头不短于标签
+    let label_width = [before.as_ref(), after.as_ref()]
+        .into_iter()
+        .flatten()
+        .map(|label| label.width + font_size)
+        .fold(0.0, f32::max);
+    let target = width.max(CD_ARROW_MIN_WIDTH * font_size).max(label_width);
+    let base_box = match math_table::stretch_glyph(arrow.glyph, Axis::Horizontal, target, font_size)
+    {
+        Some(glyph) => LayoutBox {
+            width: target.max(glyph.width),
+            height: glyph.ascent + glyph.descent,
+            baseline: glyph.ascent,
+            script_policy: ScriptPolicy::Stacked,
+            italic_correction: 0.0,
+            items: Vec::new(),
+            lines: Vec::new(),
+            paths: vec![RenderPath {
+                d: glyph.d,
+                x: (target - glyph.width).max(0.0) / 2.0,
+                y: glyph.ascent,
+                fill: Some("#000000"),
+                stroke: Some("none"),
+                stroke_width: None,
+                stroke_linecap: None,
+                stroke_linejoin: None,
+            }],
+        },
+        None => layout_text(&arrow.glyph.to_string(), font_size, font)?,
+    };
+    let gaps = StackGaps {
+        over_gap: constants.upper_limit_gap_min * font_size,
+        over_rise: constants.upper_limit_baseline_rise_min * font_size,
+        under_gap: constants.lower_limit_gap_min * font_size,
+        under_drop: constants.lower_limit_baseline_drop_min * font_size,
+    };
+    Ok(stack_annotations(base_box, before, after, gaps, font))
+}
+
+fn layout_vertical_cd_arrow(
+    glyph_char: char,
+    before: Option<LayoutBox>,
+    after: Option<LayoutBox>,
+    font_size: f32,
+    font: &Font,
+) -> Result<LayoutBox, RenderError> {
+    let label_height = [before.as_ref(), after.as_ref()]
+        .into_iter()
+        .flatten()
+        .map(|label| label.height)
+        .fold(0.0, f32::max);
+    let target = (CD_ARROW_MIN_HEIGHT * font_size).max(label_height);
+    let Some(glyph) = math_table::stretch_glyph(glyph_char, Axis::Vertical, target, font_size)
+    else {
+        return Ok(make_delimiter_box(
+            &glyph_char.to_string(),
+            target,
+            font_size,
+            font,
+        ));
+    };
+
+    // 与 `\big` 定界符一样让墨迹上下对称地落在数学轴两侧
+    let axis = math_table::constants().axis_height * font_size;
+    let shift = (glyph.ascent - glyph.descent) / 2.0 - axis;
+    let (ascent, descent, _) = line_metrics(font, font_size);
+    let above = ascent.max(glyph.ascent - shift);
+    let below = descent.max(glyph.descent + shift);
+    // 标签与箭头相隔 0.15em；两侧留出同样宽度，使箭头位于盒子正中、对准上下两行的对象
+    let gap = font_size * 0.15;
+    let side = [before.as_ref(), after.as_ref()]
+        .into_iter()
+        .flatten()
+        .map(|label| label.width + gap)
+        .fold(font_size * 0.05, f32::max);
+    let center_y = above - axis;
+
+    let mut items = Vec::new();
+    let mut lines = Vec::new();
+    let mut paths = vec![RenderPath {
+        d: glyph.d,
+        x: side,
+        y: above + shift,
+        fill: Some("#000000"),
+        stroke: Some("none"),
+        stroke_width: None,
+        stroke_linecap: None,
+        stroke_linejoin: None,
+    }];
+    let labels = [
+        before.map(|label| (side - gap - label.width, label)),
+        after.map(|label| (side + glyph.width + gap, label)),
+    ];
+    for (x, label) in labels.into_iter().flatten() {
+        let y = center_y - label.height / 2.0;
+        items.extend(offset_items_owned(label.items, x, y));
+        lines.extend(offset_lines_owned(label.lines, x, y));
+        paths.extend(offset_paths_owned(label.paths, x, y));
+    }
+
+    Ok(LayoutBox {
+        width: glyph.width + side * 2.0,
+        height: above + below,
+        baseline: above,
+        script_policy: ScriptPolicy::Right,
+        italic_correction: 0.0,
+        items,
+        lines,
+        paths,
+    })
+}
+
+/// `\big` 系列：按 MATH 表的竖向尺寸变体（必要时拼装）取得指定高度的定界符，
+/// 墨迹中心对齐数学轴
+fn layout_sized_delimiter(
+    node: &SizedDelimiterNode,
+    font_size: f32,
+    font: &Font,
+) -> Result<LayoutBox, RenderError> {
+    let spacing = node.spacing * font_size;
+    let (ascent, descent, _) = line_metrics(font, font_size);
+    let Some(glyph_text) = node.delimiter.glyph.as_ref() else {
+        let mut empty = layout_text("", font_size, font)?;
+        empty.width = font_size * 0.12 + spacing * 2.0;
+        return Ok(empty);
+    };
+    let target = node.height * font_size;
+    let stretched = glyph_text
+        .chars()
+        .next()
+        .and_then(|ch| math_table::stretch_glyph(ch, Axis::Vertical, target, font_size));
+    let Some(glyph) = stretched else {
+        let mut fallback = make_delimiter_box(glyph_text, target, font_size, font);
+        offset_items(&mut fallback.items, spacing, 0.0);
+        fallback.width += spacing * 2.0;
+        return Ok(fallback);
+    };
+
+    // 字形基线相对数学轴的偏移，使墨迹上下对称地落在轴两侧
+    let axis = math_table::constants().axis_height * font_size;
+    let shift = (glyph.ascent - glyph.descent) / 2.0 - axis;
+    let above = ascent.max(glyph.ascent - shift);
+    let below = descent.max(glyph.descent + shift);
+    let side = font_size * 0.05;
+
+    Ok(LayoutBox {
+        width: glyph.width + (side + spacing) * 2.0,
+        height: above + below,
+        baseline: above,
+        script_policy: ScriptPolicy::Right,
+        italic_correction: 0.0,
+        items: Vec::new(),
+        lines: Vec::new(),
+        paths: vec![RenderPath {
+            d: glyph.d,
+            x: side + spacing,
+            y: above + shift,
+            fill: Some("#000000"),
+            stroke: Some("none"),
+            stroke_width: None,
+            stroke_linecap: None,
+            stroke_linejoin: None,
+        }],
+    })
+}
+
+/// `\fboxsep`：边框、底色与主体之间的留白（以字号为单位）
+const FRAME_PADDING: f32 = 0.3;
+/// `\fboxrule`：边框与删除线的粗细（以字号为单位）
+const ENCLOSURE_RULE: f32 = 0.04;
+/// `\cancelto` 的箭头越过主体右上角的长度（以字号为单位）
+const CANCEL_ARROW_OVERSHOOT: f32 = 0.3;
+
+/// 边框、底色与删除线：边框与底色在主体四周留白，删除线叠画在主体上、不改变尺寸，
+/// 两者都保持主体的基线
+fn layout_enclosed(
+    enclosure: &EnclosureNode,
+    font_size: f32,
+    font: &Font,
+) -> Result<LayoutBox, RenderError> {
+    let body = layout_node(&enclosure.body, font_size, font)?;
+    let rule = (font_size * ENCLOSURE_RULE).max(0.8);
+    match enclosure.kind {
+        EnclosureKind::Frame | EnclosureKind::RoundedFrame => {
+            Ok(layout_frame(enclosure, body, rule, font_size))
+        }
+        EnclosureKind::Cancel => match &enclosure.target {
+            Some(target) => {
+                let target_box = layout_node(
+                    target,
+                    font_size * math_table::constants().script_scale,
+                    font,
+                )?;
+                Ok(layout_cancel_arrow(body, target_box, rule, font_size))
+            }
+            None => Ok(strike_through(body, &[(0.0, 1.0, 1.0, 0.0)], rule)),
+        },
+        EnclosureKind::BackCancel => Ok(strike_through(body, &[(0.0, 0.0, 1.0, 1.0)], rule)),
+        EnclosureKind::CrossCancel => Ok(strike_through(
+            body,
+            &[(0.0, 1.0, 1.0, 0.0), (0.0, 0.0, 1.0, 1.0)],
+            rule,
+        )),
+        EnclosureKind::StrikeOut => {
+            let axis = math_table::constants().axis_height * font_size;
+            let y = if body.height > 0.0 {
+                (body.baseline - axis) / body.height
+            } else {
+                0.0
+            };
+            Ok(strike_through(body, &[(0.0, y, 1.0, y)], rule))
+        }
+    }
+}
+
+/// 矩形或圆角边框：底色铺满整个盒子并画在主体之下，边框线沿盒子内缘描画
+fn layout_frame(
+    enclosure: &EnclosureNode,
+    body: LayoutBox,
+    rule: f32,
+    font_size: f32,
+) -> LayoutBox {
+    let border = if enclosure.stroke.is_some() {
+        rule
+    } else {
+        0.0
+    };
+    let inset = FRAME_PADDING * font_size + border;
+    let width = body.width + inset * 2.0;
+    let height = body.height + inset * 2.0;
+    let radius = match enclosure.kind {
+        EnclosureKind::RoundedFrame => (FRAME_PADDING * font_size).min(width.min(height) / 2.0),
+        _ => 0.0,
+    };
+
+    let mut paths = Vec::with_capacity(body.paths.len() + 2);
+    if let Some(fill) = enclosure.fill {
+        paths.push(RenderPath {
+            d: rectangle_path(width, height, radius),
+            x: 0.0,
+            y: 0.0,
+            fill: Some(fill),
+            stroke: Some("none"),
+            stroke_width: None,
+            stroke_linecap: None,
+            stroke_linejoin: None,
+        });
+    }
+    paths.extend(offset_paths_owned(body.paths, inset, inset));
+    if let Some(stroke) = enclosure.stroke {
+        paths.push(RenderPath {
+            d: rectangle_path(width - border, height - border, radius),
+            x: border / 2.0,
+            y: border / 2.0,
+            fill: None,
+            stroke: Some(stroke),
+            stroke_width: Some(border),
+            stroke_linecap: None,
+            stroke_linejoin: Some("miter"),
+        });
+    }
+
+    LayoutBox {
+        width,
+        height,
+        baseline: body.baseline + inset,
+        script_policy: ScriptPolicy::Right,
+        italic_correction: 0.0,
+        items: offset_items_owned(body.items, inset, inset),
+        lines: offset_lines_owned(body.lines, inset, inset),
+        paths,
+    }
+}
+
+/// 左上角位于原点的矩形轮廓，`radius` 大于 0 时四角为圆弧
+fn rectangle_path(width: f32, height: f32, radius: f32) -> String {
+    if radius <= 0.0 {
+        return format!("M0 0 L{width:.2} 0 L{width:.2} {height:.2} L0 {height:.2} Z");
+    }
+    let r = radius;
+    format!(
+        "M{r:.2} 0 L{:.2} 0 A{r:.2} {r:.2} 0 0 1 {width:.2} {r:.2} L{width:.2} {:.2} \
+         A{r:.2} {r:.2} 0 0 1 {:.2} {height:.2} L{r:.2} {height:.2} \
+         A{r:.2} {r:.2} 0 0 1 0 {:.2} L0 {r:.2} A{r:.2} {r:.2} 0 0 1 {r:.2} 0 Z",
+        width - r,
+        height - r,
+        width - r,
+        height - r,
+    )
+}
+
+/// 在主体上叠画直线，端点以主体宽高的比例给出 `(x1, y1, x2, y2)`
+fn strike_through(mut body: LayoutBox, strokes: &[(f32, f32, f32, f32)], rule: f32) -> LayoutBox {
+    for &(x1, y1, x2, y2) in strokes {
+        body.lines.push(RenderLine {
+            x1: x1 * body.width,
+            y1: y1 * body.height,
+            x2: x2 * body.width,
+            y2: y2 * body.height,
+            stroke_width: rule,
+        });
+    }
+    body.italic_correction = 0.0;
+    body
+}
+
+/// `\cancelto`：从主体左下角沿对角线画箭头并越过右上角，目标值以脚标字号写在箭头末端的右上方
+fn layout_cancel_arrow(body: LayoutBox, target: LayoutBox, rule: f32, font_size: f32) -> LayoutBox {
+    let length = body.width.hypot(body.height).max(f32::EPSILON);
+    let (ux, uy) = (body.width / length, -body.height / length);
+    let overshoot = CANCEL_ARROW_OVERSHOOT * font_size;
+    let (tip_x, tip_y) = (body.width + ux * overshoot, uy * overshoot);
+    let head_length = font_size * 0.25;
+    let head_half = head_length * 0.4;
+    let (base_x, base_y) = (tip_x - ux * head_length, tip_y - uy * head_length);
+    // 箭头两翼沿垂直于箭杆的方向张开
+    let (nx, ny) = (-uy * head_half, ux * head_half);
+
+    let target_x = tip_x;
+    let target_top = tip_y - target.height;
+    let top = target_top.min(base_y - ny.abs()).min(0.0);
+    let width = body.width.max(target_x + target.width);
+    let height = body.height - top;
+
+    let mut items = offset_items_owned(body.items, 0.0, -top);
+    items.extend(offset_items_owned(target.items, target_x, target_top - top));
+    let mut lines = offset_lines_owned(body.lines, 0.0, -top);
+    lines.extend(offset_lines_owned(target.lines, target_x, target_top - top));
+    lines.push(RenderLine {
+        x1: 0.0,
+        y1: body.height - top,
+        x2: base_x,
+        y2: base_y - top,
+        stroke_width: rule,
+    });
+    let mut paths = offset_paths_owned(body.paths, 0.0, -top);
+    paths.extend(offset_paths_owned(target.paths, target_x, target_top - top));
+    paths.push(RenderPath {
+        d: format!(
+            "M{:.2} {:.2} L{:.2} {:.2} L{:.2} {:.2} Z",
+            tip_x,
+            tip_y,
+            base_x + nx,
+            base_y + ny,
+            base_x - nx,
+            base_y - ny
+        ),
+        x: 0.0,
+        y: -top,
+        fill: Some("#000000"),
+        stroke: Some("none"),
+        stroke_width: None,
+        stroke_linecap: None,
+        stroke_linejoin: None,
+    });
+
+    LayoutBox {
+        width,
+        height,
+        baseline: body.baseline - top,
+        script_policy: ScriptPolicy::Right,
+        italic_correction: 0.0,
+        items,
+        lines,
+        paths,
+    }
+}
+
+/// 不含任何绘制内容的盒子，`baseline` 为基线到顶部的距离
+fn empty_box(width: f32, height: f32, baseline: f32) -> LayoutBox {
+    LayoutBox {
+        width,
+        height,
+        baseline,
+        script_policy: ScriptPolicy::Right,
+        italic_correction: 0.0,
+        items: Vec::new(),
+        lines: Vec::new(),
+        paths: Vec::new(),
+    }
+}
+
+/// 占位与尺寸调整：`\phantom` 只保留主体尺寸，`\smash` 把基线以上或以下的尺寸记为 0，
+/// `\raisebox` 平移主体后按新位置计算高度与深度（均不小于 0）
+fn layout_adjusted(
+    body: &AstNode,
+    adjust: BoxAdjust,
+    font_size: f32,
+    font: &Font,
+) -> Result<LayoutBox, RenderError> {
+    let body = layout_node(body, font_size, font)?;
+    let above = body.baseline;
+    let below = body.height - body.baseline;
+    Ok(match adjust {
+        BoxAdjust::Phantom { width, height } => {
+            let (above, below) = if height { (above, below) } else { (0.0, 0.0) };
+            let width = if width { body.width } else { 0.0 };
+            empty_box(width, above + below, above)
+        }
+        BoxAdjust::Smash { top, bottom } => {
+            let above = if top { 0.0 } else { above };
+            let below = if bottom { 0.0 } else { below };
+            reframe(body, above, below, 0.0)
+        }
+        BoxAdjust::Raise(shift) => {
+            let lift = shift * font_size;
+            reframe(body, (above + lift).max(0.0), (below - lift).max(0.0), lift)
+        }
+    })
+}
+
+/// 以新的基线上方高度 `above` 与下方深度 `below` 重新框定主体，主体内容相对基线抬高 `lift`；
+/// 超出新边界的部分照常绘制
+fn reframe(body: LayoutBox, above: f32, below: f32, lift: f32) -> LayoutBox {
+    let dy = above - body.baseline - lift;
+    LayoutBox {
+        width: body.width,
+        height: above + below,
+        baseline: above,
+        script_policy: body.script_policy,
+        italic_correction: body.italic_correction,
+        items: offset_items_owned(body.items, 0.0, dy),
+        lines: offset_lines_owned(body.lines, 0.0, dy),
+        paths: offset_paths_owned(body.paths, 0.0, dy),
+    }
+}
+
+/// `\rule`：底边位于基线之上 `raise` 处的实心矩形，宽或高不大于 0 时只占位不绘制
+fn layout_rule(rule: &RuleNode, font_size: f32) -> LayoutBox {
+    let width = rule.width.max(0.0) * font_size;
+    let height = rule.height.max(0.0) * font_size;
+    let raise = rule.raise * font_size;
+    let above = (raise + height).max(0.0);
+    let below = (-raise).max(0.0);
+    let mut layout_box = empty_box(width, above + below, above);
+    if width > 0.0 && height > 0.0 {
+        layout_box.paths.push(RenderPath {
+            d: rectangle_path(width, height, 0.0),
+            x: 0.0,
+            y: above - raise - height,
+            fill: Some("#000000"),
+            stroke: Some("none"),
+            stroke_width: None,
+            stroke_linecap: None,
+            stroke_linejoin: None,
+        });
+    }
+    layout_box
+}
+
 fn make_delimiter_box(
     glyph: &str,
     target_height: f32,
@@ -1078,6 +2248,8 @@ fn make_delimiter_box(
     };
     let effective_size = base_font_size * scale;
     let (ascent, descent, _) = line_metrics(font, effective_size);
+    // 放大后的定界符以数学轴为中心，基线相应上移，保持与正文的轴对齐
+    let axis_shift = math_table::constants().axis_height * (effective_size - base_font_size);
     let mut width = 0.0f32;
     let mut italic_correction = 0.0f32;
     for ch in glyph.chars() {
@@ -1088,7 +2260,7 @@ fn make_delimiter_box(
     LayoutBox {
         width,
         height: ascent + descent,
-        baseline: ascent,
+        baseline: ascent - axis_shift,
         script_policy: ScriptPolicy::Right,
         italic_correction,
         items: vec![RenderItem {
@@ -1102,6 +2274,53 @@ fn make_delimiter_box(
     }
 }
 
+/// 估算盒子内墨迹的上下边界（盒内纵坐标）
+fn ink_extent(layout_box: &LayoutBox, font: &Font) -> (f32, f32) {
+    let mut top = f32::INFINITY;
+    let mut bottom = f32::NEG_INFINITY;
+    for item in &layout_box.items {
+        for ch in item.text.chars() {
+            let metrics = cached_metrics(font, ch, item.font_size);
+            if metrics.bounds.height <= 0.0 {
+                continue;
+            }
+            top = top.min(item.y - (metrics.bounds.ymin + metrics.bounds.height));
+            bottom = bottom.max(item.y - metrics.bounds.ymin);
+        }
+    }
+    for line in &layout_box.lines {
+        let half = line.stroke_width / 2.0;
+        top = top.min(line.y1.min(line.y2) - half);
+        bottom = bottom.max(line.y1.max(line.y2) + half);
+    }
+    for path in &layout_box.paths {
+        if let Some((path_top, path_bottom)) = path_vertical_extent(&path.d) {
+            let half = path.stroke_width.unwrap_or(0.0) / 2.0;
+            top = top.min(path.y + path_top - half);
+            bottom = bottom.max(path.y + path_bottom + half);
+        }
+    }
+    if top > bottom {
+        // 没有可见墨迹（如空组或纯空格），退化为基线位置
+        return (layout_box.baseline, layout_box.baseline);
+    }
+    (top, bottom)
+}
+
+/// 读取路径数据中的纵坐标范围。路径均由绝对坐标对组成（`M`/`L`/`Q`/`C`），
+/// 控制点包含在内，得到的是略保守的包围范围
+fn path_vertical_extent(d: &str) -> Option<(f32, f32)> {
+    let mut extent: Option<(f32, f32)> = None;
+    let numbers = d
+        .split(|ch: char| ch.is_ascii_alphabetic() || ch == ' ' || ch == ',')
+        .filter(|token| !token.is_empty())
+        .filter_map(|token| token.parse::<f32>().ok());
+    for y in numbers.skip(1).step_by(2) {
+        extent = Some(extent.map_or((y, y), |(top, bottom)| (top.min(y), bottom.max(y))));
+    }
+    extent
+}
+
 fn measure_text_width(content: &str, font_size: f32, font: &Font) -> f32 {
     content
         .chars()
@@ -1175,7 +2394,58 @@ fn cached_metrics(font: &Font, ch: char, font_size: f32) -> GlyphMetrics {
     })
 }
 
+/// 排版阶段的剩余工作量，按线程保存以避免层层传参
+#[derive(Debug, Clone, Copy)]
+struct LayoutBudget {
+    boxes_left: usize,
+    max_boxes: usize,
+    max_items: usize,
+    render: RenderBudget,
+}
+
+impl LayoutBudget {
+    fn new(render: &RenderBudget) -> Self {
+        Self {
+            boxes_left: render.limits.max_layout_boxes,
+            max_boxes: render.limits.max_layout_boxes,
+            max_items: render.limits.max_render_items,
+            render: *render,
+        }
+    }
+}
+
+/// 每生成一个布局盒扣减一次预算，并顺带检查截止时间
+fn charge_layout_box() -> Result<(), RenderError> {
+    LAYOUT_BUDGET.with(|slot| {
+        let Some(mut budget) = slot.get() else {
+            return Ok(());
+        };
+        if budget.boxes_left == 0 {
+            return Err(RenderError::LimitExceeded(format!(
+                "布局盒数量超过 {} 个",
+                budget.max_boxes
+            )));
+        }
+        budget.boxes_left -= 1;
+        slot.set(Some(budget));
+        budget.render.check_deadline()
+    })
+}
+
+/// 子树累积的绘制元素超过上限时尽早终止，避免继续拷贝与偏移
+fn check_emitted_items(layout_box: &LayoutBox) -> Result<(), RenderError> {
+    let emitted = layout_box.items.len() + layout_box.lines.len() + layout_box.paths.len();
+    LAYOUT_BUDGET.with(|slot| match slot.get() {
+        Some(budget) if emitted > budget.max_items => Err(RenderError::LimitExceeded(format!(
+            "绘制元素数量超过 {} 个",
+            budget.max_items
+        ))),
+        _ => Ok(()),
+    })
+}
+
 thread_local! {
+    static LAYOUT_BUDGET: Cell<Option<LayoutBudget>> = const { Cell::new(None) };
     static METRICS_CACHE: RefCell<HashMap<(char, u32), GlyphMetrics>> =
         RefCell::new(HashMap::new());
 }
@@ -1,7 +1,7 @@
 #[global_allocator]
 static ALLOC: jemallocator::Jemalloc = jemallocator::Jemalloc;
 
-/// 模块入口：对外提供渲染接口，并串联各子模块
+// 模块入口：对外提供渲染接口，并串联各子模块
 
 use std::borrow::Cow;
 use std::panic::{catch_unwind, AssertUnwindSafe}; // 引入 panic 捕获工具，防止单次渲染拖垮进程
@@ -12,17 +12,51 @@ mod ast; // 语法树定义模块
 mod config; // 运行时配置管理
 mod error; // 错误类型模块，统一描述错误信息
 mod ffi; // FFI 模块，提供 C 可调用的接口
+#[cfg(feature = "fuzzing")]
+pub mod fuzzing; // 模糊测试入口，仅在 fuzzing 特性下编译
 mod init; // 初始化模块，加载字体与全局状态
 mod layout; // 排版模块，把语法树转换为布局信息
+mod math_table; // MATH 表模块，读取数学排版常量
+mod numbering; // 公式编号模块，结算 \tag 与自动编号
 mod parse; // 解析模块，把 LaTeX 字符串解析成语法树
 mod render; // 渲染模块，把布局信息转成 SVG 字符串
+#[cfg(test)]
+mod snapshot_tests; // 快照测试：排版几何与 SVG 输出的基准比对
+#[cfg(test)]
+mod visual_tests; // 像素级回归测试：resvg 栅格化后与参考 PNG 比对
 
 pub use crate::error::RenderError; // 暴露错误类型，方便调用方处理
-pub use crate::ffi::{free_svg, render_svg}; // 暴露 C 接口，让 Go 通过 cgo 调用并负责释放内存
+pub use crate::ffi::{free_svg, render_svg, render_svg_with_budget}; // 暴露 C 接口，让 Go 通过 cgo 调用并负责释放内存
+pub use config::{override_render_limits, RenderLimits}; // 提供外部调整复杂度上限的入口
 pub use config::{override_svg_text_mode, SvgTextMode}; // 提供外部调整 SVG 输出模式的入口（可选使用）
+pub use config::{DecimalMarker, EquationOptions, TagSide}; // 公式编号的起始值、摆放位置与小数点符号
+pub use numbering::{EquationLabel, RenderedFormula}; // 带编号信息的渲染结果
 
 /// 对外提供的核心函数：输入 LaTeX，输出 SVG
 pub fn render_formula(tex: &str) -> Result<String, RenderError> {
+    render_formula_with_limits(tex, &config::render_limits())
+}
+
+/// 按指定的复杂度上限与工作预算渲染，适合为单次请求设置超时
+pub fn render_formula_with_limits(tex: &str, limits: &RenderLimits) -> Result<String, RenderError> {
+    render_equation_with_limits(tex, &EquationOptions::default(), limits)
+        .map(|rendered| rendered.svg)
+}
+
+/// 渲染带编号的公式：按 `options` 分配自动编号并摆放编号，返回 SVG 与各编号、标签
+pub fn render_equation(
+    tex: &str,
+    options: &EquationOptions,
+) -> Result<RenderedFormula, RenderError> {
+    render_equation_with_limits(tex, options, &config::render_limits())
+}
+
+/// 同 [`render_equation`]，额外指定复杂度上限与工作预算
+pub fn render_equation_with_limits(
+    tex: &str,
+    options: &EquationOptions,
+    limits: &RenderLimits,
+) -> Result<RenderedFormula, RenderError> {
     init::ensure_fonts_loaded()?; // 确保字体与全局状态已经就绪，失败直接返回错误
     let trimmed = tex.trim(); // 去掉首尾空白，避免无意义字符影响结果
     if trimmed.is_empty() {
@@ -31,22 +65,38 @@ pub fn render_formula(tex: &str) -> Result<String, RenderError> {
     }
 
     let normalized = normalize_escaped_commands(trimmed);
+    let budget = config::RenderBudget::start(*limits); // 栈溢出无法被 catch_unwind 捕获，只能靠复杂度上限提前拦截
 
     let guarded_result = catch_unwind(AssertUnwindSafe(|| {
         // 用 catch_unwind 捕获潜在 panic
-        parse::parse(normalized.as_ref()) // 第一步：解析得到语法树
-            .and_then(|ast| layout::layout(&ast)) // 第二步：根据语法树生成布局数据
-            .and_then(|layout| render::render_svg_document(&layout)) // 第三步：把布局转成 SVG 字符串
+        let parsed = parse::parse(normalized.as_ref(), &budget, options)?; // 第一步：解析得到语法树
+        let numbering = numbering::assign(&parsed.tags, options.first_number); // 第二步：结算编号
+        let layout = layout::layout(&parsed, &numbering.rows, options, &budget)?; // 第三步：根据语法树生成布局数据
+        budget.check_deadline()?; // 路径模式转换较慢，进入前再确认一次是否超时
+        let svg = render::render_svg_document(&layout)?; // 第四步：把布局转成 SVG 字符串
+        Ok(RenderedFormula {
+            svg,
+            labels: numbering.labels,
+            next_number: numbering.next_number,
+        })
     }));
 
-    let svg = match guarded_result {
+    let rendered = match guarded_result {
         // 统一处理 catch_unwind 与中间错误
-        Ok(Ok(svg)) => svg,              // 正常情况：成功得到 SVG
+        Ok(Ok(rendered)) => rendered,    // 正常情况：成功得到 SVG
         Ok(Err(err)) => return Err(err), // 解析或渲染阶段返回业务错误
         Err(_) => return Err(RenderError::UnexpectedPanic), // 捕获 panic，转换成安全的错误提示
     };
 
-    Ok(svg) // 返回最终 SVG 字符串
+    if rendered.svg.len() > limits.max_output_bytes {
+        // 输出体积超限时不返回半成品，避免调用方被超大字符串拖垮
+        return Err(RenderError::LimitExceeded(format!(
+            "SVG 输出超过 {} 字节",
+            limits.max_output_bytes
+        )));
+    }
+
+    Ok(rendered) // 返回最终结果
 }
 
 /// 批量渲染接口：给 rayon 使用，提升并发性能
@@ -57,6 +107,42 @@ pub fn render_formula_batch(texts: &[String]) -> Vec<Result<String, RenderError>
         .collect() // 把结果收集成 Vec
 }
 
+fn normalize_escaped_commands(input: &str) -> Cow<'_, str> {
+    let bytes = input.as_bytes();
+    let mut idx = 0;
+    while idx + 2 <= bytes.len() {
+        if bytes[idx] == b'\\'
+            && bytes[idx + 1] == b'\\'
+            && idx + 2 < bytes.len()
+            && bytes[idx + 2].is_ascii_alphabetic()
+        {
+            let mut output = String::with_capacity(input.len());
+            output.push_str(&input[..idx]);
+            output.push('\\');
+            idx += 2;
+            let mut copied_from = idx; // 按原始切片拷贝，避免把多字节字符拆成单个字节
+            while idx < bytes.len() {
+                if bytes[idx] == b'\\'
+                    && idx + 2 < bytes.len()
+                    && bytes[idx + 1] == b'\\'
+                    && bytes[idx + 2].is_ascii_alphabetic()
+                {
+                    output.push_str(&input[copied_from..idx]);
+                    output.push('\\');
+                    idx += 2;
+                    copied_from = idx;
+                    continue;
+                }
+                idx += 1;
+            }
+            output.push_str(&input[copied_from..]);
+            return Cow::Owned(output);
+        }
+        idx += 1;
+    }
+    Cow::Borrowed(input)
+}
+
 #[cfg(test)] // 仅在测试环境编译下面的代码
 mod tests {
     use super::*; // 把父模块公开项目引入作用域
@@ -104,8 +190,8 @@ mod tests {
 
         let matrix_svg = render_formula("\\matrix{1 & 2 \\\\ 3 & 4}").expect("矩阵渲染失败"); // 测试矩阵结构排版
         assert!(
-            matrix_svg.contains("<line"),
-            "矩阵外框应当包含线条，当前输出: {matrix_svg}"
+            matrix_svg.contains('4') && !matrix_svg.contains("<line"),
+            "矩阵应输出全部单元格且不带外框线条，当前输出: {matrix_svg}"
         );
         override_svg_text_mode(None);
     }
@@ -167,8 +253,8 @@ mod tests {
 
         let tilde = render_formula("\\tilde{x}").expect("tilde 渲染失败");
         assert!(
-            tilde.contains('~'),
-            "波浪符装饰应添加 '~' 符号，当前输出: {tilde}"
+            tilde.contains("<path") && tilde.contains('x'),
+            "波浪符装饰应以组合重音字形路径绘制，当前输出: {tilde}"
         );
 
         let bold = render_formula("\\mathbf{AB}").expect("粗体字母渲染失败");
@@ -179,34 +265,1251 @@ mod tests {
 
         override_svg_text_mode(None);
     }
-}
 
-fn normalize_escaped_commands(input: &str) -> Cow<'_, str> {
-    let bytes = input.as_bytes();
-    let mut idx = 0;
-    while idx + 2 <= bytes.len() {
-        if bytes[idx] == b'\\' && bytes[idx + 1] == b'\\' {
-            if idx + 2 < bytes.len() && bytes[idx + 2].is_ascii_alphabetic() {
-                let mut output = String::with_capacity(input.len());
-                output.push_str(&input[..idx]);
-                output.push('\\');
-                idx += 2;
-                while idx < bytes.len() {
-                    let ch = bytes[idx] as char;
-                    if ch == '\\' && idx + 1 < bytes.len() && bytes[idx + 1] == b'\\' {
-                        if idx + 2 < bytes.len() && bytes[idx + 2].is_ascii_alphabetic() {
-                            output.push('\\');
-                            idx += 2;
-                            continue;
-                        }
-                    }
-                    output.push(ch);
-                    idx += 1;
-                }
-                return Cow::Owned(output);
-            }
+    #[test]
+    fn under_over_should_center_script_sized_annotations() {
+        use crate::snapshot_tests::{find_item, layout_formula};
+
+        let plan = layout_formula(r"\overset{a}{b}");
+        let (over, base) = (find_item(&plan, "a"), find_item(&plan, "b"));
+        assert!(over.y < base.y, "上方注释应位于主体之上");
+        assert!((over.font_size - base.font_size * 0.7).abs() < 0.01);
+
+        let plan = layout_formula(r"\underset{a}{b}");
+        let (under, base) = (find_item(&plan, "a"), find_item(&plan, "b"));
+        assert!(under.y > base.y, "下方注释应位于主体之下");
+
+        // 注释比主体宽时，主体应居中
+        let plan = layout_formula(r"\stackrel{\text{def}}{=}");
+        let (label, equals) = (find_item(&plan, "def"), find_item(&plan, "="));
+        assert!(equals.x > label.x, "较窄的主体应在注释下方居中");
+        assert!(label.y < equals.y);
+
+        let plan = layout_formula(r"\overunderset{a}{c}{b}");
+        let (over, base, under) = (
+            find_item(&plan, "a"),
+            find_item(&plan, "b"),
+            find_item(&plan, "c"),
+        );
+        assert!(over.y < base.y && base.y < under.y);
+
+        let plan = layout_formula(r"\xrightarrow[g]{f}");
+        let (above, arrow, below) = (
+            find_item(&plan, "f"),
+            find_item(&plan, "→"),
+            find_item(&plan, "g"),
+        );
+        assert!(above.y < arrow.y && arrow.y < below.y);
+
+        let _guard = MODE_GUARD.lock().unwrap();
+        override_svg_text_mode(Some(SvgTextMode::Text));
+        let svg = render_formula(r"\underset{x}{\arg\max} f(x)").expect("underset 渲染失败");
+        assert!(svg.contains("arg"), "\\arg 应渲染为函数名，当前输出: {svg}");
+        assert!(render_formula(r"\overset{a}").is_err(), "缺少主体时应报错");
+        override_svg_text_mode(None);
+    }
+
+    #[test]
+    fn braces_should_stretch_and_center_labels() {
+        use crate::snapshot_tests::{find_item, layout_formula};
+
+        let narrow = layout_formula(r"\overbrace{a}");
+        let wide = layout_formula(r"\overbrace{a + b + c + d}");
+        assert_eq!(narrow.paths.len(), 1, "花括号应以字形路径绘制");
+        assert!(narrow.lines.is_empty(), "花括号不应再退化为直线");
+        assert!(
+            wide.paths[0].d.len() > narrow.paths[0].d.len(),
+            "宽主体应使用拼装后的更长花括号"
+        );
+
+        let plan = layout_formula(r"\underbrace{x + y + z}_{n}");
+        let label = find_item(&plan, "n");
+        let base = find_item(&plan, "x + y + z");
+        assert!(label.y > plan.paths[0].y && plan.paths[0].y > base.y);
+        let center = plan.width / 2.0;
+        assert!(
+            label.x < center && center < label.x + label.font_size,
+            "标签应在花括号下方居中"
+        );
+
+        for tex in [
+            r"\overbracket{ab}^{1}",
+            r"\underbracket{ab}_{2}",
+            r"\overparen{AB}",
+            r"\underparen{AB}",
+        ] {
+            let plan = layout_formula(tex);
+            assert_eq!(plan.paths.len(), 1, "{tex} 应绘制伸缩字形");
         }
-        idx += 1;
     }
-    Cow::Borrowed(input)
+
+    #[test]
+    fn accents_should_use_math_variants_and_attachments() {
+        use crate::snapshot_tests::layout_formula;
+
+        let accent_width = |tex: &str| {
+            let plan = layout_formula(tex);
+            assert_eq!(plan.paths.len(), 1, "{tex} 应以字形路径绘制重音");
+            let (left, right) = path_horizontal_extent(&plan.paths[0].d);
+            right - left
+        };
+
+        // \hat 固定尺寸，\widehat 随主体加宽，但不超过主体宽度
+        assert!((accent_width(r"\hat{x}") - accent_width(r"\hat{xyz}")).abs() < 0.01);
+        assert!(accent_width(r"\widehat{xyz}") > accent_width(r"\widehat{x}"));
+        // 布局四周留有与主体起点相同的边距
+        let base_width = |plan: &layout::LayoutPlan| plan.width - 2.0 * plan.items[0].x;
+        let plan = layout_formula(r"\widehat{xyz}");
+        let (left, right) = path_horizontal_extent(&plan.paths[0].d);
+        assert!(right - left <= base_width(&plan) + 0.01);
+
+        // 箭头类重音拼装到覆盖主体宽度
+        let plan = layout_formula(r"\overrightarrow{ABCD}");
+        let (left, right) = path_horizontal_extent(&plan.paths[0].d);
+        assert!(
+            right - left >= base_width(&plan) - 0.5,
+            "箭头应覆盖整个主体"
+        );
+
+        // 单个字母按顶部重音附着点对齐，而不是简单居中
+        let plan = layout_formula(r"\hat{b}");
+        let (left, right) = path_horizontal_extent(&plan.paths[0].d);
+        let accent_center = plan.paths[0].x + (left + right) / 2.0;
+        assert!(accent_center < plan.width / 2.0 - 1.0, "b 的附着点偏向竖笔");
+
+        // 下方重音位于基线以下
+        for tex in [
+            r"\underrightarrow{AB}",
+            r"\underleftarrow{xy}",
+            r"\utilde{AB}",
+        ] {
+            let plan = layout_formula(tex);
+            let base = &plan.items[0];
+            assert!(plan.paths[0].y >= base.y, "{tex} 的重音应位于主体下方");
+        }
+    }
+
+    #[test]
+    fn standard_accents_should_share_glyph_positioning() {
+        use crate::snapshot_tests::layout_formula;
+
+        let commands = [
+            "acute", "grave", "check", "breve", "mathring", "bar", "dot", "ddot", "dddot",
+            "ddddot", "hat", "tilde", "vec",
+        ];
+        for command in commands {
+            let tex = format!(r"\{command}{{x}}");
+            let plan = layout_formula(&tex);
+            assert!(
+                plan.items.iter().all(|item| !item.text.contains('\\')),
+                "{tex} 不应回退为命令原文"
+            );
+            assert_eq!(plan.paths.len(), 1, "{tex} 应绘制一个重音字形");
+            assert!(
+                plan.paths[0].y <= plan.items[0].y,
+                "{tex} 的重音应以主体基线为参照放在上方"
+            );
+        }
+
+        // 高字母上的重音随主体上移，不与字母重叠
+        let low = layout_formula(r"\acute{a}");
+        let high = layout_formula(r"\acute{A}");
+        let raise = |plan: &layout::LayoutPlan| plan.items[0].y - plan.paths[0].y;
+        assert!(raise(&high) > raise(&low) + 1.0);
+    }
+
+    #[test]
+    fn sized_delimiters_and_middle_should_stretch() {
+        use crate::snapshot_tests::{find_item, layout_formula};
+
+        let ink_height = |tex: &str| {
+            let plan = layout_formula(tex);
+            assert_eq!(plan.paths.len(), 1, "{tex} 应以字形路径绘制定界符");
+            let (top, bottom) = path_vertical_extent(&plan.paths[0].d);
+            bottom - top
+        };
+        let heights: Vec<f32> = ["big", "Big", "bigg", "Bigg"]
+            .iter()
+            .map(|size| ink_height(&format!(r"\{size}l( x")))
+            .collect();
+        assert!(heights.windows(2).all(|pair| pair[1] > pair[0]));
+        // Latin Modern 的 ( 变体已覆盖到 3em，无需拼装
+        assert!((heights[3] / 28.0 - 3.0).abs() < 0.1);
+        assert!((ink_height(r"\Bigr]") - ink_height(r"\Bigl[")).abs() < 0.5);
+
+        // \bigm 两侧按关系符留白
+        let plain = layout_formula(r"a \big| b");
+        let spaced = layout_formula(r"a \bigm| b");
+        assert!(spaced.width > plain.width + 10.0);
+
+        // \middle 与外层定界符使用相同的放大字号
+        let plan = layout_formula(r"\left\{ \frac{a}{b} \middle| c \right\}");
+        let size_of = |glyph: &str| find_item(&plan, glyph).font_size;
+        assert!(size_of("{") > 28.0);
+        assert_eq!(size_of("|"), size_of("{"));
+        assert_eq!(size_of("}"), size_of("{"));
+
+        // 大型运算符不会被误当作 \big 系列
+        let union = layout_formula(r"\bigcup_i A_i");
+        assert!(union.items.iter().any(|item| item.text.contains('⋃')));
+
+        let _guard = MODE_GUARD.lock().unwrap();
+        assert!(matches!(
+            render_formula(r"a \middle| b"),
+            Err(RenderError::ParseError(_))
+        ));
+    }
+
+    #[test]
+    fn fraction_variants_should_share_generalized_layout() {
+        use crate::snapshot_tests::{assert_parse_errors, find_item, layout_formula};
+
+        // 分数线中心位于数学轴上
+        let plan = layout_formula(r"\frac{a}{b} = x");
+        let axis = find_item(&plan, " = x").y - plan.lines[0].y1;
+        assert!((axis / 28.0 - 0.25).abs() < 0.01, "轴高 {axis}");
+
+        // \tfrac 的分子分母取脚标字号，\dfrac 与 \frac 保持正文字号
+        assert!(
+            (find_item(&layout_formula(r"\tfrac{a}{b}"), "a").font_size - 28.0 * 0.7).abs() < 0.1
+        );
+        assert_eq!(
+            find_item(&layout_formula(r"\dfrac{a}{b}"), "a").font_size,
+            28.0
+        );
+
+        // 中缀 \over 与 \frac 排版一致，两侧空白被忽略
+        let infix = layout_formula(r"a \over b");
+        let prefix = layout_formula(r"\frac{a}{b}");
+        assert_eq!(infix.width, prefix.width);
+        assert_eq!(find_item(&infix, "a").x, find_item(&prefix, "a").x);
+
+        // 二项式与 \atop 不画分数线，\choose 与 \binom 相同
+        for tex in [
+            r"\binom{n}{k}",
+            r"{n \choose k}",
+            r"\dbinom{n}{k}",
+            r"\tbinom{n}{k}",
+        ] {
+            let plan = layout_formula(tex);
+            assert!(plan.lines.is_empty(), "{tex} 不应有分数线");
+            assert!(
+                plan.items.iter().any(|item| item.text == "("),
+                "{tex} 缺少左括号"
+            );
+            assert!(
+                plan.items.iter().any(|item| item.text == ")"),
+                "{tex} 缺少右括号"
+            );
+        }
+        let atop = layout_formula(r"a \atop b");
+        assert!(atop.lines.is_empty() && atop.items.len() == 2);
+
+        // \genfrac 自定义定界符与线宽
+        let plan = layout_formula(r"\genfrac{[}{]}{0pt}{}{n}{k}");
+        assert!(plan.lines.is_empty());
+        assert!(plan.items.iter().any(|item| item.text == "["));
+        let plan = layout_formula(r"\genfrac{}{}{2pt}{1}{a}{b}");
+        assert!((plan.lines[0].stroke_width - 0.2 * 28.0).abs() < 0.01);
+        assert!(find_item(&plan, "a").font_size < 28.0);
+
+        // \cfrac[l] 的分子靠左
+        let centered = layout_formula(r"\cfrac{1}{x+y+z}");
+        let left = layout_formula(r"\cfrac[l]{1}{x+y+z}");
+        let right = layout_formula(r"\cfrac[r]{1}{x+y+z}");
+        assert!(find_item(&left, "1").x < find_item(&centered, "1").x);
+        assert!(find_item(&right, "1").x > find_item(&centered, "1").x);
+
+        assert_parse_errors(&[
+            r"a \over b \over c",
+            r"\genfrac{(}{)}{x}{}{a}{b}",
+            r"\cfrac[q]{a}{b}",
+        ]);
+    }
+
+    #[test]
+    fn radicals_should_stretch_and_seat_index() {
+        use crate::snapshot_tests::{find_item, layout_formula};
+
+        // 根号字形与横线均为路径，横线顶端与字形顶端齐平并伸入连接段
+        let radical = |tex: &str| {
+            let plan = layout_formula(tex);
+            assert!(
+                plan.items.iter().all(|item| item.text != "√"),
+                "{tex} 不应再用文本根号"
+            );
+            let glyph = plan.paths[plan.paths.len() - 2].clone();
+            let bar = plan.paths[plan.paths.len() - 1].clone();
+            let (glyph_top, glyph_bottom) = path_vertical_extent(&glyph.d);
+            let (_, glyph_right) = path_horizontal_extent(&glyph.d);
+            assert!(
+                (glyph.y + glyph_top - bar.y).abs() < 0.05,
+                "{tex} 横线未与字形顶端对齐"
+            );
+            assert!(bar.x < glyph.x + glyph_right, "{tex} 横线与字形之间有缝隙");
+            (plan, glyph_bottom - glyph_top)
+        };
+
+        let (_, short) = radical(r"\sqrt{x}");
+        let (_, tall) = radical(r"\sqrt{\frac{\frac{a}{b}}{\frac{c}{d}}}");
+        assert!(tall > short * 2.0, "高内容应使用更大的根号变体或拼装");
+
+        // 根指数以二级脚标字号放在根号弯折处，而不是像上标那样浮在右上角
+        let (plan, _) = radical(r"\sqrt[3]{x}");
+        let degree = find_item(&plan, "3");
+        let body = find_item(&plan, "x");
+        let glyph = &plan.paths[0];
+        let (glyph_top, glyph_bottom) = path_vertical_extent(&glyph.d);
+        assert!((degree.font_size - 14.0).abs() < 0.1);
+        assert!(degree.x < body.x);
+        assert!(degree.x + 14.0 * 0.5 > glyph.x, "根指数应伸入根号弯折处");
+        assert!(degree.y > glyph.y + glyph_top && degree.y < glyph.y + glyph_bottom);
+    }
+
+    #[test]
+    fn array_should_follow_column_spec_and_rules() {
+        use crate::snapshot_tests::{assert_parse_errors, find_item, layout_formula};
+
+        let vertical =
+            |plan: &layout::LayoutPlan| plan.lines.iter().filter(|line| line.x1 == line.x2).count();
+        let horizontal = |plan: &layout::LayoutPlan| {
+            plan.lines
+                .iter()
+                .filter(|line| line.y1 == line.y2)
+                .map(|line| line.x2 - line.x1)
+                .collect::<Vec<_>>()
+        };
+
+        // 列格式控制对齐，且不会作为单元格内容输出，也不再画矩阵括号
+        let plan = layout_formula(r"\begin{array}{lr} a & b \\ aaa & bbb \end{array}");
+        assert!(plan.items.iter().all(|item| !item.text.contains("lr")));
+        assert!(plan.lines.is_empty());
+        assert_eq!(find_item(&plan, "a").x, find_item(&plan, "aaa").x);
+        assert!(find_item(&plan, "b").x > find_item(&plan, "bbb").x);
+
+        let single = layout_formula(r"\begin{array}{c|c} a & b \end{array}");
+        let double = layout_formula(r"\begin{array}{|c||c|} a & b \end{array}");
+        assert_eq!(vertical(&single), 1);
+        assert_eq!(vertical(&double), 4);
+
+        // \hline 贯穿整表，\cline 只覆盖指定列
+        let plan = layout_formula(
+            r"\begin{array}{ccc} \hline a & b & c \\ \cline{2-3} d & e & f \\ \hline \end{array}",
+        );
+        let spans = horizontal(&plan);
+        assert_eq!(spans.len(), 3);
+        assert!(spans[1] < spans[0] * 0.8 && spans[0] == spans[2]);
+        // 同一边界上的 \hline 与 \cline 都会画出，\cline 在下方
+        let mixed = layout_formula(
+            r"\begin{array}{ccc} a & b & c \\ \hline \cline{2-3} d & e & f \end{array}",
+        );
+        let spans = horizontal(&mixed);
+        assert_eq!(spans.len(), 2);
+        assert!(spans[1] < spans[0] * 0.8);
+        let ys = mixed.lines.iter().map(|line| line.y1).collect::<Vec<_>>();
+        assert!(ys[0] < ys[1]);
+
+        // @{} 去掉列间距，\\[..] 与 \arraystretch 增加行距，后者只在所在分组内生效
+        let plain = layout_formula(r"\begin{array}{c} a \\ b \end{array}");
+        let tight = layout_formula(r"\begin{array}{@{}c@{}} a \\ b \end{array}");
+        assert!(tight.width < plain.width);
+        let spaced = layout_formula(r"\begin{array}{c} a \\[1em] b \end{array}");
+        assert!((spaced.height - plain.height - 28.0).abs() < 0.5);
+        let stretched =
+            layout_formula(r"\renewcommand{\arraystretch}{2} \begin{array}{c} a \\ b \end{array}");
+        assert!(stretched.height > plain.height + 20.0);
+        let scoped = layout_formula(
+            r"{\renewcommand{\arraystretch}{2}} \begin{array}{c} a \\ b \end{array}",
+        );
+        assert_eq!(scoped.height, plain.height);
+
+        // 单元格内的嵌套环境与花括号不会被外层的 & 和 \\ 切开
+        let nested = layout_formula(
+            r"\begin{array}{cc} \begin{matrix} 1 & 2 \\ 3 & 4 \end{matrix} & {x \\ y} \end{array}",
+        );
+        assert!(nested.items.iter().any(|item| item.text == "4"));
+
+        assert_parse_errors(&[
+            r"\begin{array}{cc} a & b & c \end{array}",
+            r"\begin{array}{cx} a & b \end{array}",
+            r"\begin{array}{cc} \cline{2-3} a & b \end{array}",
+            r"\begin{array}{*{0}{c}} a \end{array}",
+            r"\begin{array}{*{3}{|}c} a \end{array}",
+            r"\begin{array} a & b \end{array}",
+        ]);
+    }
+
+    #[test]
+    fn matrix_environments_should_leave_framing_to_delimiters() {
+        use crate::snapshot_tests::{assert_parse_errors, find_item, layout_formula};
+
+        // 矩阵主体不画线，括号只来自环境自身的定界符
+        for tex in [
+            r"\begin{matrix} a & b \\ c & d \end{matrix}",
+            r"\begin{cases} x & x > 0 \\ -x & x \leq 0 \end{cases}",
+            r"\begin{pmatrix} a & b \\ c & d \end{pmatrix}",
+            r"\begin{smallmatrix} a & b \\ c & d \end{smallmatrix}",
+            r"\matrix{a & b \\ c & d}",
+        ] {
+            let plan = layout_formula(tex);
+            assert!(plan.lines.is_empty(), "{tex} 不应绘制线条");
+        }
+        let plain = layout_formula(r"\begin{matrix} a & b \\ c & d \end{matrix}");
+        let framed = layout_formula(r"\begin{pmatrix} a & b \\ c & d \end{pmatrix}");
+        assert!(plain.items.iter().all(|item| item.text != "("));
+        assert!(find_item(&framed, "(").x < find_item(&framed, "a").x);
+        assert!(find_item(&framed, ")").x > find_item(&framed, "b").x);
+
+        // 带星号的变体按 [l]/[c]/[r] 对齐列，cases 各列靠左
+        let right = layout_formula(r"\begin{pmatrix*}[r] a \\ bbb \end{pmatrix*}");
+        assert!(find_item(&right, "a").x > find_item(&right, "bbb").x);
+        let left = layout_formula(r"\begin{matrix*}[l] a \\ bbb \end{matrix*}");
+        assert_eq!(find_item(&left, "a").x, find_item(&left, "bbb").x);
+        let centered = layout_formula(r"\begin{matrix*} a \\ bbb \end{matrix*}");
+        assert!(find_item(&centered, "a").x > find_item(&centered, "bbb").x);
+        assert!(find_item(&centered, "a").x < find_item(&right, "a").x);
+        let cases = layout_formula(r"\begin{cases} a & x \\ bbb & y \end{cases}");
+        assert_eq!(find_item(&cases, "a").x, find_item(&cases, "bbb").x);
+
+        // smallmatrix 以脚标字号紧凑排版
+        let small = layout_formula(r"\begin{smallmatrix} a & b \\ c & d \end{smallmatrix}");
+        assert!(find_item(&small, "a").font_size < find_item(&plain, "a").font_size);
+        assert!(small.width < plain.width && small.height < plain.height);
+
+        assert_parse_errors(&[
+            r"\begin{pmatrix*}[x] a \end{pmatrix*}",
+            r"\begin{smallmatrix*} a \end{smallmatrix*}",
+        ]);
+    }
+
+    #[test]
+    fn substack_and_subarray_should_stack_tightly() {
+        use crate::snapshot_tests::{assert_parse_errors, find_item, layout_formula};
+
+        // 多行下限以脚标字号排在运算符下方，行距比 matrix 更紧
+        let plan = layout_formula(r"\sum_{\substack{i \\ j}} x");
+        let (sum, i, j) = (
+            find_item(&plan, "∑"),
+            find_item(&plan, "i"),
+            find_item(&plan, "j"),
+        );
+        assert!(sum.y < i.y && i.y < j.y);
+        assert!(i.font_size < sum.font_size && i.font_size == j.font_size);
+        let loose = layout_formula(r"\sum_{\begin{matrix} i \\ j \end{matrix}} x");
+        assert!(j.y - i.y < find_item(&loose, "j").y - find_item(&loose, "i").y);
+
+        // subarray 只有一列，按 l/c 对齐
+        let left = layout_formula(r"\begin{subarray}{l} a \\ bbb \end{subarray}");
+        assert_eq!(find_item(&left, "a").x, find_item(&left, "bbb").x);
+        let centered = layout_formula(r"\begin{subarray}{c} a \\ bbb \end{subarray}");
+        assert!(find_item(&centered, "a").x > find_item(&centered, "bbb").x);
+        let stacked = layout_formula(r"\substack{a \\ bbb}");
+        assert_eq!(find_item(&stacked, "a").x, find_item(&centered, "a").x);
+
+        assert_parse_errors(&[
+            r"\substack{a & b}",
+            r"\begin{subarray}{r} a \end{subarray}",
+            r"\begin{subarray}{ll} a & b \end{subarray}",
+            r"\substack",
+        ]);
+    }
+
+    #[test]
+    fn align_environments_should_line_up_relations() {
+        use crate::snapshot_tests::{assert_parse_errors, find_item, layout_formula};
+
+        // 左列右对齐、右列左对齐，关系符落在同一竖线上并补上关系间距，不画括号
+        let plan = layout_formula(r"\begin{aligned} a &= 1 \\ bbb &\leq 22 \end{aligned}");
+        assert!(plan.lines.is_empty());
+        let first = find_item(&plan, "= 1");
+        let second = find_item(&plan, "≤ 22");
+        assert_eq!(first.x, second.x);
+        assert!(find_item(&plan, "a").x > find_item(&plan, "bbb").x);
+
+        // 列对之间留 2em，alignat 不留；行间额外加 \jot，\\[..] 逐行追加
+        let pairs = layout_formula(r"\begin{align} a &= b & c &= d \end{align}");
+        let packed = layout_formula(r"\begin{alignat}{2} a &= b & c &= d \end{alignat}");
+        assert!((pairs.width - packed.width - 56.0).abs() < 0.5);
+        let array = layout_formula(r"\begin{array}{rl} a & b \\ c & d \end{array}");
+        let align = layout_formula(r"\begin{align*} a & b \\ c & d \end{align*}");
+        let spaced = layout_formula(r"\begin{align*} a & b \\[1em] c & d \end{align*}");
+        assert!((align.height - array.height - 28.0 * 0.3).abs() < 0.5);
+        assert!((spaced.height - align.height - 28.0).abs() < 0.5);
+
+        // gather 单列居中，multline 首行靠左、末行靠右
+        let gather = layout_formula(r"\begin{gather} a \\ bbb \end{gather}");
+        assert!(find_item(&gather, "a").x > find_item(&gather, "bbb").x);
+        let multline = layout_formula(r"\begin{multline} aaaa \\ b \\ cc \end{multline}");
+        assert!(find_item(&multline, "cc").x > find_item(&multline, "aaaa").x);
+        // 中间各行居中：与 gather 中同一行的位置相同
+        let centered = layout_formula(r"\begin{gather} aaaa \\ b \\ cc \end{gather}");
+        assert!(find_item(&multline, "b").x > find_item(&multline, "aaaa").x);
+        assert_eq!(find_item(&multline, "b").x, find_item(&centered, "b").x);
+
+        assert_parse_errors(&[
+            r"\begin{gather} a & b \end{gather}",
+            r"\begin{alignat}{1} a &= b & c \end{alignat}",
+            r"\begin{alignat}{0} a \end{alignat}",
+            r"\begin{aligned*} a \end{aligned*}",
+        ]);
+    }
+
+    #[test]
+    fn equation_tags_should_number_rows_and_report_labels() {
+        use crate::snapshot_tests::{
+            assert_parse_errors, find_item, layout_formula, layout_formula_with_options,
+        };
+
+        // align 逐行编号并与该行基线对齐，\notag 跳过，\tag 不占用计数
+        let tex = r"\begin{align} a &= b \\ c &= d \notag \\ e &= f \tag{A} \label{eq:e} \\ g &= h \end{align}";
+        let options = EquationOptions {
+            first_number: 4,
+            ..EquationOptions::default()
+        };
+        let plan = layout_formula_with_options(tex, &options);
+        assert_eq!(find_item(&plan, "(4)").y, find_item(&plan, "a").y);
+        assert_eq!(find_item(&plan, "(A)").y, find_item(&plan, "e").y);
+        assert_eq!(find_item(&plan, "(5)").y, find_item(&plan, "g").y);
+        assert!(plan.items.iter().all(|item| item.text != "(6)"));
+        assert!(find_item(&plan, "(4)").x > find_item(&plan, "= b").x);
+
+        {
+            let _guard = MODE_GUARD.lock().unwrap();
+            let rendered = render_equation(tex, &options).unwrap();
+            assert_eq!(rendered.next_number, 6);
+            let nested = render_equation(r"\sqrt{x\tag{7}\label{r}}", &options).unwrap();
+            assert_eq!(
+                nested.labels,
+                vec![EquationLabel {
+                    tag: Some("(7)".into()),
+                    number: None,
+                    label: Some("r".into()),
+                }]
+            );
+            assert_eq!(
+                rendered.labels,
+                vec![
+                    EquationLabel {
+                        tag: Some("(4)".into()),
+                        number: Some(4),
+                        label: None,
+                    },
+                    EquationLabel {
+                        tag: Some("(A)".into()),
+                        number: None,
+                        label: Some("eq:e".into()),
+                    },
+                    EquationLabel {
+                        tag: Some("(5)".into()),
+                        number: Some(5),
+                        label: None,
+                    },
+                ]
+            );
+        }
+
+        // equation 只有一个编号，split 中的 \tag、\label 归属整个公式；带星号的环境不自动编号
+        let plan = layout_formula(
+            r"\begin{equation} \begin{split} a &= b \\ &= c \label{s} \end{split} \end{equation}",
+        );
+        let tag = find_item(&plan, "(1)");
+        let second_row = plan
+            .items
+            .iter()
+            .find(|item| item.text.starts_with("= c"))
+            .expect("缺少第二行");
+        assert!(tag.y > find_item(&plan, "a").y && tag.y < second_row.y);
+        let starred = layout_formula(r"\begin{align*} a &= b \\ c &= d \end{align*}");
+        assert!(starred.items.iter().all(|item| !item.text.starts_with('(')));
+        let plain = layout_formula(r"E = mc^2 \tag*{[A]}");
+        assert!(plain.items.iter().any(|item| item.text == "[A]"));
+        // 花括号参数中的 \tag 与 \label 同样归属所在的行
+        let nested = layout_formula(r"\frac{a\tag{7}}{b}");
+        let tag = find_item(&nested, "(7)");
+        assert!(tag.y > find_item(&nested, "a").y && tag.y < find_item(&nested, "b").y);
+
+        // 指定显示宽度时公式居中，编号贴齐右侧或左侧
+        let wide = EquationOptions {
+            display_width: Some(600.0),
+            ..EquationOptions::default()
+        };
+        let right = layout_formula_with_options(r"x\tag{1}", &wide);
+        let left = layout_formula_with_options(
+            r"x\tag{1}",
+            &EquationOptions {
+                tag_side: TagSide::Left,
+                ..wide
+            },
+        );
+        assert_eq!(right.width, 600.0);
+        assert_eq!(left.width, 600.0);
+        assert!(find_item(&right, "(1)").x > 500.0);
+        assert!((find_item(&left, "(1)").x - 5.6).abs() < 0.01);
+        assert!((find_item(&right, "x").x - find_item(&left, "x").x).abs() < 0.01);
+        assert!((find_item(&right, "x").x - 300.0).abs() < 20.0);
+        // 没有编号时也按显示宽度居中
+        let untagged = layout_formula_with_options(r"x", &wide);
+        assert_eq!(untagged.width, 600.0);
+
+        assert_parse_errors(&[
+            r"x = \begin{align} a &= b \end{align}",
+            r"\frac{\begin{equation} a \end{equation}}{2}",
+            r"\begin{equation} \begin{gather} a \end{gather} \end{equation}",
+            r"a \tag{1} \tag{2}",
+            r"\frac{a \tag{1}}{b \tag{2}}",
+            r"\begin{align} a \label{x} & b \label{y} \end{align}",
+            r"a \label{}",
+        ]);
+    }
+
+    /// 读取测试路径的横坐标范围（路径由绝对坐标对组成）
+    fn path_horizontal_extent(d: &str) -> (f32, f32) {
+        path_coordinate_range(d, 0)
+    }
+
+    /// 读取测试路径的纵坐标范围
+    fn path_vertical_extent(d: &str) -> (f32, f32) {
+        path_coordinate_range(d, 1)
+    }
+
+    fn path_coordinate_range(d: &str, component: usize) -> (f32, f32) {
+        d.split(|ch: char| ch.is_ascii_alphabetic() || ch == ' ')
+            .filter_map(|token| token.parse::<f32>().ok())
+            .skip(component)
+            .step_by(2)
+            .fold((f32::INFINITY, f32::NEG_INFINITY), |(lo, hi), x| {
+                (lo.min(x), hi.max(x))
+            })
+    }
+
+    #[test]
+    fn cases_environments_should_lay_out_conditions() {
+        use crate::snapshot_tests::{assert_parse_errors, find_item, layout_formula};
+
+        // cases* 的第二列是文本，$...$ 内才是公式
+        let starred = layout_formula(r"\begin{cases*} x & if $x>0$ \\ 0 & otherwise \end{cases*}");
+        assert_eq!(
+            find_item(&starred, "otherwise").x,
+            find_item(&starred, "if ").x
+        );
+        assert!(find_item(&starred, "if ").x > find_item(&starred, "x").x);
+        assert_eq!(
+            find_item(&starred, "otherwise").y,
+            find_item(&starred, "0").y
+        );
+
+        // cases 中的分式按行内样式缩小，dcases 保持行间样式
+        let inline = layout_formula(r"\begin{cases} \frac{a}{b} & x \end{cases}");
+        let display = layout_formula(r"\begin{dcases} \frac{a}{b} & x \end{dcases}");
+        assert!(find_item(&inline, "a").font_size < find_item(&display, "a").font_size);
+
+        // rcases 的括号在右侧
+        let right = layout_formula(r"\begin{rcases} a & x \\ b & y \end{rcases}");
+        assert!(find_item(&right, "}").x > find_item(&right, "x").x);
+        assert!(right.items.iter().all(|item| item.text != "{"));
+
+        // numcases 逐行编号，编号与各行基线对齐
+        let numbered = layout_formula(
+            r"\begin{numcases}{f(x)=} x & if $x \geq 0$ \\ -x & otherwise \label{neg} \end{numcases}",
+        );
+        // 编号经由整体基线换算，允许浮点误差
+        assert!((find_item(&numbered, "(1)").y - find_item(&numbered, "x").y).abs() < 0.01);
+        assert!((find_item(&numbered, "(2)").y - find_item(&numbered, "otherwise").y).abs() < 0.01);
+        let lhs = numbered
+            .items
+            .iter()
+            .find(|item| item.text.starts_with('f'))
+            .expect("缺少左侧公式");
+        assert!(find_item(&numbered, "{").x > lhs.x);
+        // 行数与编号数恰好相同的普通矩阵不按 numcases 摆放编号
+        let matrix = layout_formula(r"x=\begin{pmatrix}a\end{pmatrix}\tag{1}");
+        assert_eq!(find_item(&matrix, "(1)").y, find_item(&matrix, "x=").y);
+
+        assert_parse_errors(&[
+            r"\begin{cases*} a & if $x \end{cases*}",
+            r"a + \begin{numcases}{f=} a \end{numcases}",
+        ]);
+    }
+
+    #[test]
+    fn commutative_diagrams_should_stretch_arrows_across_columns() {
+        use crate::snapshot_tests::{assert_parse_errors, find_item, layout_formula};
+
+        // 对象与横向箭头交替成列，纵向箭头对准上下两行的对象
+        let tex = r"\begin{CD} A @>f>> B \\ @VgVV @AA\beta A \\ C @<<h< D \end{CD}";
+        let plan = layout_formula(tex);
+        // 同列对象居中对齐，字宽不同导致的偏差不到 1 像素
+        assert!((find_item(&plan, "A").x - find_item(&plan, "C").x).abs() < 1.0);
+        assert_eq!(find_item(&plan, "A").y, find_item(&plan, "B").y);
+        assert!(find_item(&plan, "f").y < find_item(&plan, "A").y);
+        assert!(find_item(&plan, "h").y > find_item(&plan, "C").y);
+        assert!(find_item(&plan, "g").x < find_item(&plan, "A").x);
+        assert!(find_item(&plan, "β").x > find_item(&plan, "B").x);
+        assert!(
+            find_item(&plan, "g").y > find_item(&plan, "A").y
+                && find_item(&plan, "g").y < find_item(&plan, "C").y
+        );
+        assert_eq!(plan.paths.len(), 4);
+
+        // 横向箭头伸缩到整列宽度：长标签撑宽整列，同列的箭头一起变长
+        let short = layout_formula(r"\begin{CD} A @>>> B \\ C @>>> D \end{CD}");
+        let long = layout_formula(r"\begin{CD} A @>\text{a long label}>> B \\ C @>>> D \end{CD}");
+        assert!(long.width > short.width);
+        assert!((find_item(&long, "B").x - find_item(&long, "D").x).abs() < 1.0);
+        let arrow_widths = |plan: &layout::LayoutPlan| {
+            plan.paths
+                .iter()
+                .map(|path| path_horizontal_extent(&path.d))
+                .map(|(left, right)| right - left)
+                .collect::<Vec<_>>()
+        };
+        let widths = arrow_widths(&long);
+        assert!(
+            (widths[0] - widths[1]).abs() < 0.5,
+            "同列箭头应等长: {widths:?}"
+        );
+        assert!(widths[0] > arrow_widths(&short)[0]);
+
+        assert_parse_errors(&[
+            r"\begin{CD} A @>f> B \end{CD}",
+            r"\begin{CD} A & B \end{CD}",
+            r"\begin{CD} A @VVV B \end{CD}",
+            r"\begin{CD} A @xyz B \end{CD}",
+        ]);
+    }
+
+    #[test]
+    fn chemistry_should_build_scripts_and_reaction_arrows() {
+        use crate::snapshot_tests::{assert_parse_errors, find_item, layout_formula};
+
+        // 原子后的数字是下标，词首的数字是化学计量数
+        let water = layout_formula(r"\ce{2H2O}");
+        let twos = water
+            .items
+            .iter()
+            .filter(|item| item.text == "2")
+            .collect::<Vec<_>>();
+        assert_eq!(twos.len(), 2);
+        assert_eq!(twos[0].font_size, find_item(&water, "H").font_size);
+        assert!(twos[0].x < find_item(&water, "H").x);
+        assert!(twos[1].font_size < find_item(&water, "O").font_size);
+        assert!(twos[1].y > find_item(&water, "O").y);
+
+        // 电荷与质量数：右上角的电荷、左上/左下角的质量数与原子序数
+        let ion = layout_formula(r"\ce{SO4^2-}");
+        assert!(find_item(&ion, "2−").y < find_item(&ion, "S").y);
+        assert!(find_item(&ion, "4").y > find_item(&ion, "S").y);
+        let sodium = layout_formula(r"\ce{Na+}");
+        assert!(find_item(&sodium, "+").y < find_item(&sodium, "Na").y);
+        let isotope = layout_formula(r"\ce{^{227}_{90}Th}");
+        assert!(find_item(&isotope, "227").x < find_item(&isotope, "Th").x);
+        assert!(find_item(&isotope, "227").y < find_item(&isotope, "Th").y);
+        assert!(find_item(&isotope, "90").y > find_item(&isotope, "Th").y);
+
+        // 反应箭头、平衡箭头与带标签的堆叠箭头
+        let equation = layout_formula(r"\ce{2H2 + O2 ->[\Delta][cat] 2H2O <=> A}");
+        assert!(find_item(&equation, "Δ").y < find_item(&equation, "→").y);
+        assert!(find_item(&equation, "cat").y > find_item(&equation, "→").y);
+        assert!(equation.items.iter().any(|item| item.text.contains('⇌')));
+        let bonds = layout_formula(r"\ce{H-C#C-H}");
+        assert!(bonds.items.iter().any(|item| item.text.contains("−C≡C−")));
+        let hydrate = layout_formula(r"\ce{CuSO4*5H2O}");
+        assert!(hydrate.items.iter().any(|item| item.text.contains('·')));
+
+        // \pu：数值的 e 指数换成 10 的幂，单位后的数字是幂次
+        let quantity = layout_formula(r"\pu{1.2e3 kJ mol-1}");
+        assert!(find_item(&quantity, "3").y < find_item(&quantity, "·10").y);
+        assert!(find_item(&quantity, "−1").y < find_item(&quantity, "mol").y);
+
+        assert_parse_errors(&[
+            r"\ce{A ->[x B}",
+            r"\ce{$x}",
+            r"\ce{A\bond{?}B}",
+            r"\ce{H2O^}",
+        ]);
+    }
+
+    #[test]
+    fn physics_commands_should_build_auto_sized_brackets_and_derivatives() {
+        use crate::snapshot_tests::{assert_parse_errors, find_item, layout_formula};
+
+        // 狄拉克记号：竖线与尖括号随内容伸缩，中间的竖线与外层等高
+        let plain = layout_formula(r"\braket{\phi|\psi}");
+        let tall = layout_formula(r"\braket{\phi|\frac{A}{2}|\psi}");
+        assert!(find_item(&tall, "⟨").font_size > find_item(&plain, "⟨").font_size);
+        let bars = tall
+            .items
+            .iter()
+            .filter(|item| item.text == "|")
+            .collect::<Vec<_>>();
+        assert_eq!(bars.len(), 2);
+        assert_eq!(bars[0].font_size, find_item(&tall, "⟩").font_size);
+        let physics = layout_formula(r"\braket{\phi}{\psi}");
+        assert_eq!(find_item(&physics, "|").x, find_item(&plain, "|").x);
+        let ket = layout_formula(r"\ket{\frac{1}{2}}");
+        assert!(find_item(&ket, "⟩").font_size > find_item(&ket, "1").font_size);
+        let fixed = layout_formula(r"\abs*{\frac{1}{2}}");
+        assert_eq!(
+            find_item(&fixed, "|").font_size,
+            find_item(&plain, "⟨").font_size
+        );
+
+        // 期望值、矩阵元与对易子
+        let expval = layout_formula(r"\expval{H}{\psi}");
+        let states = expval.items.iter().filter(|item| item.text == "ψ").count();
+        assert_eq!(states, 2);
+        let comm = layout_formula(r"\comm{A}{B}");
+        assert!(find_item(&comm, "[").x < find_item(&comm, "A").x);
+        assert!(find_item(&comm, ",").x < find_item(&comm, "B").x);
+        assert!(find_item(&comm, "B").x < find_item(&comm, "]").x);
+
+        // 导数：阶数作为 d 与变量的上标，混合偏导的阶数为变量个数
+        let second = layout_formula(r"\dv[2]{f}{x}");
+        let ds = second
+            .items
+            .iter()
+            .filter(|item| item.text == "d")
+            .collect::<Vec<_>>();
+        assert_eq!(ds.len(), 2);
+        assert!(ds[0].y < ds[1].y);
+        assert!(find_item(&second, "f").y < find_item(&second, "x").y);
+        let mixed = layout_formula(r"\pdv{f}{x}{y}");
+        assert!(find_item(&mixed, "2").y < find_item(&mixed, "f").y);
+        let partials = mixed.items.iter().filter(|item| item.text == "∂").count();
+        assert_eq!(partials, 3);
+
+        // 竖线分隔的各段与其他花括号参数一样保留 \tag
+        find_item(&layout_formula(r"\braket{a \tag{3} | b}"), "(3)");
+
+        assert_parse_errors(&[r"\braket{a|b", r"\dv[2]", r"\comm{A}"]);
+    }
+
+    #[test]
+    fn siunitx_should_group_digits_and_format_units() {
+        use crate::snapshot_tests::{
+            assert_parse_errors, find_item, layout_formula, layout_formula_with_options,
+        };
+
+        // 五位及以上的整数与小数每三位以细空格分组，四位数不分组
+        let grouped = layout_formula(r"\num{1234567.891011}");
+        let chunks = ["1", "234", "567.891", "011"].map(|text| find_item(&grouped, text).x);
+        assert!(chunks.windows(2).all(|pair| pair[0] < pair[1]));
+        find_item(&layout_formula(r"\num{1234}"), "1234");
+        find_item(&layout_formula(r"\num{.5}"), "0.5");
+
+        // 指数写成 ×10 的幂，不确定度与指数同时出现时加括号
+        let exponent = layout_formula(r"\num{-1.5e-03}");
+        assert!(find_item(&exponent, "−1.5").x < find_item(&exponent, "×").x);
+        assert!(find_item(&exponent, "−3").y < find_item(&exponent, "10").y);
+        let uncertain = layout_formula(r"\num{1.2+-0.1e3}");
+        for text in ["(1.2", "±", "0.1)", "×"] {
+            find_item(&uncertain, text);
+        }
+
+        // 单位：\per 默认写成负幂次，/ 保留斜线，per-mode 可改为分式
+        let speed = layout_formula(r"\SI{3.0e8}{m/s}");
+        assert!(find_item(&speed, "/").x < find_item(&speed, "s").x);
+        let acceleration = layout_formula(r"\si{\kilo\gram\metre\per\second\squared}");
+        assert!(find_item(&acceleration, "−2").y < find_item(&acceleration, "s").y);
+        find_item(&acceleration, "kg");
+        let fraction = layout_formula(r"\si[per-mode=fraction]{\joule\per\mole\per\kelvin}");
+        assert!(find_item(&fraction, "J").y < find_item(&fraction, "mol").y);
+        assert_eq!(find_item(&fraction, "mol").y, find_item(&fraction, "K").y);
+        let angle = layout_formula(r"\SI{30}{\degree}");
+        let length = layout_formula(r"\SI{30}{\metre}");
+        assert!(find_item(&angle, "°").x < find_item(&length, "m").x);
+
+        // 小数点符号：渲染设置给出默认值，命令选项可以覆盖
+        let comma = EquationOptions {
+            decimal_marker: DecimalMarker::Comma,
+            ..EquationOptions::default()
+        };
+        find_item(&layout_formula_with_options(r"\num{3.14}", &comma), "3,14");
+        let point = layout_formula_with_options(r"\num[output-decimal-marker=.]{3,14}", &comma);
+        find_item(&point, "3.14");
+        find_item(
+            &layout_formula(r"\num[output-decimal-marker={,}]{3.14}"),
+            "3,14",
+        );
+
+        assert_parse_errors(&[
+            r"\num{abc}",
+            r"\num{}",
+            r"\num{1e}",
+            r"\num[foo=bar]{1}",
+            r"\si{\kilo}",
+            r"\si{\furlong}",
+            r"\si{^2}",
+        ]);
+    }
+
+    #[test]
+    fn enclosures_should_pad_frames_and_keep_baselines() {
+        use crate::snapshot_tests::{assert_parse_errors, find_item, layout_formula};
+
+        // 边框在主体四周留白，主体与两侧内容仍在同一基线上
+        let plain = layout_formula(r"x + a");
+        let boxed = layout_formula(r"x + \boxed{a}");
+        assert_eq!(find_item(&boxed, "x + ").y, find_item(&boxed, "a").y);
+        assert!(boxed.width > plain.width);
+        assert!(boxed.height > plain.height);
+        let frame = &boxed.paths[0];
+        assert_eq!(frame.stroke, Some("#000000"));
+        assert!(frame.x < find_item(&boxed, "a").x);
+
+        // 底色画在边框之前，文本模式参数中的 $...$ 按公式解析
+        let colored = layout_formula(r"\fcolorbox{red}{yellow}{area $x^2$}");
+        assert_eq!(colored.paths[0].fill, Some("#FFFF00"));
+        assert_eq!(colored.paths[1].stroke, Some("#FF0000"));
+        assert!(find_item(&colored, "2").y < find_item(&colored, "x").y);
+        let oval = layout_formula(r"\ovalbox{x}");
+        assert!(oval.paths[0].d.contains('A'));
+
+        // 删除线不改变主体尺寸：\cancel 一条斜线，\xcancel 两条，\sout 为横线
+        let cancel = layout_formula(r"\cancel{ab}");
+        let original = layout_formula(r"ab");
+        assert_eq!(cancel.width, original.width);
+        assert_eq!(cancel.lines.len(), 1);
+        assert!(cancel.lines[0].y1 > cancel.lines[0].y2);
+        let back = layout_formula(r"\bcancel{ab}");
+        assert!(back.lines[0].y1 < back.lines[0].y2);
+        assert_eq!(layout_formula(r"\xcancel{ab}").lines.len(), 2);
+        let strike = layout_formula(r"\sout{ab}");
+        assert_eq!(strike.lines[0].y1, strike.lines[0].y2);
+        assert!(strike.lines[0].y1 < find_item(&strike, "ab").y);
+
+        // \cancelto：箭头越过右上角，目标值以脚标字号写在箭头末端
+        let to = layout_formula(r"\cancelto{0}{x}");
+        assert!(find_item(&to, "0").font_size < find_item(&to, "x").font_size);
+        assert!(find_item(&to, "0").x > find_item(&to, "x").x);
+        assert!(find_item(&to, "0").y < find_item(&to, "x").y);
+        assert_eq!(to.paths.len(), 1);
+
+        assert_parse_errors(&[
+            r"\colorbox{chartreuse}{x}",
+            r"\fcolorbox[HTML]{FF0000}{white}{x}",
+            r"\fbox{$x}",
+            r"\cancelto{0}",
+        ]);
+    }
+
+    #[test]
+    fn phantoms_and_kerns_should_reserve_space_without_drawing() {
+        use crate::snapshot_tests::{assert_parse_errors, find_item, layout_formula};
+
+        let close = |a: f32, b: f32| (a - b).abs() < 0.01;
+
+        // \phantom 占位但不绘制，\hphantom 不撑高度，\vphantom 与 \mathstrut 零宽且不引入间距
+        let phantom = layout_formula(r"\phantom{ab}c");
+        assert_eq!(phantom.items.len(), 1);
+        assert!(close(
+            find_item(&phantom, "c").x,
+            find_item(&layout_formula(r"{ab}c"), "c").x
+        ));
+        let plain = layout_formula(r"x");
+        let tall = layout_formula(r"x\dfrac{a}{b}");
+        assert_eq!(
+            layout_formula(r"x\hphantom{\dfrac{a}{b}}").height,
+            plain.height
+        );
+        let strut = layout_formula(r"x\vphantom{\dfrac{a}{b}}");
+        assert!(close(strut.height, tall.height));
+        assert!(close(strut.width, plain.width));
+        assert!(close(layout_formula(r"x\mathstrut").width, plain.width));
+
+        // \smash 照常绘制但不计高度，[t] 只忽略基线以上的部分
+        let smashed = layout_formula(r"x\smash{\dfrac{a}{b}}");
+        assert!(close(smashed.height, plain.height));
+        assert_eq!(smashed.lines.len(), 1);
+        let top = layout_formula(r"x\smash[t]{\dfrac{a}{b}}");
+        assert!(top.height > plain.height && top.height < tall.height);
+
+        // 显式间距取代默认间距，各种单位写法等价
+        let one = layout_formula(r"a\kern1em b");
+        let two = layout_formula(r"a\kern 2em b");
+        let size = find_item(&one, "b").font_size;
+        assert!(close(find_item(&two, "b").x - find_item(&one, "b").x, size));
+        for tex in [
+            r"a\mkern18mu b",
+            r"a\hspace{1em}b",
+            r"a\mspace{18mu}b",
+            r"a\kern{10pt}b",
+        ] {
+            assert!(
+                close(
+                    find_item(&layout_formula(tex), "b").x,
+                    find_item(&one, "b").x
+                ),
+                "{tex}"
+            );
+        }
+        let negative = layout_formula(r"a\kern-1em b");
+        let advance = |plan: &layout::LayoutPlan| find_item(plan, "b").x - find_item(plan, "a").x;
+        assert!(close(advance(&one) - advance(&negative), size * 2.0));
+        // 首尾的负间距不会把内容推出画布：盒子始终覆盖全部墨迹
+        let plain = layout_formula(r"x");
+        for tex in [r"\hspace{-2em}x", r"x\hspace{-5em}"] {
+            let plan = layout_formula(tex);
+            assert!(
+                close(find_item(&plan, "x").x, find_item(&plain, "x").x),
+                "{tex}"
+            );
+            assert!(close(plan.width, plain.width), "{tex}");
+        }
+
+        // \raisebox 平移主体，\rule 画出实心矩形
+        let raised = layout_formula(r"x\raisebox{0.5em}{y}");
+        assert!(close(
+            find_item(&raised, "x").y - find_item(&raised, "y").y,
+            size * 0.5
+        ));
+        let rule = layout_formula(r"x\rule[-0.5em]{1em}{2em}");
+        assert_eq!(rule.paths[0].fill, Some("#000000"));
+        assert!(rule.paths[0].y + size * 2.0 > find_item(&rule, "x").y);
+
+        // 超长的长度被截断，画布不会随之撑到数万像素
+        let huge = layout_formula(r"\rule{99999in}{99999in}");
+        assert!(huge.width < size * 101.0 && huge.height < size * 101.0);
+        let spaced = layout_formula(r"\begin{aligned} a \\[99999in] b \end{aligned}");
+        assert!(spaced.height < size * 110.0);
+        let kerned = layout_formula(r"a\kern99999in b");
+        assert!(kerned.width < size * 110.0);
+
+        assert_parse_errors(&[
+            r"a\mkern3pt b",
+            r"a\kern b",
+            r"\hspace{1furlong}",
+            r"\smash[x]{a}",
+            r"\raisebox{1em}[2em]{x}",
+            r"\rule{1em}",
+        ]);
+    }
+
+    #[test]
+    fn spacing_commands_should_become_offsets_not_characters() {
+        use crate::snapshot_tests::{find_item, layout_formula};
+
+        let x_of = |tex: &str, text: &str| {
+            let plan = layout_formula(tex);
+            assert!(
+                plan.items.iter().all(|item| !item
+                    .text
+                    .contains(|ch| ('\u{2000}'..='\u{200A}').contains(&ch))),
+                "{tex} 不应输出空白字符"
+            );
+            let item = find_item(&plan, text);
+            (item.x, item.font_size)
+        };
+        let close = |a: f32, b: f32| (a - b).abs() < 0.01;
+
+        // 固定间距与等长的 \kern 完全一致，\! 等为负间距
+        assert!(close(x_of(r"a\quad b", "b").0, x_of(r"a\kern1em b", "b").0));
+        assert!(close(
+            x_of(r"a\qquad b", "b").0,
+            x_of(r"a\kern2em b", "b").0
+        ));
+        assert!(close(x_of(r"a\,b", "b").0, x_of(r"a\thinspace b", "b").0));
+        assert!(close(
+            x_of(r"a\!b", "b").0,
+            x_of(r"a\negthinspace b", "b").0
+        ));
+        let (thin, size) = x_of(r"a\,b", "b");
+        assert!(close(thin - x_of(r"a\!b", "b").0, size / 3.0));
+        assert!(close(x_of(r"a\;b", "b").0 - thin, size / 9.0));
+        assert!(x_of(r"a\negmedspace b", "b").0 < x_of(r"a\!b", "b").0);
+        // 公式首尾的负间距不会让内容移出画布
+        let plain = layout_formula(r"x");
+        for tex in [r"\!\!\!\!\!\!\!x", r"x\!\!\!\!\!\!\!\!\!\!\!\!"] {
+            let plan = layout_formula(tex);
+            assert!(close(x_of(tex, "x").0, find_item(&plain, "x").x), "{tex}");
+            assert!(close(plan.width, plain.width), "{tex}");
+        }
+
+        // 化学式与 siunitx 的间距同样只是偏移
+        assert!(x_of(r"\ce{A + B}", "+").0 < x_of(r"\ce{A + B}", "B").0);
+        assert!(x_of(r"\num{12345}", "12").0 < x_of(r"\num{12345}", "345").0);
+
+        let _guard = MODE_GUARD.lock().unwrap();
+        let svg = render_formula(r"a\,b\;c\quad d\ e").unwrap();
+        assert!(!svg.contains(|ch| ('\u{2000}'..='\u{200A}').contains(&ch)));
+    }
+
+    #[test]
+    fn deeply_nested_input_should_hit_limits_instead_of_overflowing() {
+        let _guard = MODE_GUARD.lock().unwrap();
+        let braces = format!("{}x{}", "{".repeat(2000), "}".repeat(2000));
+        assert!(
+            matches!(render_formula(&braces), Err(RenderError::LimitExceeded(_))),
+            "深层大括号嵌套应返回复杂度超限错误"
+        );
+
+        let scripts = format!("{}x{}", "x^{".repeat(500), "}".repeat(500));
+        assert!(
+            matches!(render_formula(&scripts), Err(RenderError::LimitExceeded(_))),
+            "深层上标嵌套应返回复杂度超限错误"
+        );
+
+        let delimiters = format!("{}x{}", "\\left(".repeat(300), "\\right)".repeat(300));
+        assert!(
+            matches!(
+                render_formula(&delimiters),
+                Err(RenderError::LimitExceeded(_))
+            ),
+            "深层定界符嵌套应返回复杂度超限错误"
+        );
+
+        let fractions = format!("{}x{}", "\\frac{".repeat(200), "}{y}".repeat(200));
+        assert!(
+            matches!(
+                render_formula(&fractions),
+                Err(RenderError::LimitExceeded(_))
+            ),
+            "深层分数嵌套应返回复杂度超限错误"
+        );
+
+        let repeated = r"\begin{array}{*{1000}{*{1000}{*{100}{c}}}c}a\end{array}";
+        assert!(
+            matches!(render_formula(repeated), Err(RenderError::LimitExceeded(_))),
+            "嵌套的列格式重复应在展开前按单元格上限报错"
+        );
+    }
+
+    #[test]
+    fn custom_limits_should_be_enforced() {
+        let _guard = MODE_GUARD.lock().unwrap();
+        override_svg_text_mode(Some(SvgTextMode::Text));
+
+        override_render_limits(Some(RenderLimits {
+            max_matrix_cells: 3,
+            ..RenderLimits::default()
+        }));
+        let matrix = render_formula("\\matrix{1 & 2 \\\\ 3 & 4}");
+        assert!(
+            matches!(matrix, Err(RenderError::LimitExceeded(_))),
+            "矩阵单元格超限应返回错误，当前结果: {matrix:?}"
+        );
+
+        override_render_limits(Some(RenderLimits {
+            max_nodes: 4,
+            ..RenderLimits::default()
+        }));
+        let nodes = render_formula("\\frac{a}{b} + \\frac{c}{d}");
+        assert!(
+            matches!(nodes, Err(RenderError::LimitExceeded(_))),
+            "节点数超限应返回错误，当前结果: {nodes:?}"
+        );
+
+        override_render_limits(Some(RenderLimits {
+            max_output_bytes: 64,
+            ..RenderLimits::default()
+        }));
+        let output = render_formula("a + b");
+        assert!(
+            matches!(output, Err(RenderError::LimitExceeded(_))),
+            "输出体积超限应返回错误，当前结果: {output:?}"
+        );
+
+        override_render_limits(None);
+        assert!(
+            render_formula("{{{{x}}}}").is_ok(),
+            "默认上限下普通嵌套应正常渲染"
+        );
+        override_svg_text_mode(None);
+    }
+
+    #[test]
+    fn work_budget_should_abort_expensive_renders() {
+        let _guard = MODE_GUARD.lock().unwrap();
+        override_svg_text_mode(Some(SvgTextMode::Text));
+        let formula = "\\begin{pmatrix}a & b & c \\\\ d & e & f \\\\ g & h & i\\end{pmatrix}";
+
+        let boxes = render_formula_with_limits(
+            formula,
+            &RenderLimits {
+                max_layout_boxes: 4,
+                ..RenderLimits::default()
+            },
+        );
+        assert!(
+            matches!(boxes, Err(RenderError::LimitExceeded(_))),
+            "布局盒超出预算应返回错误，当前结果: {boxes:?}"
+        );
+
+        let items = render_formula_with_limits(
+            formula,
+            &RenderLimits {
+                max_render_items: 5,
+                ..RenderLimits::default()
+            },
+        );
+        assert!(
+            matches!(items, Err(RenderError::LimitExceeded(_))),
+            "绘制元素超出预算应返回错误，当前结果: {items:?}"
+        );
+
+        let timed_out = render_formula_with_limits(
+            formula,
+            &RenderLimits {
+                timeout: Some(std::time::Duration::ZERO),
+                ..RenderLimits::default()
+            },
+        );
+        assert!(
+            matches!(timed_out, Err(RenderError::LimitExceeded(_))),
+            "超过截止时间应返回错误，当前结果: {timed_out:?}"
+        );
+
+        let generous = render_formula_with_limits(
+            formula,
+            &RenderLimits {
+                timeout: Some(std::time::Duration::from_secs(30)),
+                ..RenderLimits::default()
+            },
+        );
+        assert!(generous.is_ok(), "预算充足时应正常渲染");
+        override_svg_text_mode(None);
+    }
+
+    #[test]
+    fn fuzz_regressions_should_not_panic() {
+        let _guard = MODE_GUARD.lock().unwrap();
+        override_svg_text_mode(Some(SvgTextMode::Text));
+        // 来自模糊测试语料与人工构造的边界输入：允许返回错误，但不能 panic
+        for input in [
+            "\\",
+            "x^",
+            "_",
+            "{",
+            "}",
+            "\\left",
+            "\\left(",
+            "\\right)",
+            "\\sqrt[",
+            "\\sqrt[]{}",
+            "\\frac{",
+            "\\begin{",
+            "\\begin{matrix}",
+            "\\begin{matrix}\\end{matrix}",
+            "\\end{matrix}",
+            "\\matrix{&&\\\\&}",
+            "\\begin{bogus}x\\end{bogus}",
+            "a^b^c",
+            "\\mathcal{中}",
+            "\u{0}\u{200b}",
+            "\\\\\\\\",
+        ] {
+            let result = render_formula(input);
+            assert!(
+                !matches!(result, Err(RenderError::UnexpectedPanic)),
+                "输入 {input:?} 触发了 panic"
+            );
+        }
+
+        let escaped = render_formula("\\\\alpha + α").expect("转义命令与多字节字符混排渲染失败");
+        assert!(
+            escaped.contains("α + α"),
+            "双反斜杠命令归一化后不应破坏多字节字符，当前输出: {escaped}"
+        );
+        override_svg_text_mode(None);
+    }
 }
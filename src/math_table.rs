@@ -0,0 +1,361 @@
+//! MATH 表模块：读取 OpenType MATH 表中的排版常量，统一换算为相对字号的比例
+
+use std::fmt::Write as _;
+
+use once_cell::sync::Lazy;
+use ttf_parser::math::GlyphPart;
+use ttf_parser::{Face, GlyphId, OutlineBuilder};
+
+use crate::init;
+
+/// 数学排版常量，数值均为相对字号（em）的比例，使用时乘以当前字号
+#[derive(Debug, Clone, Copy)]
+pub struct MathConstants {
+    /// 一级脚标相对正文的缩放比例
+    pub script_scale: f32,
+    /// 二级脚标相对正文的缩放比例
+    pub script_script_scale: f32,
+    /// 上方注释墨迹底部与主体墨迹顶部的最小间距
+    pub upper_limit_gap_min: f32,
+    /// 上方注释基线高出主体墨迹顶部的最小距离
+    pub upper_limit_baseline_rise_min: f32,
+    /// 下方注释墨迹顶部与主体墨迹底部的最小间距
+    pub lower_limit_gap_min: f32,
+    /// 下方注释基线低于主体墨迹底部的最小距离
+    pub lower_limit_baseline_drop_min: f32,
+    /// 伸缩字形（如横向花括号）墨迹与其上方元素墨迹的最小间距
+    pub stretch_stack_gap_above_min: f32,
+    /// 伸缩字形墨迹与其下方元素墨迹的最小间距
+    pub stretch_stack_gap_below_min: f32,
+    /// 重音按原位放置时主体允许的最大高度，超出部分重音随之上移
+    pub accent_base_height: f32,
+    /// 数学轴高度，定界符与分数线以其为中心
+    pub axis_height: f32,
+    /// 根号横线与被开方式墨迹顶部的间距（取展示样式的值）
+    pub radical_vertical_gap: f32,
+    /// 根号横线粗细
+    pub radical_rule_thickness: f32,
+    /// 根号横线上方额外留白
+    pub radical_extra_ascender: f32,
+    /// 根指数左侧的间距
+    pub radical_kern_before_degree: f32,
+    /// 根指数右侧的间距，通常为负值，使根指数伸进根号的弯折处
+    pub radical_kern_after_degree: f32,
+    /// 根指数底部相对根号字形底部的抬升比例（占根号字形总高度）
+    pub radical_degree_bottom_raise: f32,
+}
+
+impl Default for MathConstants {
+    /// 字体缺少 MATH 表时的兜底值，取自 Latin Modern Math
+    fn default() -> Self {
+        Self {
+            script_scale: 0.7,
+            script_script_scale: 0.5,
+            upper_limit_gap_min: 0.2,
+            upper_limit_baseline_rise_min: 0.111,
+            lower_limit_gap_min: 0.167,
+            lower_limit_baseline_drop_min: 0.6,
+            stretch_stack_gap_above_min: 0.2,
+            stretch_stack_gap_below_min: 0.167,
+            accent_base_height: 0.45,
+            axis_height: 0.25,
+            radical_vertical_gap: 0.148,
+            radical_rule_thickness: 0.04,
+            radical_extra_ascender: 0.04,
+            radical_kern_before_degree: 0.278,
+            radical_kern_after_degree: -0.556,
+            radical_degree_bottom_raise: 0.6,
+        }
+    }
+}
+
+static CONSTANTS: Lazy<MathConstants> = Lazy::new(|| load_constants().unwrap_or_default());
+
+/// 获取当前字体的数学排版常量
+pub fn constants() -> &'static MathConstants {
+    &CONSTANTS
+}
+
+fn load_constants() -> Option<MathConstants> {
+    let face = init::math_face().ok()?;
+    let table = face.tables().math?.constants?;
+    let units = f32::from(face.units_per_em());
+    let em = |value: i16| f32::from(value) / units;
+    Some(MathConstants {
+        script_scale: f32::from(table.script_percent_scale_down()) / 100.0,
+        script_script_scale: f32::from(table.script_script_percent_scale_down()) / 100.0,
+        upper_limit_gap_min: em(table.upper_limit_gap_min().value),
+        upper_limit_baseline_rise_min: em(table.upper_limit_baseline_rise_min().value),
+        lower_limit_gap_min: em(table.lower_limit_gap_min().value),
+        lower_limit_baseline_drop_min: em(table.lower_limit_baseline_drop_min().value),
+        stretch_stack_gap_above_min: em(table.stretch_stack_gap_above_min().value),
+        stretch_stack_gap_below_min: em(table.stretch_stack_gap_below_min().value),
+        accent_base_height: em(table.accent_base_height().value),
+        axis_height: em(table.axis_height().value),
+        radical_vertical_gap: em(table.radical_display_style_vertical_gap().value),
+        radical_rule_thickness: em(table.radical_rule_thickness().value),
+        radical_extra_ascender: em(table.radical_extra_ascender().value),
+        radical_kern_before_degree: em(table.radical_kern_before_degree().value),
+        radical_kern_after_degree: em(table.radical_kern_after_degree().value),
+        radical_degree_bottom_raise: f32::from(table.radical_degree_bottom_raise_percent()) / 100.0,
+    })
+}
+
+/// 伸缩方向：横向用于花括号、宽重音与长箭头，纵向用于定界符
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Axis {
+    Horizontal,
+    Vertical,
+}
+
+/// 纵向选取变体时允许的尺寸不足比例，类似 TeX 的 `\delimitershortfall`
+const SHORTFALL: f32 = 0.01;
+
+/// 伸缩后的字形轮廓。路径以墨迹左边缘、基线为原点，y 轴向下，
+/// 纵向范围为 `-ascent..descent`，横向范围为 `0..width`
+#[derive(Debug, Clone)]
+pub struct StretchedGlyph {
+    pub d: String,
+    pub width: f32,
+    pub ascent: f32,
+    pub descent: f32,
+    /// 顶部重音附着点距墨迹左边缘的距离；仅单个字形且字体提供该数据时存在
+    pub top_accent: Option<f32>,
+}
+
+/// 查询字符的顶部重音附着点（像素，自字形原点起算），用于把重音对准斜体字母的视觉中心
+pub fn top_accent_attachment(ch: char, font_size: f32) -> Option<f32> {
+    let face = init::math_face().ok()?;
+    let glyph = face.glyph_index(ch)?;
+    let attachment = face
+        .tables()
+        .math?
+        .glyph_info?
+        .top_accent_attachments?
+        .get(glyph)?;
+    Some(f32::from(attachment.value) * font_size / f32::from(face.units_per_em()))
+}
+
+/// 挑选不超过 `max_width` 像素的最宽横向变体（宽重音的选取规则），
+/// 所有变体都更宽时使用原字形；不做部件拼装
+pub fn fit_horizontal(ch: char, max_width: f32, font_size: f32) -> Option<StretchedGlyph> {
+    let face = init::math_face().ok()?;
+    let glyph = face.glyph_index(ch)?;
+    let scale = font_size / f32::from(face.units_per_em());
+    let construction = face
+        .tables()
+        .math
+        .and_then(|math| math.variants)
+        .and_then(|variants| variants.horizontal_constructions.get(glyph));
+
+    let mut chosen = glyph;
+    for variant in construction.into_iter().flat_map(|item| item.variants) {
+        if f32::from(variant.advance_measurement) * scale > max_width {
+            break;
+        }
+        chosen = variant.variant_glyph;
+    }
+    build_outline(face, &[(chosen, 0.0, 0.0)], scale)
+}
+
+/// 把字符沿 `axis` 伸缩到至少 `target` 像素（横向为宽度、纵向为高度）：
+/// 先在 MATH 表的尺寸变体中挑选第一个足够大的，不够时用部件拼装；
+/// 字符没有伸缩信息时直接返回原字形轮廓，字体缺字时返回 `None`
+pub fn stretch_glyph(ch: char, axis: Axis, target: f32, font_size: f32) -> Option<StretchedGlyph> {
+    let face = init::math_face().ok()?;
+    let glyph = face.glyph_index(ch)?;
+    let scale = font_size / f32::from(face.units_per_em());
+    let variants = face.tables().math.and_then(|math| math.variants);
+    let construction = variants.and_then(|variants| match axis {
+        Axis::Horizontal => variants.horizontal_constructions.get(glyph),
+        Axis::Vertical => variants.vertical_constructions.get(glyph),
+    });
+
+    let Some(construction) = construction else {
+        return build_outline(face, &[(glyph, 0.0, 0.0)], scale);
+    };
+
+    let threshold = match axis {
+        Axis::Horizontal => target,
+        Axis::Vertical => target * (1.0 - SHORTFALL),
+    };
+    let mut largest = glyph;
+    for variant in construction.variants {
+        largest = variant.variant_glyph;
+        if f32::from(variant.advance_measurement) * scale >= threshold {
+            return build_outline(face, &[(largest, 0.0, 0.0)], scale);
+        }
+    }
+
+    let placements: Vec<(GlyphId, f32, f32)> = match (construction.assembly, variants) {
+        (Some(assembly), Some(variants)) => {
+            let parts: Vec<GlyphPart> = assembly.parts.into_iter().collect();
+            let min_overlap = f32::from(variants.min_connector_overlap);
+            assemble(&parts, target / scale, min_overlap)
+                .into_iter()
+                .map(|(part, offset)| match axis {
+                    Axis::Horizontal => (part.glyph_id, offset, 0.0),
+                    // 纵向部件自下而上排列
+                    Axis::Vertical => (part.glyph_id, 0.0, offset),
+                })
+                .collect()
+        }
+        _ => vec![(largest, 0.0, 0.0)],
+    };
+    build_outline(face, &placements, scale)
+}
+
+/// 部件拼装：逐步增加延伸段重复次数直到能覆盖目标尺寸，
+/// 再把多余长度均摊到各接缝的重叠量上（不小于最小重叠、不超过连接段长度）
+fn assemble(parts: &[GlyphPart], target: f32, min_overlap: f32) -> Vec<(GlyphPart, f32)> {
+    const MAX_REPEATS: usize = 64;
+
+    let mut sequence = Vec::new();
+    for repeats in 0..=MAX_REPEATS {
+        sequence = parts
+            .iter()
+            .flat_map(|part| {
+                let count = if part.part_flags.extender() {
+                    repeats
+                } else {
+                    1
+                };
+                std::iter::repeat_n(*part, count)
+            })
+            .collect();
+        let joints = sequence.len().saturating_sub(1) as f32;
+        let full: f32 = sequence
+            .iter()
+            .map(|part| f32::from(part.full_advance))
+            .sum();
+        if full - joints * min_overlap >= target {
+            break;
+        }
+    }
+
+    let joints = sequence.len().saturating_sub(1);
+    let full: f32 = sequence
+        .iter()
+        .map(|part| f32::from(part.full_advance))
+        .sum();
+    let max_overlap = sequence
+        .windows(2)
+        .map(|pair| {
+            f32::from(
+                pair[0]
+                    .end_connector_length
+                    .min(pair[1].start_connector_length),
+            )
+        })
+        .fold(f32::INFINITY, f32::min)
+        .max(min_overlap);
+    let overlap = if joints == 0 {
+        0.0
+    } else {
+        ((full - target) / joints as f32).clamp(min_overlap, max_overlap)
+    };
+
+    let mut offset = 0.0;
+    sequence
+        .into_iter()
+        .map(|part| {
+            let placed = (part, offset);
+            offset += f32::from(part.full_advance) - overlap;
+            placed
+        })
+        .collect()
+}
+
+/// 把若干字形（字体单位偏移，向右、向上为正）合并成一条像素坐标路径，
+/// 并以整体墨迹左边缘为横向原点
+fn build_outline(
+    face: &Face<'_>,
+    placements: &[(GlyphId, f32, f32)],
+    scale: f32,
+) -> Option<StretchedGlyph> {
+    let (mut min_x, mut max_x) = (f32::INFINITY, f32::NEG_INFINITY);
+    let (mut min_y, mut max_y) = (f32::INFINITY, f32::NEG_INFINITY);
+    for &(glyph, dx, dy) in placements {
+        if let Some(rect) = face.glyph_bounding_box(glyph) {
+            min_x = min_x.min(f32::from(rect.x_min) + dx);
+            max_x = max_x.max(f32::from(rect.x_max) + dx);
+            min_y = min_y.min(f32::from(rect.y_min) + dy);
+            max_y = max_y.max(f32::from(rect.y_max) + dy);
+        }
+    }
+    if min_x > max_x {
+        return None;
+    }
+
+    let mut sink = PathSink {
+        d: String::new(),
+        scale,
+        dx: 0.0,
+        dy: 0.0,
+    };
+    for &(glyph, dx, dy) in placements {
+        sink.dx = dx - min_x;
+        sink.dy = dy;
+        face.outline_glyph(glyph, &mut sink);
+    }
+    let top_accent = match placements {
+        [(glyph, _, _)] => face
+            .tables()
+            .math
+            .and_then(|math| math.glyph_info)
+            .and_then(|info| info.top_accent_attachments)
+            .and_then(|attachments| attachments.get(*glyph))
+            .map(|attachment| (f32::from(attachment.value) - min_x) * scale),
+        _ => None,
+    };
+    Some(StretchedGlyph {
+        d: sink.d,
+        width: (max_x - min_x) * scale,
+        ascent: max_y * scale,
+        descent: -min_y * scale,
+        top_accent,
+    })
+}
+
+/// ttf-parser 轮廓回调：换算为像素并翻转 y 轴后写入 SVG 路径
+struct PathSink {
+    d: String,
+    scale: f32,
+    dx: f32,
+    dy: f32,
+}
+
+impl PathSink {
+    fn point(&self, x: f32, y: f32) -> (f32, f32) {
+        ((x + self.dx) * self.scale, -(y + self.dy) * self.scale)
+    }
+}
+
+impl OutlineBuilder for PathSink {
+    fn move_to(&mut self, x: f32, y: f32) {
+        let (x, y) = self.point(x, y);
+        let _ = write!(self.d, "M{x:.2} {y:.2}");
+    }
+
+    fn line_to(&mut self, x: f32, y: f32) {
+        let (x, y) = self.point(x, y);
+        let _ = write!(self.d, "L{x:.2} {y:.2}");
+    }
+
+    fn quad_to(&mut self, x1: f32, y1: f32, x: f32, y: f32) {
+        let (x1, y1) = self.point(x1, y1);
+        let (x, y) = self.point(x, y);
+        let _ = write!(self.d, "Q{x1:.2} {y1:.2} {x:.2} {y:.2}");
+    }
+
+    fn curve_to(&mut self, x1: f32, y1: f32, x2: f32, y2: f32, x: f32, y: f32) {
+        let (x1, y1) = self.point(x1, y1);
+        let (x2, y2) = self.point(x2, y2);
+        let (x, y) = self.point(x, y);
+        let _ = write!(self.d, "C{x1:.2} {y1:.2} {x2:.2} {y2:.2} {x:.2} {y:.2}");
+    }
+
+    fn close(&mut self) {
+        self.d.push('Z');
+    }
+}
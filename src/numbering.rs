@@ -0,0 +1,67 @@
+//! 公式编号：把解析出的 `\tag` 与自动编号结算成最终显示的文本，并汇总给调用方
+
+use crate::ast::{EquationTag, RowTag};
+
+/// 一个公式编号或标签的分配结果
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct EquationLabel {
+    /// 显示的编号文本，如 `(3)`；只有 `\label` 而未编号的行为 `None`
+    pub tag: Option<String>,
+    /// 自动编号的计数值；`\tag` 指定的编号为 `None`
+    pub number: Option<u32>,
+    /// `\label{..}` 的名称
+    pub label: Option<String>,
+}
+
+/// 带编号信息的渲染结果
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RenderedFormula {
+    pub svg: String,
+    /// 按出现顺序列出的编号与标签
+    pub labels: Vec<EquationLabel>,
+    /// 下一条公式应使用的自动编号
+    pub next_number: u32,
+}
+
+/// 结算后的编号
+pub(crate) struct Numbering {
+    /// 每行显示的编号文本，与 `ParsedFormula::tags` 一一对应
+    pub rows: Vec<Option<String>>,
+    pub labels: Vec<EquationLabel>,
+    pub next_number: u32,
+}
+
+/// 从 `first_number` 起为自动编号的行依次分配编号
+pub(crate) fn assign(tags: &[RowTag], first_number: u32) -> Numbering {
+    let mut next_number = first_number;
+    let mut rows = Vec::with_capacity(tags.len());
+    let mut labels = Vec::new();
+    for row in tags {
+        let (tag, number) = match &row.tag {
+            EquationTag::None => (None, None),
+            EquationTag::Auto => {
+                let number = next_number;
+                next_number = next_number.saturating_add(1);
+                (Some(format!("({number})")), Some(number))
+            }
+            EquationTag::Custom {
+                text,
+                starred: true,
+            } => (Some(text.clone()), None),
+            EquationTag::Custom { text, .. } => (Some(format!("({text})")), None),
+        };
+        if tag.is_some() || row.label.is_some() {
+            labels.push(EquationLabel {
+                tag: tag.clone(),
+                number,
+                label: row.label.clone(),
+            });
+        }
+        rows.push(tag);
+    }
+    Numbering {
+        rows,
+        labels,
+        next_number,
+    }
+}
@@ -1,23 +1,133 @@
-use crate::ast::ParseResult;
+use crate::ast::{ArrayNode, EquationMarks, ParseResult, RowTag};
+use crate::config::{DecimalMarker, RenderBudget, RenderLimits};
 use crate::error::RenderError;
 
 pub struct Parser {
     source: Vec<char>,
     len: usize,
     pos: usize,
+    depth: usize,
+    budget: RenderBudget,
+    /// 当前分组内生效的 `\arraystretch`
+    array_stretch: f32,
+    /// 本解析器读到的 `\tag`、`\notag`、`\label`，由外层按行收集
+    marks: EquationMarks,
+    /// 顶层编号环境（`equation`、`align` 等）产生的各行编号
+    equation_rows: Option<Vec<RowTag>>,
+    /// 顶层 numcases 环境的表格，`equation_rows` 与其各行对应
+    numcases: Option<ArrayNode>,
+    /// `\num`、`\SI` 等数值命令默认的小数点符号
+    decimal_marker: DecimalMarker,
 }
 
 impl Parser {
-    pub fn new(source: &str) -> Self {
+    pub fn new(source: &str, budget: RenderBudget) -> Self {
         let chars: Vec<char> = source.chars().collect();
         let len = chars.len();
         Self {
             source: chars,
             len,
             pos: 0,
+            depth: 0,
+            budget,
+            array_stretch: 1.0,
+            marks: EquationMarks::default(),
+            equation_rows: None,
+            numcases: None,
+            decimal_marker: DecimalMarker::Point,
         }
     }
 
+    /// 为子片段创建解析器，继承当前嵌套深度与复杂度上限
+    pub(crate) fn nested(&self, source: &str) -> Self {
+        let mut parser = Self::new(source, self.budget);
+        parser.depth = self.depth;
+        parser.array_stretch = self.array_stretch;
+        parser.decimal_marker = self.decimal_marker;
+        parser
+    }
+
+    pub(crate) fn array_stretch(&self) -> f32 {
+        self.array_stretch
+    }
+
+    pub(crate) fn set_array_stretch(&mut self, stretch: f32) {
+        self.array_stretch = stretch;
+    }
+
+    pub(crate) fn decimal_marker(&self) -> DecimalMarker {
+        self.decimal_marker
+    }
+
+    pub(crate) fn set_decimal_marker(&mut self, marker: DecimalMarker) {
+        self.decimal_marker = marker;
+    }
+
+    pub(crate) fn marks_mut(&mut self) -> &mut EquationMarks {
+        &mut self.marks
+    }
+
+    pub(crate) fn take_marks(&mut self) -> EquationMarks {
+        std::mem::take(&mut self.marks)
+    }
+
+    pub(crate) fn set_equation_rows(&mut self, rows: Vec<RowTag>) {
+        self.equation_rows = Some(rows);
+    }
+
+    pub(crate) fn take_equation_rows(&mut self) -> Option<Vec<RowTag>> {
+        self.equation_rows.take()
+    }
+
+    pub(crate) fn set_numcases(&mut self, cases: ArrayNode) {
+        self.numcases = Some(cases);
+    }
+
+    pub(crate) fn take_numcases(&mut self) -> Option<ArrayNode> {
+        self.numcases.take()
+    }
+
+    /// 是否处在公式最外层（顶层分组内，未进入任何花括号、参数或环境单元格）
+    pub(crate) fn is_top_level(&self) -> bool {
+        self.depth == 1
+    }
+
+    pub(crate) fn position(&self) -> usize {
+        self.pos
+    }
+
+    /// `[start, end)` 之外的源码是否只有空白，用于确认某个结构独占整条公式
+    pub(crate) fn is_blank_outside(&self, start: usize, end: usize) -> bool {
+        let blank = |chars: &[char]| chars.iter().all(|ch| ch.is_whitespace());
+        blank(&self.source[..start.min(self.len)]) && blank(&self.source[end.min(self.len)..])
+    }
+
+    pub(crate) fn limits(&self) -> &RenderLimits {
+        &self.budget.limits
+    }
+
+    /// 协作式超时检查，供各解析循环调用
+    #[inline]
+    pub(crate) fn check_deadline(&self) -> ParseResult<()> {
+        self.budget.check_deadline()
+    }
+
+    /// 进入一层嵌套结构，超过上限时返回错误，避免递归耗尽栈空间
+    pub(crate) fn enter_nested(&mut self) -> ParseResult<()> {
+        let max_depth = self.budget.limits.max_depth;
+        if self.depth >= max_depth {
+            return Err(RenderError::LimitExceeded(format!(
+                "嵌套层数超过 {max_depth} 层"
+            )));
+        }
+        self.depth += 1;
+        Ok(())
+    }
+
+    pub(crate) fn leave_nested(&mut self) {
+        self.depth = self.depth.saturating_sub(1);
+    }
+
     #[inline]
     pub(crate) fn peek_char(&self) -> Option<char> {
         self.source.get(self.pos).copied()
@@ -25,13 +135,9 @@ impl Parser {
 
     #[inline]
     pub(crate) fn consume_char(&mut self) -> Option<char> {
-        if self.pos < self.len {
-            let ch = unsafe { *self.source.get_unchecked(self.pos) };
-            self.pos += 1;
-            Some(ch)
-        } else {
-            None
-        }
+        let ch = self.source.get(self.pos).copied()?;
+        self.pos += 1;
+        Some(ch)
     }
 
     #[inline]
@@ -39,6 +145,12 @@ impl Parser {
         self.pos = (self.pos + count).min(self.len);
     }
 
+    pub(crate) fn skip_whitespace(&mut self) {
+        while self.peek_char().is_some_and(char::is_whitespace) {
+            self.pos += 1;
+        }
+    }
+
     pub(crate) fn parse_command(&mut self) -> String {
         let mut name = String::new();
         while let Some(ch) = self.peek_char() {
@@ -74,8 +186,7 @@ impl Parser {
                 self.pos += 1;
                 let mut depth = 1;
                 let mut content = String::new();
-                while self.pos < self.len {
-                    let ch = unsafe { *self.source.get_unchecked(self.pos) };
+                while let Some(ch) = self.source.get(self.pos).copied() {
                     self.pos += 1;
                     match ch {
                         '{' => {
@@ -103,14 +214,34 @@ impl Parser {
         }
     }
 
-    pub(crate) fn starts_with_str(&self, pattern: &str) -> bool {
-        let mut idx = self.pos;
-        for ch in pattern.chars() {
-            if idx >= self.len || unsafe { *self.source.get_unchecked(idx) } != ch {
-                return false;
+    /// 读取 `[...]` 中的原始内容（支持嵌套方括号），调用前需确认下一个字符是 `[`
+    pub(crate) fn consume_bracketed_content(&mut self, context: &str) -> ParseResult<String> {
+        self.consume_char();
+        let mut depth = 0;
+        let mut content = String::new();
+        while let Some(ch) = self.consume_char() {
+            match ch {
+                '[' => {
+                    depth += 1;
+                    content.push(ch);
+                }
+                ']' if depth == 0 => return Ok(content),
+                ']' => {
+                    depth -= 1;
+                    content.push(ch);
+                }
+                _ => content.push(ch),
             }
-            idx += 1;
         }
-        true
+        Err(RenderError::ParseError(format!(
+            "{context}缺少匹配的方括号"
+        )))
+    }
+
+    pub(crate) fn starts_with_str(&self, pattern: &str) -> bool {
+        let mut remaining = self.source.get(self.pos..).unwrap_or(&[]).iter();
+        pattern
+            .chars()
+            .all(|ch| remaining.next().is_some_and(|&actual| actual == ch))
     }
 }
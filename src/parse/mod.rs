@@ -2,11 +2,16 @@ mod lexer;
 pub mod rules;
 
 use crate::ast::{AstNode, ParseResult, ParsedFormula};
+use crate::config::{EquationOptions, RenderBudget};
 use crate::error::RenderError;
 
 use lexer::Parser;
 
-pub fn parse(input: &str) -> ParseResult<ParsedFormula> {
+pub(crate) fn parse(
+    input: &str,
+    budget: &RenderBudget,
+    options: &EquationOptions,
+) -> ParseResult<ParsedFormula> {
     if input.len() > 5 * 1024 {
         return Err(RenderError::ParseError("公式长度超过 5KB 限制".into()));
     }
@@ -14,15 +19,46 @@ pub fn parse(input: &str) -> ParseResult<ParsedFormula> {
         return Err(RenderError::InvalidUtf8);
     }
 
-    let mut parser = Parser::new(input);
+    let limits = &budget.limits;
+    let mut parser = Parser::new(input, *budget);
+    parser.set_decimal_marker(options.decimal_marker);
     let ast = parser.parse_group(None)?;
-    Ok(ParsedFormula::new(parser.normalize_group(ast)))
+    let ast = parser.normalize_group(ast);
+    let numcases = parser.take_numcases();
+    let node_count =
+        ast.node_count() + numcases.as_ref().map_or(0, |cases| cases.cell_node_count());
+    if node_count > limits.max_nodes {
+        return Err(RenderError::LimitExceeded(format!(
+            "语法树节点数超过 {} 个",
+            limits.max_nodes
+        )));
+    }
+    // 顶层编号环境已按行结算；其余情况由整条公式上的 \tag、\label 决定唯一的编号
+    let marks = parser.take_marks();
+    let tags = match parser.take_equation_rows() {
+        Some(rows) => rows,
+        None if marks.is_empty() => Vec::new(),
+        None => vec![marks.resolve(false)],
+    };
+    Ok(ParsedFormula::new(ast, tags, numcases))
 }
 
 impl Parser {
     pub(crate) fn parse_group(&mut self, stop: Option<char>) -> ParseResult<AstNode> {
+        self.enter_nested()?;
+        // 与 TeX 一致，分组内的 \arraystretch 设置在分组结束后失效
+        let saved_stretch = self.array_stretch();
+        let result = self.parse_group_items(stop);
+        self.set_array_stretch(saved_stretch);
+        self.leave_nested();
+        result
+    }
+
+    fn parse_group_items(&mut self, stop: Option<char>) -> ParseResult<AstNode> {
         let mut nodes = Vec::with_capacity(16);
+        let mut infix = None;
         while let Some(ch) = self.peek_char() {
+            self.check_deadline()?;
             if let Some(end) = stop {
                 if ch == end {
                     self.consume_char();
@@ -47,7 +83,9 @@ impl Parser {
                 '\\' => {
                     self.consume_char();
                     let command = self.parse_command();
-                    if let Some(result) = rules::handle_command(self, &command) {
+                    if rules::is_infix_fraction(&command) {
+                        Self::split_infix(&mut infix, command, &mut nodes)?;
+                    } else if let Some(result) = rules::handle_command(self, &command) {
                         nodes.push(result?);
                     } else if let Some(mapped) = rules::handle_text_command(&command) {
                         nodes.push(AstNode::Text(mapped.to_string()));
@@ -65,7 +103,45 @@ impl Parser {
                 }
             }
         }
-        Ok(AstNode::Group(Self::merge_text_nodes(nodes)))
+        Ok(Self::finish_group(nodes, infix))
+    }
+
+    /// 遇到 `\over` 等中缀命令时，把已解析的内容暂存为分子；同一分组只允许出现一次
+    pub(crate) fn split_infix(
+        infix: &mut Option<(String, Vec<AstNode>)>,
+        command: String,
+        nodes: &mut Vec<AstNode>,
+    ) -> ParseResult<()> {
+        if infix.is_some() {
+            return Err(RenderError::ParseError(format!(
+                "同一分组内不能出现多个中缀分数命令，请用花括号分隔 \\{command}"
+            )));
+        }
+        *infix = Some((command, std::mem::take(nodes)));
+        Ok(())
+    }
+
+    /// 收尾分组：存在中缀命令时以其两侧内容构造分数，否则返回普通分组
+    pub(crate) fn finish_group(
+        nodes: Vec<AstNode>,
+        infix: Option<(String, Vec<AstNode>)>,
+    ) -> AstNode {
+        let Some((command, numerator)) = infix else {
+            return AstNode::Group(Self::merge_text_nodes(nodes));
+        };
+        let side = |nodes: Vec<AstNode>| {
+            let mut merged = Self::merge_text_nodes(nodes);
+            // TeX 忽略中缀命令两侧的空白
+            if let Some(AstNode::Text(text)) = merged.first_mut() {
+                *text = text.trim_start().to_string();
+            }
+            if let Some(AstNode::Text(text)) = merged.last_mut() {
+                *text = text.trim_end().to_string();
+            }
+            merged.retain(|node| !matches!(node, AstNode::Text(text) if text.is_empty()));
+            Self::normalize_group_static(AstNode::Group(merged))
+        };
+        rules::build_infix_fraction(&command, side(numerator), side(nodes))
     }
 
     pub(crate) fn parse_atom(&mut self) -> ParseResult<AstNode> {
@@ -102,11 +178,27 @@ impl Parser {
 
     pub(crate) fn parse_block(&mut self, context: &str) -> ParseResult<AstNode> {
         let content = self.consume_braced_content(context)?;
-        let mut nested = Parser::new(&content);
+        let mut nested = self.nested(&content);
         let ast = nested.parse_group(None)?;
+        self.marks_mut().merge(nested.take_marks())?;
         Ok(Self::normalize_group_static(ast))
     }
 
+    /// 解析可选的方括号参数（如 `\sqrt[3]`、`\xrightarrow[下方]`），缺省或为空时返回 `None`
+    pub(crate) fn parse_optional_block(&mut self, context: &str) -> ParseResult<Option<AstNode>> {
+        if self.peek_char() != Some('[') {
+            return Ok(None);
+        }
+        let content = self.consume_bracketed_content(context)?;
+        if content.trim().is_empty() {
+            return Ok(None);
+        }
+        let mut nested = self.nested(content.trim());
+        let ast = nested.parse_group(None)?;
+        self.marks_mut().merge(nested.take_marks())?;
+        Ok(Some(Self::normalize_group_static(ast)))
+    }
+
     pub(crate) fn normalize_group(&self, node: AstNode) -> AstNode {
         Self::normalize_group_static(node)
     }
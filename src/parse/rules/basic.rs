@@ -1,4 +1,5 @@
 use crate::ast::{AstNode, ParseResult};
+use crate::error::RenderError;
 
 use super::super::lexer::Parser;
 
@@ -7,6 +8,7 @@ pub fn handle(parser: &mut Parser, command: &str) -> Option<ParseResult<AstNode>
     match command {
         "text" => Some(handle_text_command(parser)),
         "displaystyle" => Some(Ok(AstNode::Group(Vec::new()))),
+        "renewcommand" | "def" => Some(handle_redefinition(parser)),
         _ => None,
     }
 }
@@ -15,3 +17,37 @@ fn handle_text_command(parser: &mut Parser) -> ParseResult<AstNode> {
     let content = parser.consume_braced_content("text")?;
     Ok(AstNode::Text(content))
 }
+
+/// `\renewcommand{\arraystretch}{1.5}` 或 `\def\arraystretch{1.5}`，目前只支持调整表格行距
+fn handle_redefinition(parser: &mut Parser) -> ParseResult<AstNode> {
+    let name = match parser.peek_char() {
+        Some('{') => parser
+            .consume_braced_content("重定义的命令")?
+            .trim()
+            .to_string(),
+        Some('\\') => {
+            parser.consume_char();
+            format!("\\{}", parser.parse_command())
+        }
+        _ => {
+            return Err(RenderError::ParseError(
+                "\\renewcommand 缺少要重定义的命令".into(),
+            ))
+        }
+    };
+    let value = parser.consume_braced_content("命令定义")?;
+    if name != "\\arraystretch" {
+        return Err(RenderError::ParseError(format!(
+            "暂不支持重定义 {name}，目前仅支持 \\arraystretch"
+        )));
+    }
+    let stretch = value
+        .trim()
+        .parse::<f32>()
+        .ok()
+        .filter(|stretch| stretch.is_finite() && *stretch > 0.0)
+        .ok_or_else(|| RenderError::ParseError(format!("无效的 \\arraystretch 数值 {value}")))?;
+    // 限制上限，避免极端行距撑出超大画布
+    parser.set_array_stretch(stretch.min(10.0));
+    Ok(AstNode::Group(Vec::new()))
+}
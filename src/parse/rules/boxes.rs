@@ -0,0 +1,95 @@
+//! 占位与盒子尺寸调整：`\phantom`、`\hphantom`、`\vphantom`、`\mathstrut`、`\smash`、
+//! `\raisebox` 与实心矩形 `\rule`
+
+use crate::ast::{AstNode, BoxAdjust, EquationMarks, ParseResult, RuleNode};
+use crate::error::RenderError;
+
+use super::super::lexer::Parser;
+use super::matrix::parse_text_cell;
+use super::spacing::parse_length;
+
+pub fn handle(parser: &mut Parser, command: &str) -> Option<ParseResult<AstNode>> {
+    let result = match command {
+        "phantom" => phantom(parser, command, true, true),
+        "hphantom" => phantom(parser, command, true, false),
+        "vphantom" => phantom(parser, command, false, true),
+        // 与 `\vphantom{(}` 相同：零宽，高度与深度取圆括号
+        "mathstrut" => Ok(adjusted(
+            AstNode::Text("(".into()),
+            BoxAdjust::Phantom {
+                width: false,
+                height: true,
+            },
+        )),
+        "smash" => handle_smash(parser),
+        "raisebox" => handle_raisebox(parser),
+        "rule" => handle_rule(parser),
+        _ => return None,
+    };
+    Some(result)
+}
+
+fn adjusted(body: AstNode, adjust: BoxAdjust) -> AstNode {
+    AstNode::Adjusted {
+        body: Box::new(body),
+        adjust,
+    }
+}
+
+fn phantom(parser: &mut Parser, command: &str, width: bool, height: bool) -> ParseResult<AstNode> {
+    let body = parser.parse_block(command)?;
+    Ok(adjusted(body, BoxAdjust::Phantom { width, height }))
+}
+
+/// `\smash[t]` 只忽略基线以上的高度，`\smash[b]` 只忽略深度，缺省两者都忽略
+fn handle_smash(parser: &mut Parser) -> ParseResult<AstNode> {
+    let (top, bottom) = if parser.peek_char() == Some('[') {
+        match parser.consume_bracketed_content("smash 可选参数")?.trim() {
+            "t" => (true, false),
+            "b" => (false, true),
+            "" | "tb" | "bt" => (true, true),
+            other => {
+                return Err(RenderError::ParseError(format!(
+                    "\\smash 的可选参数只能是 t 或 b，收到 {other}"
+                )))
+            }
+        }
+    } else {
+        (true, true)
+    };
+    let body = parser.parse_block("smash")?;
+    Ok(adjusted(body, BoxAdjust::Smash { top, bottom }))
+}
+
+/// `\raisebox{距离}{文本}`：参数为文本模式，其中 `$...$` 之间按公式解析
+fn handle_raisebox(parser: &mut Parser) -> ParseResult<AstNode> {
+    let shift = parser.consume_braced_content("raisebox 距离")?;
+    let shift = parse_length(&shift, "raisebox", false)?;
+    if parser.peek_char() == Some('[') {
+        return Err(RenderError::ParseError(
+            "\\raisebox 暂不支持指定高度与深度的可选参数".into(),
+        ));
+    }
+    let content = parser.consume_braced_content("raisebox")?;
+    let mut marks = EquationMarks::default();
+    let body = parse_text_cell(parser, &content, &mut marks)?;
+    parser.marks_mut().merge(marks)?;
+    Ok(adjusted(body, BoxAdjust::Raise(shift)))
+}
+
+/// `\rule[抬高]{宽}{高}`
+fn handle_rule(parser: &mut Parser) -> ParseResult<AstNode> {
+    let raise = if parser.peek_char() == Some('[') {
+        let text = parser.consume_bracketed_content("rule 抬高")?;
+        parse_length(&text, "rule", false)?
+    } else {
+        0.0
+    };
+    let width = parser.consume_braced_content("rule 宽度")?;
+    let height = parser.consume_braced_content("rule 高度")?;
+    Ok(AstNode::Rule(RuleNode {
+        width: parse_length(&width, "rule", false)?,
+        height: parse_length(&height, "rule", false)?,
+        raise,
+    }))
+}
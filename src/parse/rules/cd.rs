@@ -0,0 +1,256 @@
+use crate::ast::{
+    ArrayNode, AstNode, CdArrowNode, ColumnAlign, ColumnSeparator, EquationMarks, ParseResult,
+    RowSeparator,
+};
+use crate::error::RenderError;
+
+use super::super::lexer::Parser;
+use super::matrix::{parse_cell, split_rows};
+
+/// 交换图中对象与箭头之间的留白（以字号为单位，每侧）
+const CD_COLUMN_PADDING: f32 = 0.25;
+
+/// 一行交换图源码切分后的片段
+enum Piece {
+    /// 箭头之间的对象源码
+    Object(String),
+    /// `@.` 表示的空箭头，只占位置
+    Empty,
+    Arrow(CdArrowNode),
+}
+
+/// amscd 的 `CD` 环境：对象行中的对象与 `@>>>` 等横向箭头交替排成相间的列，
+/// `@VVV` 等纵向箭头单独成行并对准对象所在的列；箭头在排版时伸缩到整列宽度
+pub fn parse_cd(parser: &mut Parser, body: &str) -> ParseResult<ArrayNode> {
+    let max_cells = parser.limits().max_matrix_cells;
+    let mut cell_count = 0usize;
+    let mut marks = EquationMarks::default();
+    let mut rows = Vec::new();
+    let mut row_separators = vec![RowSeparator::default()];
+    for raw_row in split_rows(body) {
+        parser.check_deadline()?;
+        let extra_space = raw_row.extra_space()?;
+        if raw_row.is_blank() {
+            if let Some(boundary) = row_separators.last_mut() {
+                boundary.extra_space += extra_space;
+            }
+            continue;
+        }
+        if raw_row.cells.len() > 1 {
+            return Err(RenderError::ParseError(
+                "CD 环境中不能使用 &，对象之间请用 @>>> 等箭头分隔".into(),
+            ));
+        }
+        let pieces = split_pieces(parser, &raw_row.cells[0], &mut marks)?;
+        let row = if pieces.iter().any(is_vertical) {
+            vertical_row(pieces)?
+        } else {
+            object_row(parser, pieces, &mut marks)?
+        };
+        cell_count += row.len();
+        if cell_count > max_cells {
+            return Err(RenderError::LimitExceeded(format!(
+                "矩阵单元格数超过 {max_cells} 个"
+            )));
+        }
+        rows.push(row);
+        row_separators.push(RowSeparator {
+            extra_space,
+            ..RowSeparator::default()
+        });
+    }
+    if rows.is_empty() {
+        return Err(RenderError::ParseError("CD 环境内容不能为空".into()));
+    }
+    // 交换图本身不编号，其中的 \tag 等归属外层
+    parser.marks_mut().merge(marks)?;
+
+    let column_count = rows.iter().map(Vec::len).max().unwrap_or(1);
+    for row in &mut rows {
+        row.resize_with(column_count, || AstNode::Text(String::new()));
+    }
+    let mut column_separators = vec![ColumnSeparator::default(); column_count + 1];
+    for edge in [0, column_count] {
+        column_separators[edge].custom = Some(Box::new(AstNode::Group(Vec::new())));
+    }
+    Ok(ArrayNode {
+        rows,
+        columns: vec![ColumnAlign::Center; column_count],
+        column_separators,
+        row_separators,
+        row_stretch: parser.array_stretch(),
+        row_aligns: Vec::new(),
+        column_padding: CD_COLUMN_PADDING,
+        cell_style: None,
+    })
+}
+
+fn is_vertical(piece: &Piece) -> bool {
+    match piece {
+        Piece::Arrow(arrow) => arrow.vertical,
+        Piece::Object(_) | Piece::Empty => false,
+    }
+}
+
+/// 对象行：对象占偶数列，横向箭头占奇数列；相邻两个对象之间必须有箭头
+fn object_row(
+    parser: &Parser,
+    pieces: Vec<Piece>,
+    marks: &mut EquationMarks,
+) -> ParseResult<Vec<AstNode>> {
+    let mut cells = vec![AstNode::Text(String::new())];
+    for piece in pieces {
+        match piece {
+            Piece::Object(source) => {
+                let object = parse_cell(parser, &source, marks)?;
+                if let Some(last) = cells.last_mut() {
+                    *last = object;
+                }
+            }
+            Piece::Empty => {
+                cells.push(AstNode::Text(String::new()));
+                cells.push(AstNode::Text(String::new()));
+            }
+            Piece::Arrow(arrow) => {
+                cells.push(AstNode::CdArrow(arrow));
+                cells.push(AstNode::Text(String::new()));
+            }
+        }
+    }
+    Ok(cells)
+}
+
+/// 纵向箭头行：第 k 个箭头对准第 k 个对象，箭头之间只能有空白
+fn vertical_row(pieces: Vec<Piece>) -> ParseResult<Vec<AstNode>> {
+    let mut cells = Vec::new();
+    for piece in pieces {
+        let cell = match piece {
+            Piece::Object(source) if source.trim().is_empty() => continue,
+            Piece::Empty => AstNode::Text(String::new()),
+            Piece::Arrow(arrow) if arrow.vertical => AstNode::CdArrow(arrow),
+            _ => {
+                return Err(RenderError::ParseError(
+                    "CD 环境中纵向箭头所在的行只能包含 @VVV、@AAA、@| 与 @.".into(),
+                ))
+            }
+        };
+        if !cells.is_empty() {
+            cells.push(AstNode::Text(String::new()));
+        }
+        cells.push(cell);
+    }
+    Ok(cells)
+}
+
+/// 按顶层的 `@` 箭头切分一行；花括号内的 `@` 保持原样
+fn split_pieces(
+    parser: &Parser,
+    source: &str,
+    marks: &mut EquationMarks,
+) -> ParseResult<Vec<Piece>> {
+    let chars: Vec<char> = source.chars().collect();
+    let mut pieces = Vec::new();
+    let mut current = String::new();
+    let mut depth = 0usize;
+    let mut index = 0;
+    while index < chars.len() {
+        let ch = chars[index];
+        index += 1;
+        match ch {
+            '{' => depth += 1,
+            '}' => depth = depth.saturating_sub(1),
+            '\\' => {
+                // 转义字符（如 \@ \{）整体复制，不参与切分
+                current.push(ch);
+                if let Some(&next) = chars.get(index) {
+                    current.push(next);
+                    index += 1;
+                }
+                continue;
+            }
+            '@' if depth == 0 => {
+                pieces.push(Piece::Object(std::mem::take(&mut current)));
+                let Some(&kind) = chars.get(index) else {
+                    return Err(RenderError::ParseError("CD 环境中 @ 后缺少箭头".into()));
+                };
+                index += 1;
+                let piece = match kind {
+                    '.' => Piece::Empty,
+                    '=' => Piece::Arrow(arrow('=', false, None, None)),
+                    '|' => Piece::Arrow(arrow('‖', true, None, None)),
+                    '>' | '<' | 'V' | 'A' => {
+                        let before = take_label(&chars, &mut index, kind)?;
+                        let after = take_label(&chars, &mut index, kind)?;
+                        let glyph = match kind {
+                            '>' => '→',
+                            '<' => '←',
+                            'V' => '↓',
+                            _ => '↑',
+                        };
+                        Piece::Arrow(arrow(
+                            glyph,
+                            matches!(kind, 'V' | 'A'),
+                            parse_label(parser, &before, marks)?,
+                            parse_label(parser, &after, marks)?,
+                        ))
+                    }
+                    other => {
+                        return Err(RenderError::ParseError(format!(
+                            "CD 环境不支持箭头 @{other}"
+                        )))
+                    }
+                };
+                pieces.push(piece);
+                continue;
+            }
+            _ => {}
+        }
+        current.push(ch);
+    }
+    pieces.push(Piece::Object(current));
+    Ok(pieces)
+}
+
+fn arrow(
+    glyph: char,
+    vertical: bool,
+    before: Option<AstNode>,
+    after: Option<AstNode>,
+) -> CdArrowNode {
+    CdArrowNode {
+        glyph,
+        vertical,
+        before: before.map(Box::new),
+        after: after.map(Box::new),
+    }
+}
+
+/// 读取箭头中到下一个顶层 `kind` 为止的标签，如 `@>a>b>` 中的 `a` 与 `b`
+fn take_label(chars: &[char], index: &mut usize, kind: char) -> ParseResult<String> {
+    let mut label = String::new();
+    let mut depth = 0usize;
+    while let Some(&ch) = chars.get(*index) {
+        *index += 1;
+        match ch {
+            '{' => depth += 1,
+            '}' => depth = depth.saturating_sub(1),
+            _ if ch == kind && depth == 0 => return Ok(label),
+            _ => {}
+        }
+        label.push(ch);
+    }
+    Err(RenderError::ParseError(format!(
+        "CD 箭头 @{kind} 缺少结尾的 {kind}"
+    )))
+}
+
+fn parse_label(
+    parser: &Parser,
+    source: &str,
+    marks: &mut EquationMarks,
+) -> ParseResult<Option<AstNode>> {
+    if source.trim().is_empty() {
+        return Ok(None);
+    }
+    parse_cell(parser, source, marks).map(Some)
+}
@@ -0,0 +1,462 @@
+//! mhchem 风格的化学式：`\ce{..}` 解析化学方程式，`\pu{..}` 解析带单位的物理量。
+//! 两者都只生成已有的语法树节点（直立文字、上下标与堆叠箭头）
+
+use crate::ast::{AstNode, ParseResult};
+use crate::error::RenderError;
+
+use super::super::lexer::Parser;
+use super::decorations::stacked;
+use super::spacing::{MEDIUM_SPACE, THICK_SPACE, THIN_SPACE};
+
+pub fn handle(parser: &mut Parser, command: &str) -> Option<ParseResult<AstNode>> {
+    match command {
+        "ce" => Some(handle_ce(parser)),
+        "pu" => Some(handle_pu(parser)),
+        _ => None,
+    }
+}
+
+fn handle_ce(parser: &mut Parser) -> ParseResult<AstNode> {
+    let content = parser.consume_braced_content("ce")?;
+    parse_equation(parser, &content)
+}
+
+fn handle_pu(parser: &mut Parser) -> ParseResult<AstNode> {
+    let content = parser.consume_braced_content("pu")?;
+    parse_quantity(parser, &content)
+}
+
+/// 反应箭头，按从长到短的顺序匹配
+const ARROWS: &[(&str, &str)] = &[
+    ("<=>>", "⥂"),
+    ("<<=>", "⥄"),
+    ("<-->", "⇄"),
+    ("<=>", "⇌"),
+    ("<->", "↔"),
+    ("->", "→"),
+    ("<-", "←"),
+];
+
+/// 化学式源码的游标，siunitx 的单位解析也复用它
+pub(super) struct Cursor {
+    chars: Vec<char>,
+    pos: usize,
+}
+
+impl Cursor {
+    pub(super) fn new(source: &str) -> Self {
+        Self {
+            chars: source.chars().collect(),
+            pos: 0,
+        }
+    }
+
+    pub(super) fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn peek_at(&self, offset: usize) -> Option<char> {
+        self.chars.get(self.pos + offset).copied()
+    }
+
+    pub(super) fn bump(&mut self) -> Option<char> {
+        let ch = self.peek()?;
+        self.pos += 1;
+        Some(ch)
+    }
+
+    fn starts_with(&self, pattern: &str) -> bool {
+        let mut remaining = self.chars[self.pos..].iter();
+        pattern
+            .chars()
+            .all(|ch| remaining.next().is_some_and(|&actual| actual == ch))
+    }
+
+    /// `offset` 处是否为词尾（空白或结尾）
+    fn at_boundary(&self, offset: usize) -> bool {
+        self.peek_at(offset).is_none_or(char::is_whitespace)
+    }
+
+    pub(super) fn skip_whitespace(&mut self) {
+        while self.peek().is_some_and(char::is_whitespace) {
+            self.pos += 1;
+        }
+    }
+
+    pub(super) fn take_while(&mut self, accept: impl Fn(char) -> bool) -> String {
+        let start = self.pos;
+        while self.peek().is_some_and(&accept) {
+            self.pos += 1;
+        }
+        self.chars[start..self.pos].iter().collect()
+    }
+
+    /// 读取以 `open` 开头、与 `close` 配对的内容（支持嵌套），调用前需确认下一个字符是 `open`
+    pub(super) fn take_group(
+        &mut self,
+        open: char,
+        close: char,
+        context: &str,
+    ) -> ParseResult<String> {
+        self.bump();
+        let mut depth = 0usize;
+        let mut content = String::new();
+        while let Some(ch) = self.bump() {
+            if ch == close && depth == 0 {
+                return Ok(content);
+            }
+            if ch == open {
+                depth += 1;
+            } else if ch == close {
+                depth -= 1;
+            }
+            content.push(ch);
+        }
+        Err(RenderError::ParseError(format!(
+            "{context} 缺少匹配的 {close}"
+        )))
+    }
+
+    /// 读取 `$...$` 之间的公式源码，调用前需确认下一个字符是 `$`
+    fn take_math(&mut self) -> ParseResult<String> {
+        self.bump();
+        let math = self.take_while(|ch| ch != '$');
+        if self.bump() != Some('$') {
+            return Err(RenderError::ParseError("\\ce 中的 $ 未配对".into()));
+        }
+        Ok(math)
+    }
+
+    /// 读取 `\命令` 及其后紧跟的花括号参数，原样返回源码
+    pub(super) fn take_command(&mut self) -> ParseResult<(String, String)> {
+        let start = self.pos;
+        self.bump();
+        let mut name = self.take_while(|ch| ch.is_ascii_alphabetic());
+        if name.is_empty() {
+            name.extend(self.bump());
+        }
+        while self.peek() == Some('{') {
+            self.take_group('{', '}', &format!("\\{name}"))?;
+        }
+        Ok((name, self.chars[start..self.pos].iter().collect()))
+    }
+
+    fn arrow(&self) -> Option<(&'static str, &'static str)> {
+        ARROWS
+            .iter()
+            .copied()
+            .find(|(pattern, _)| self.starts_with(pattern))
+    }
+}
+
+/// `\ce{..}` 的内容：以空白分隔的物种、运算符与反应箭头
+fn parse_equation(parser: &Parser, source: &str) -> ParseResult<AstNode> {
+    let mut scope = parser.nested(source);
+    scope.enter_nested()?;
+    let mut cursor = Cursor::new(source);
+    let mut nodes = Vec::new();
+    loop {
+        scope.check_deadline()?;
+        cursor.skip_whitespace();
+        let Some(ch) = cursor.peek() else {
+            break;
+        };
+        if let Some((pattern, glyph)) = cursor.arrow() {
+            cursor.pos += pattern.chars().count();
+            parse_arrow(&scope, &mut cursor, glyph, &mut nodes)?;
+            continue;
+        }
+        if cursor.at_boundary(1) {
+            // 单独成词的 `+`、`=` 是运算符，`v`、`^` 表示沉淀与气体
+            let operator = match ch {
+                '+' => Some(("+", MEDIUM_SPACE)),
+                '=' => Some(("=", THICK_SPACE)),
+                'v' => Some(("↓", 0.0)),
+                '^' => Some(("↑", 0.0)),
+                _ => None,
+            };
+            if let Some((glyph, space)) = operator {
+                cursor.bump();
+                push_spaced(&mut nodes, AstNode::Text(glyph.into()), space);
+                continue;
+            }
+        }
+        parse_species(&scope, &mut cursor, &mut nodes)?;
+    }
+    Ok(Parser::normalize_group_static(AstNode::Group(
+        Parser::merge_text_nodes(nodes),
+    )))
+}
+
+/// 两侧加上 `\:`、`\;` 等间距的运算符，间距为 0 时不加
+fn push_spaced(nodes: &mut Vec<AstNode>, operator: AstNode, space: f32) {
+    if space == 0.0 {
+        nodes.push(operator);
+        return;
+    }
+    nodes.extend([AstNode::Kern(space), operator, AstNode::Kern(space)]);
+}
+
+/// 反应箭头及其 `[上方][下方]` 标签，标签同样按化学式解析；箭头连同两侧的间距追加到 `nodes`
+fn parse_arrow(
+    scope: &Parser,
+    cursor: &mut Cursor,
+    glyph: &str,
+    nodes: &mut Vec<AstNode>,
+) -> ParseResult<()> {
+    let mut labels = [None, None];
+    for label in &mut labels {
+        if cursor.peek() != Some('[') {
+            break;
+        }
+        let source = cursor.take_group('[', ']', "反应箭头标签")?;
+        if !source.trim().is_empty() {
+            *label = Some(parse_equation(scope, &source)?);
+        }
+    }
+    let [above, below] = labels;
+    let arrow = if above.is_none() && below.is_none() {
+        AstNode::Text(glyph.to_string())
+    } else {
+        stacked(AstNode::Text(glyph.to_string()), above, below)
+    };
+    push_spaced(nodes, arrow, THICK_SPACE);
+    Ok(())
+}
+
+/// 一个物种，如 `2H2O`、`^{14}C`、`[Cu(NH3)4]^2+`、`CuSO4*5H2O`；读到空白或反应箭头为止
+fn parse_species(scope: &Parser, cursor: &mut Cursor, nodes: &mut Vec<AstNode>) -> ParseResult<()> {
+    if matches!(cursor.peek(), Some('^' | '_')) {
+        // 左上角的质量数与左下角的原子序数
+        let mut scripts = vec![AstNode::Text(String::new())];
+        while let Some(mark @ ('^' | '_')) = cursor.peek() {
+            cursor.bump();
+            let script = read_script(scope, cursor, mark)?;
+            Parser::attach_script(&mut scripts, mark, script)?;
+        }
+        nodes.extend(scripts);
+    }
+    // 数字跟在原子或右括号之后表示原子个数（下标），否则是化学计量数
+    let mut after_atom = false;
+    while let Some(ch) = cursor.peek() {
+        scope.check_deadline()?;
+        if ch.is_whitespace() || cursor.arrow().is_some() {
+            break;
+        }
+        match ch {
+            _ if ch.is_alphabetic() => {
+                cursor.bump();
+                let mut atom = ch.to_string();
+                atom.push_str(&cursor.take_while(|next| next.is_lowercase()));
+                nodes.push(AstNode::Text(atom));
+                after_atom = true;
+            }
+            _ if ch.is_ascii_digit() => {
+                let digits = cursor.take_while(|next| next.is_ascii_digit());
+                if after_atom {
+                    Parser::attach_script(nodes, '_', AstNode::Text(digits))?;
+                } else {
+                    let fraction = cursor.take_while(|next| next.is_ascii_digit() || next == '/');
+                    nodes.push(AstNode::Text(digits + &fraction));
+                }
+            }
+            ')' | ']' => {
+                cursor.bump();
+                nodes.push(AstNode::Text(ch.to_string()));
+                after_atom = true;
+            }
+            '^' | '_' => {
+                cursor.bump();
+                let script = read_script(scope, cursor, ch)?;
+                Parser::attach_script(nodes, ch, script)?;
+            }
+            // 原子之后、位于词尾的 `+`/`-` 是电荷，如 `Na+`、`NH4+`、`e-`
+            '+' | '-' if after_atom && is_trailing_charge(cursor) => {
+                let charge = cursor.take_while(|next| matches!(next, '+' | '-'));
+                Parser::attach_script(nodes, '^', AstNode::Text(charge.replace('-', "−")))?;
+            }
+            '-' | '=' | '#' => {
+                cursor.bump();
+                nodes.push(AstNode::Text(bond(&ch.to_string())?.to_string()));
+                after_atom = false;
+            }
+            // 结晶水等加合物的居中点
+            '*' | '.' => {
+                cursor.bump();
+                nodes.push(AstNode::Text("·".into()));
+                after_atom = false;
+            }
+            '$' => {
+                let math = cursor.take_math()?;
+                nodes.push(parse_math(scope, &math)?);
+                after_atom = true;
+            }
+            '\\' => {
+                let (name, source) = cursor.take_command()?;
+                if name == "bond" {
+                    let kind = source["\\bond".len()..]
+                        .trim_start_matches('{')
+                        .trim_end_matches('}');
+                    nodes.push(AstNode::Text(bond(kind)?.to_string()));
+                    after_atom = false;
+                } else {
+                    nodes.push(parse_math(scope, &source)?);
+                    after_atom = true;
+                }
+            }
+            '{' => {
+                let group = cursor.take_group('{', '}', "\\ce 分组")?;
+                nodes.push(parse_equation(scope, &group)?);
+                after_atom = true;
+            }
+            _ => {
+                cursor.bump();
+                nodes.push(AstNode::Text(ch.to_string()));
+                after_atom = ch == ')';
+            }
+        }
+    }
+    Ok(())
+}
+
+/// 连续的 `+`/`-` 之后是否到了词尾、反应箭头或状态标注 `(aq)`
+fn is_trailing_charge(cursor: &Cursor) -> bool {
+    let mut offset = 0;
+    while matches!(cursor.peek_at(offset), Some('+' | '-')) {
+        offset += 1;
+    }
+    cursor.at_boundary(offset) || cursor.peek_at(offset) == Some('(')
+}
+
+/// 化学键：`-` 单键、`=` 双键、`#` 三键，`\bond{..}` 还支持 `~` 与 `...`
+fn bond(kind: &str) -> ParseResult<&'static str> {
+    match kind {
+        "-" | "1" => Ok("−"),
+        "=" | "2" => Ok("="),
+        "#" | "3" => Ok("≡"),
+        "~" => Ok("∼"),
+        "..." => Ok("⋯"),
+        "->" => Ok("→"),
+        "<-" => Ok("←"),
+        other => Err(RenderError::ParseError(format!("不支持的化学键 {other}"))),
+    }
+}
+
+/// 读取 `^`/`_` 之后的内容：花括号分组，或一串数字与电荷符号（如 `^2-`），或单个字符
+fn read_script(scope: &Parser, cursor: &mut Cursor, mark: char) -> ParseResult<AstNode> {
+    let text = match cursor.peek() {
+        Some('{') => cursor.take_group('{', '}', "上下标")?,
+        Some('$') => return parse_math(scope, &cursor.take_math()?),
+        Some('\\') => return parse_math(scope, &cursor.take_command()?.1),
+        _ => {
+            let run = cursor
+                .take_while(|ch| ch.is_ascii_digit() || (mark == '^' && matches!(ch, '+' | '-')));
+            if run.is_empty() {
+                cursor
+                    .bump()
+                    .map(String::from)
+                    .ok_or_else(|| RenderError::ParseError("\\ce 上下标缺少内容".into()))?
+            } else {
+                run
+            }
+        }
+    };
+    if text.contains(['\\', '$']) {
+        return parse_math(scope, &text.replace('$', ""));
+    }
+    Ok(AstNode::Text(text.replace('-', "−")))
+}
+
+/// `$...$` 与 `\alpha` 等命令按普通公式解析
+fn parse_math(scope: &Parser, source: &str) -> ParseResult<AstNode> {
+    let mut nested = scope.nested(source);
+    let ast = nested.parse_group(None)?;
+    Ok(Parser::normalize_group_static(ast))
+}
+
+/// `\pu{..}` 的内容：数值（可带 `e` 指数）后接单位，如 `1.2e3 kJ/mol`、`9.81 m s-2`
+fn parse_quantity(parser: &Parser, source: &str) -> ParseResult<AstNode> {
+    let mut scope = parser.nested(source);
+    scope.enter_nested()?;
+    let mut cursor = Cursor::new(source.trim());
+    let mut nodes = Vec::new();
+
+    let mut mantissa = cursor.take_while(|ch| matches!(ch, '+' | '-'));
+    mantissa.push_str(&cursor.take_while(|ch| ch.is_ascii_digit() || matches!(ch, '.' | ',')));
+    if !mantissa.is_empty() {
+        nodes.push(AstNode::Text(mantissa.replace('-', "−")));
+        let has_exponent = matches!(cursor.peek(), Some('e' | 'E'))
+            && matches!(cursor.peek_at(1), Some(next) if next.is_ascii_digit() || next == '-' || next == '+');
+        if has_exponent {
+            cursor.bump();
+            let mut exponent = cursor.take_while(|ch| matches!(ch, '+' | '-'));
+            exponent.push_str(&cursor.take_while(|ch| ch.is_ascii_digit()));
+            nodes.push(AstNode::Text("·10".into()));
+            Parser::attach_script(&mut nodes, '^', AstNode::Text(exponent.replace('-', "−")))?;
+        }
+        cursor.skip_whitespace();
+        if cursor.peek().is_some() {
+            nodes.push(AstNode::Kern(THIN_SPACE));
+        }
+    }
+    parse_units(&scope, &mut cursor, &mut nodes)?;
+    Ok(Parser::normalize_group_static(AstNode::Group(
+        Parser::merge_text_nodes(nodes),
+    )))
+}
+
+/// 单位部分：字母后紧跟的（可带负号的）数字是幂次，空白为细空格，`*`、`.` 为居中点
+fn parse_units(scope: &Parser, cursor: &mut Cursor, nodes: &mut Vec<AstNode>) -> ParseResult<()> {
+    while let Some(ch) = cursor.peek() {
+        scope.check_deadline()?;
+        match ch {
+            _ if ch.is_whitespace() => {
+                cursor.skip_whitespace();
+                if cursor.peek().is_some() {
+                    nodes.push(AstNode::Kern(THIN_SPACE));
+                }
+            }
+            _ if ch.is_alphabetic() || matches!(ch, '°' | '%' | '\'') => {
+                nodes.push(AstNode::Text(cursor.take_while(|next| {
+                    next.is_alphabetic() || matches!(next, '°' | '%' | '\'')
+                })));
+                let negative = cursor.peek() == Some('-')
+                    && cursor.peek_at(1).is_some_and(|next| next.is_ascii_digit());
+                if negative || cursor.peek().is_some_and(|next| next.is_ascii_digit()) {
+                    let sign = if negative {
+                        cursor.bump();
+                        "−"
+                    } else {
+                        ""
+                    };
+                    let power = cursor.take_while(|next| next.is_ascii_digit());
+                    Parser::attach_script(nodes, '^', AstNode::Text(format!("{sign}{power}")))?;
+                }
+            }
+            '^' => {
+                cursor.bump();
+                let script = read_script(scope, cursor, '^')?;
+                Parser::attach_script(nodes, '^', script)?;
+            }
+            '*' | '.' => {
+                cursor.bump();
+                nodes.push(AstNode::Text("·".into()));
+            }
+            '\\' => {
+                let (_, source) = cursor.take_command()?;
+                nodes.push(parse_math(scope, &source)?);
+            }
+            '{' => {
+                let group = cursor.take_group('{', '}', "\\pu 分组")?;
+                let mut inner_scope = scope.nested(&group);
+                inner_scope.enter_nested()?;
+                parse_units(&inner_scope, &mut Cursor::new(&group), nodes)?;
+            }
+            _ => {
+                cursor.bump();
+                nodes.push(AstNode::Text(ch.to_string()));
+            }
+        }
+    }
+    Ok(())
+}
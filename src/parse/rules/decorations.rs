@@ -9,6 +9,9 @@ pub fn handle(parser: &mut Parser, command: &str) -> Option<ParseResult<AstNode>
         "xRightarrow" => return Some(handle_stacked_arrow(parser, "⇒")),
         "xLeftarrow" => return Some(handle_stacked_arrow(parser, "⇐")),
         "xLeftrightarrow" => return Some(handle_stacked_arrow(parser, "⇔")),
+        "overset" | "stackrel" => return Some(handle_overset(parser)),
+        "underset" => return Some(handle_underset(parser)),
+        "overunderset" => return Some(handle_overunderset(parser)),
         _ => {}
     }
 
@@ -21,8 +24,31 @@ pub fn handle(parser: &mut Parser, command: &str) -> Option<ParseResult<AstNode>
         "vec" => Some(DecorationKind::Vector),
         "dot" => Some(DecorationKind::Dot),
         "ddot" => Some(DecorationKind::Ddot),
+        "dddot" => Some(DecorationKind::Dddot),
+        "ddddot" => Some(DecorationKind::Ddddot),
+        "check" => Some(DecorationKind::Check),
+        "acute" => Some(DecorationKind::Acute),
+        "grave" => Some(DecorationKind::Grave),
+        "breve" => Some(DecorationKind::Breve),
+        "mathring" => Some(DecorationKind::MathRing),
         "overbrace" => Some(DecorationKind::Overbrace),
         "underbrace" => Some(DecorationKind::Underbrace),
+        "overbracket" => Some(DecorationKind::Overbracket),
+        "underbracket" => Some(DecorationKind::Underbracket),
+        "overparen" => Some(DecorationKind::Overparen),
+        "underparen" => Some(DecorationKind::Underparen),
+        "widehat" => Some(DecorationKind::WideHat),
+        "widetilde" => Some(DecorationKind::WideTilde),
+        "widecheck" => Some(DecorationKind::WideCheck),
+        "overrightarrow" => Some(DecorationKind::OverRightArrow),
+        "overleftarrow" => Some(DecorationKind::OverLeftArrow),
+        "overleftrightarrow" => Some(DecorationKind::OverLeftRightArrow),
+        "overleftharpoon" => Some(DecorationKind::OverLeftHarpoon),
+        "overrightharpoon" => Some(DecorationKind::OverRightHarpoon),
+        "underrightarrow" => Some(DecorationKind::UnderRightArrow),
+        "underleftarrow" => Some(DecorationKind::UnderLeftArrow),
+        "underleftrightarrow" => Some(DecorationKind::UnderLeftRightArrow),
+        "utilde" => Some(DecorationKind::UnderTilde),
         _ => None,
     }?;
 
@@ -38,10 +64,41 @@ fn handle_decorated(parser: &mut Parser, decoration: DecorationKind) -> ParseRes
 }
 
 fn handle_stacked_arrow(parser: &mut Parser, arrow: &str) -> ParseResult<AstNode> {
-    let label = parser.parse_block("箭头标签")?;
-    Ok(AstNode::Scripts {
-        base: Box::new(AstNode::Text(arrow.to_string())),
-        superscript: Some(Box::new(label)),
-        subscript: None,
-    })
+    let below = parser.parse_optional_block("箭头下方标签")?;
+    let above = parser.parse_block("箭头标签")?;
+    Ok(stacked(
+        AstNode::Text(arrow.to_string()),
+        Some(above),
+        below,
+    ))
+}
+
+/// `\overset{上方}{主体}`，`\stackrel` 与之等价
+fn handle_overset(parser: &mut Parser) -> ParseResult<AstNode> {
+    let over = parser.parse_block("上方注释")?;
+    let base = parser.parse_block("堆叠主体")?;
+    Ok(stacked(base, Some(over), None))
+}
+
+/// `\underset{下方}{主体}`
+fn handle_underset(parser: &mut Parser) -> ParseResult<AstNode> {
+    let under = parser.parse_block("下方注释")?;
+    let base = parser.parse_block("堆叠主体")?;
+    Ok(stacked(base, None, Some(under)))
+}
+
+/// `\overunderset{上方}{下方}{主体}`
+fn handle_overunderset(parser: &mut Parser) -> ParseResult<AstNode> {
+    let over = parser.parse_block("上方注释")?;
+    let under = parser.parse_block("下方注释")?;
+    let base = parser.parse_block("堆叠主体")?;
+    Ok(stacked(base, Some(over), Some(under)))
+}
+
+pub(super) fn stacked(base: AstNode, over: Option<AstNode>, under: Option<AstNode>) -> AstNode {
+    AstNode::UnderOver {
+        base: Box::new(base),
+        over: over.map(Box::new),
+        under: under.map(Box::new),
+    }
 }
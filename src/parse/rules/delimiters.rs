@@ -1,21 +1,59 @@
-use crate::ast::{AstNode, Delimiter, ParseResult};
+use crate::ast::{AstNode, Delimiter, ParseResult, SizedDelimiterNode};
 use crate::error::RenderError;
 
 use super::super::lexer::Parser;
-use super::{build_large_operator, handle_command, handle_text_command, is_large_operator};
+use super::{
+    build_large_operator, handle_command, handle_text_command, is_infix_fraction, is_large_operator,
+};
 
 pub fn handle(parser: &mut Parser, command: &str) -> Option<ParseResult<AstNode>> {
     match command {
         "left" => Some(parser.parse_delimited_expression()),
-        _ => None,
+        "middle" => Some(Err(RenderError::ParseError(
+            "\\middle 必须位于 \\left 与 \\right 之间".into(),
+        ))),
+        _ => {
+            let (height, spacing) = sized_delimiter_spec(command)?;
+            Some(parse_delimiter_token(parser).map(|delimiter| {
+                AstNode::SizedDelimiter(SizedDelimiterNode {
+                    delimiter,
+                    height,
+                    spacing,
+                })
+            }))
+        }
     }
 }
 
+/// `\big` 系列的高度（以字号为单位）与两侧间距；l/r 只影响 TeX 的原子类型，
+/// 这里与无后缀版本相同，m 版本按关系符两侧各留 5/18 em
+fn sized_delimiter_spec(command: &str) -> Option<(f32, f32)> {
+    const SIZES: [(&str, f32); 4] = [("Bigg", 3.0), ("bigg", 2.4), ("Big", 1.8), ("big", 1.2)];
+    let (prefix, height) = SIZES
+        .iter()
+        .find(|(prefix, _)| command.starts_with(prefix))?;
+    let spacing = match &command[prefix.len()..] {
+        "" | "l" | "r" => 0.0,
+        "m" => 5.0 / 18.0,
+        _ => return None,
+    };
+    Some((*height, spacing))
+}
+
 impl Parser {
     pub(crate) fn parse_delimited_expression(&mut self) -> ParseResult<AstNode> {
+        self.enter_nested()?;
+        let result = self.parse_delimited_body();
+        self.leave_nested();
+        result
+    }
+
+    fn parse_delimited_body(&mut self) -> ParseResult<AstNode> {
         let left = parse_delimiter_token(self)?;
         let mut nodes = Vec::new();
+        let mut infix = None;
         loop {
+            self.check_deadline()?;
             let ch = self
                 .peek_char()
                 .ok_or_else(|| RenderError::ParseError("缺少与 \\left 对应的 \\right".into()))?;
@@ -36,11 +74,18 @@ impl Parser {
                 '\\' => {
                     self.consume_char();
                     let command = self.parse_command();
+                    if is_infix_fraction(&command) {
+                        Parser::split_infix(&mut infix, command, &mut nodes)?;
+                        continue;
+                    }
+                    if command == "middle" {
+                        nodes.push(AstNode::Middle(parse_delimiter_token(self)?));
+                        continue;
+                    }
                     if command == "right" {
                         let right = parse_delimiter_token(self)?;
-                        let inner = Parser::normalize_group_static(AstNode::Group(
-                            Parser::merge_text_nodes(nodes),
-                        ));
+                        let inner =
+                            Parser::normalize_group_static(Parser::finish_group(nodes, infix));
                         return Ok(AstNode::Delimited {
                             left,
                             inner: Box::new(inner),
@@ -68,7 +113,8 @@ impl Parser {
     }
 }
 
-fn parse_delimiter_token(parser: &mut Parser) -> ParseResult<Delimiter> {
+pub(super) fn parse_delimiter_token(parser: &mut Parser) -> ParseResult<Delimiter> {
+    parser.skip_whitespace();
     match parser.peek_char() {
         Some('.') => {
             parser.consume_char();
@@ -104,10 +150,12 @@ fn delimiter_command_to_glyph(name: &str) -> Option<Option<&'static str>> {
         "rceil" => Some(Some("⌉")),
         "lfloor" => Some(Some("⌊")),
         "rfloor" => Some(Some("⌋")),
-        "lbrace" => Some(Some("{")),
-        "rbrace" => Some(Some("}")),
+        "lbrace" | "{" => Some(Some("{")),
+        "rbrace" | "}" => Some(Some("}")),
+        "lbrack" => Some(Some("[")),
+        "rbrack" => Some(Some("]")),
         "lvert" | "vert" | "rvert" => Some(Some("|")),
-        "lVert" | "Vert" | "rVert" => Some(Some("‖")),
+        "lVert" | "Vert" | "rVert" | "|" => Some(Some("‖")),
         "." => Some(None),
         _ => None,
     }
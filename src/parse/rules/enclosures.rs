@@ -0,0 +1,135 @@
+//! 边框、底色与删除线：`\boxed`、`\fbox`、`\colorbox`、`\fcolorbox`、`\ovalbox`、
+//! cancel 宏包的 `\cancel`、`\bcancel`、`\xcancel`、`\cancelto` 与 ulem 的 `\sout`
+
+use crate::ast::{AstNode, EnclosureKind, EnclosureNode, EquationMarks, ParseResult};
+use crate::error::RenderError;
+
+use super::super::lexer::Parser;
+use super::matrix::parse_text_cell;
+
+const BLACK: &str = "#000000";
+
+pub fn handle(parser: &mut Parser, command: &str) -> Option<ParseResult<AstNode>> {
+    let result = match command {
+        "boxed" => math_body(parser, command, EnclosureKind::Frame, Some(BLACK)),
+        "fbox" => text_body(parser, command, EnclosureKind::Frame, Some(BLACK), None),
+        "ovalbox" => text_body(
+            parser,
+            command,
+            EnclosureKind::RoundedFrame,
+            Some(BLACK),
+            None,
+        ),
+        "colorbox" => handle_colorbox(parser, false),
+        "fcolorbox" => handle_colorbox(parser, true),
+        "cancel" => math_body(parser, command, EnclosureKind::Cancel, None),
+        "bcancel" => math_body(parser, command, EnclosureKind::BackCancel, None),
+        "xcancel" => math_body(parser, command, EnclosureKind::CrossCancel, None),
+        "sout" => math_body(parser, command, EnclosureKind::StrikeOut, None),
+        "cancelto" => handle_cancelto(parser),
+        _ => return None,
+    };
+    Some(result)
+}
+
+fn enclosed(
+    body: AstNode,
+    kind: EnclosureKind,
+    stroke: Option<&'static str>,
+    fill: Option<&'static str>,
+) -> AstNode {
+    AstNode::Enclosed(EnclosureNode {
+        body: Box::new(body),
+        kind,
+        stroke,
+        fill,
+        target: None,
+    })
+}
+
+fn math_body(
+    parser: &mut Parser,
+    command: &str,
+    kind: EnclosureKind,
+    stroke: Option<&'static str>,
+) -> ParseResult<AstNode> {
+    let body = parser.parse_block(command)?;
+    Ok(enclosed(body, kind, stroke, None))
+}
+
+/// `\fbox` 等盒子的参数为文本模式，其中 `$...$` 之间按公式解析
+fn text_body(
+    parser: &mut Parser,
+    command: &str,
+    kind: EnclosureKind,
+    stroke: Option<&'static str>,
+    fill: Option<&'static str>,
+) -> ParseResult<AstNode> {
+    let content = parser.consume_braced_content(command)?;
+    let mut marks = EquationMarks::default();
+    let body = parse_text_cell(parser, &content, &mut marks)?;
+    parser.marks_mut().merge(marks)?;
+    Ok(enclosed(body, kind, stroke, fill))
+}
+
+/// `\colorbox{底色}{文本}` 与 `\fcolorbox{边框色}{底色}{文本}`
+fn handle_colorbox(parser: &mut Parser, framed: bool) -> ParseResult<AstNode> {
+    let command = if framed { "fcolorbox" } else { "colorbox" };
+    let stroke = if framed {
+        Some(parse_color(parser, command)?)
+    } else {
+        None
+    };
+    let fill = parse_color(parser, command)?;
+    text_body(parser, command, EnclosureKind::Frame, stroke, Some(fill))
+}
+
+/// `\cancelto{目标}{主体}`：斜向箭头划掉主体，箭头末端写上目标值
+fn handle_cancelto(parser: &mut Parser) -> ParseResult<AstNode> {
+    let target = parser.parse_block("cancelto 目标")?;
+    let body = parser.parse_block("cancelto")?;
+    Ok(AstNode::Enclosed(EnclosureNode {
+        body: Box::new(body),
+        kind: EnclosureKind::Cancel,
+        stroke: None,
+        fill: None,
+        target: Some(Box::new(target)),
+    }))
+}
+
+fn parse_color(parser: &mut Parser, command: &str) -> ParseResult<&'static str> {
+    if parser.peek_char() == Some('[') {
+        return Err(RenderError::ParseError(format!(
+            "\\{command} 暂不支持颜色模型参数，请使用颜色名称"
+        )));
+    }
+    let name = parser.consume_braced_content(&format!("{command} 颜色"))?;
+    color_value(name.trim())
+        .ok_or_else(|| RenderError::ParseError(format!("未知的颜色 {}", name.trim())))
+}
+
+/// xcolor 预定义的基本颜色
+fn color_value(name: &str) -> Option<&'static str> {
+    Some(match name {
+        "black" => BLACK,
+        "white" => "#FFFFFF",
+        "red" => "#FF0000",
+        "green" => "#00FF00",
+        "blue" => "#0000FF",
+        "cyan" => "#00FFFF",
+        "magenta" => "#FF00FF",
+        "yellow" => "#FFFF00",
+        "gray" => "#808080",
+        "darkgray" => "#404040",
+        "lightgray" => "#BFBFBF",
+        "brown" => "#BF8040",
+        "lime" => "#BFFF00",
+        "olive" => "#808000",
+        "orange" => "#FF8000",
+        "pink" => "#FFBFBF",
+        "purple" => "#BF0040",
+        "teal" => "#008080",
+        "violet" => "#800080",
+        _ => return None,
+    })
+}
@@ -1,8 +1,9 @@
-use crate::ast::{AstNode, Delimiter, ParseResult};
+use crate::ast::{AstNode, ColumnAlign, Delimiter, EquationMarks, ParseResult};
 use crate::error::RenderError;
 
 use super::super::lexer::Parser;
-use super::matrix;
+use super::cd;
+use super::matrix::{self, AlignKind};
 
 pub fn handle(parser: &mut Parser, command: &str) -> Option<ParseResult<AstNode>> {
     if command == "begin" {
@@ -13,31 +14,290 @@ pub fn handle(parser: &mut Parser, command: &str) -> Option<ParseResult<AstNode>
 }
 
 fn parse_environment(parser: &mut Parser) -> ParseResult<AstNode> {
+    let start = parser.position() - "\\begin".len();
     let name = parser.consume_braced_content("环境名称")?;
+    if name == "array" {
+        // 可选的纵向位置参数 [t]/[c]/[b] 对单个公式没有影响，读取后忽略
+        parser.skip_whitespace();
+        if parser.peek_char() == Some('[') {
+            parser.consume_bracketed_content("array 位置参数")?;
+            parser.skip_whitespace();
+        }
+        let spec = parser.consume_braced_content("array 列格式")?;
+        let body = consume_environment_body(parser, &name)?;
+        return Ok(AstNode::Array(matrix::parse_array(parser, &spec, &body)?));
+    }
+    if name == "subarray" {
+        let spec = parser.consume_braced_content("subarray 列格式")?;
+        let align = match spec.trim() {
+            "l" => ColumnAlign::Left,
+            "c" => ColumnAlign::Center,
+            other => {
+                return Err(RenderError::ParseError(format!(
+                    "subarray 的列格式只能是 l 或 c，收到 {other}"
+                )))
+            }
+        };
+        let body = consume_environment_body(parser, &name)?;
+        let stack = matrix::parse_subarray(parser, &name, &body, align)?;
+        return Ok(AstNode::Array(stack));
+    }
+    if name == "CD" {
+        let body = consume_environment_body(parser, &name)?;
+        return Ok(AstNode::Array(cd::parse_cd(parser, &body)?));
+    }
+    if let Some(numbered) = equation_numbering(&name) {
+        let body = consume_environment_body(parser, &name)?;
+        ensure_standalone(parser, &name, start)?;
+        let mut nested = parser.nested(&body);
+        let ast = nested.parse_group(None)?;
+        parser.set_equation_rows(vec![nested.take_marks().resolve(numbered)]);
+        return Ok(Parser::normalize_group_static(ast));
+    }
+    if let Some(variant) = cases_variant(&name) {
+        return parse_cases_environment(parser, &name, variant, start);
+    }
+    if let Some(kind) = align_kind(parser, &name)? {
+        let body = consume_environment_body(parser, &name)?;
+        let (array, row_marks) = matrix::parse_align(parser, &name, &body, kind)?;
+        match display_numbering(&name) {
+            Some(numbered) => {
+                ensure_standalone(parser, &name, start)?;
+                let rows = if kind == AlignKind::Multline {
+                    // multline 整体只有一个编号，放在最后一行
+                    let mut merged = EquationMarks::default();
+                    for marks in row_marks {
+                        merged.merge(marks)?;
+                    }
+                    let mut rows = vec![EquationMarks::default().resolve(false); array.rows.len()];
+                    if let Some(last) = rows.last_mut() {
+                        *last = merged.resolve(numbered);
+                    }
+                    rows
+                } else {
+                    row_marks
+                        .into_iter()
+                        .map(|marks| marks.resolve(numbered))
+                        .collect()
+                };
+                parser.set_equation_rows(rows);
+            }
+            // aligned、split 等内嵌环境不编号，其中的 \tag 归属外层公式
+            None => {
+                matrix::merge_row_marks(parser, row_marks)?;
+            }
+        }
+        return Ok(AstNode::Array(array));
+    }
+    let base = name.strip_suffix('*').unwrap_or(&name);
+    let (left, right) = match matrix_delimiters(base) {
+        Some(delimiters) if base == name || base != "smallmatrix" => delimiters,
+        _ => return Err(RenderError::ParseError(format!("暂不支持环境 {name}"))),
+    };
+    let align = if base != name {
+        parse_column_alignment(parser, &name)?
+    } else {
+        ColumnAlign::Center
+    };
     let body = consume_environment_body(parser, &name)?;
-    let rows = matrix::parse_rows(&body)?;
-
-    match name.as_str() {
-        "cases" => Ok(make_delimited("{", None, rows)),
-        "aligned" | "align" | "array" => Ok(AstNode::Matrix(rows)),
-        "pmatrix" => Ok(make_delimited("(", Some(")"), rows)),
-        "bmatrix" => Ok(make_delimited("[", Some("]"), rows)),
-        "Bmatrix" => Ok(make_delimited("{", Some("}"), rows)),
-        "vmatrix" => Ok(make_delimited("|", Some("|"), rows)),
-        "Vmatrix" => Ok(make_delimited("‖", Some("‖"), rows)),
-        "matrix" => Ok(AstNode::Matrix(rows)),
-        other => Err(RenderError::ParseError(format!("暂不支持环境 {other}"))),
+    let matrix = matrix::parse_matrix(parser, &name, &body, align, base == "smallmatrix")?;
+    Ok(make_delimited(left, right, AstNode::Array(matrix)))
+}
+
+/// 矩阵类环境两侧的定界符：括号属于环境本身，矩阵主体不画任何线条
+fn matrix_delimiters(base: &str) -> Option<(Option<&'static str>, Option<&'static str>)> {
+    match base {
+        "matrix" | "smallmatrix" => Some((None, None)),
+        "pmatrix" => Some((Some("("), Some(")"))),
+        "bmatrix" => Some((Some("["), Some("]"))),
+        "Bmatrix" => Some((Some("{"), Some("}"))),
+        "vmatrix" => Some((Some("|"), Some("|"))),
+        "Vmatrix" => Some((Some("‖"), Some("‖"))),
+        _ => None,
     }
 }
 
-fn make_delimited(left: &str, right: Option<&str>, rows: Vec<Vec<AstNode>>) -> AstNode {
+/// cases 家族的变体：括号在哪一侧、是否按行间样式排版、第二列是否为文本
+#[derive(Debug, Clone, Copy)]
+struct CasesVariant {
+    right_brace: bool,
+    display: bool,
+    text_column: bool,
+    /// `numcases`：带左侧公式参数、逐行编号
+    numbered: bool,
+}
+
+fn cases_variant(name: &str) -> Option<CasesVariant> {
+    let base = name.strip_suffix('*').unwrap_or(name);
+    let (right_brace, display) = match base {
+        "cases" => (false, false),
+        "dcases" => (false, true),
+        "rcases" => (true, false),
+        "drcases" => (true, true),
+        "numcases" if base == name => {
+            return Some(CasesVariant {
+                right_brace: false,
+                display: false,
+                text_column: true,
+                numbered: true,
+            })
+        }
+        _ => return None,
+    };
+    Some(CasesVariant {
+        right_brace,
+        display,
+        text_column: base != name,
+        numbered: false,
+    })
+}
+
+/// cases 家族：括号由环境补上；`numcases{lhs}` 必须独占整条公式，每行各自编号
+fn parse_cases_environment(
+    parser: &mut Parser,
+    name: &str,
+    variant: CasesVariant,
+    start: usize,
+) -> ParseResult<AstNode> {
+    let lhs = if variant.numbered {
+        Some(parser.consume_braced_content("numcases 左侧公式")?)
+    } else {
+        None
+    };
+    let body = consume_environment_body(parser, name)?;
+    let (cases, row_marks) =
+        matrix::parse_cases(parser, name, &body, variant.text_column, variant.display)?;
+    let (left, right) = if variant.right_brace {
+        (None, Some("}"))
+    } else {
+        (Some("{"), None)
+    };
+    let Some(lhs) = lhs else {
+        matrix::merge_row_marks(parser, row_marks)?;
+        return Ok(make_delimited(left, right, AstNode::Array(cases)));
+    };
+    ensure_standalone(parser, name, start)?;
+    let mut nested = parser.nested(&lhs);
+    let lhs_ast = Parser::normalize_group_static(nested.parse_group(None)?);
+    // 左侧公式中的 \tag 等归入第一行
+    let mut rows = Vec::with_capacity(row_marks.len());
+    let mut lhs_marks = Some(nested.take_marks());
+    for mut marks in row_marks {
+        if let Some(extra) = lhs_marks.take() {
+            marks.merge(extra)?;
+        }
+        rows.push(marks.resolve(true));
+    }
+    parser.set_equation_rows(rows);
+    // 表格交由排版阶段接在左侧公式之后，以便直接取得各行基线
+    parser.set_numcases(cases);
+    Ok(lhs_ast)
+}
+
+/// 带星号的矩阵环境可在 `[l]`、`[c]`、`[r]` 中指定列对齐，缺省居中
+fn parse_column_alignment(parser: &mut Parser, name: &str) -> ParseResult<ColumnAlign> {
+    parser.skip_whitespace();
+    if parser.peek_char() != Some('[') {
+        return Ok(ColumnAlign::Center);
+    }
+    let content = parser.consume_bracketed_content(&format!("{name} 对齐参数"))?;
+    match content.trim() {
+        "l" => Ok(ColumnAlign::Left),
+        "c" | "" => Ok(ColumnAlign::Center),
+        "r" => Ok(ColumnAlign::Right),
+        other => Err(RenderError::ParseError(format!(
+            "{name} 的对齐参数只能是 l、c 或 r，收到 {other}"
+        ))),
+    }
+}
+
+/// 识别 align 家族环境并读取其参数；`alignat{n}` 给出列对数，`aligned` 等的 `[t]/[b]` 位置参数忽略
+fn align_kind(parser: &mut Parser, name: &str) -> ParseResult<Option<AlignKind>> {
+    let base = name.strip_suffix('*').unwrap_or(name);
+    // 只有顶层显示环境有星号形式，aligned/split 等内嵌环境不接受 `*`
+    if base != name
+        && !matches!(
+            base,
+            "align" | "alignat" | "gather" | "multline" | "flalign"
+        )
+    {
+        return Ok(None);
+    }
+    let kind = match base {
+        "align" | "aligned" | "split" | "flalign" => AlignKind::Align,
+        "gather" | "gathered" => AlignKind::Gather,
+        "multline" => AlignKind::Multline,
+        "alignat" | "alignedat" => AlignKind::AlignAt(0),
+        _ => return Ok(None),
+    };
+    if matches!(base, "aligned" | "gathered" | "alignedat") {
+        skip_position_argument(parser, name)?;
+    }
+    if kind == AlignKind::AlignAt(0) {
+        let count = parser.consume_braced_content("alignat 列对数")?;
+        let pairs = count
+            .trim()
+            .parse::<usize>()
+            .ok()
+            .filter(|&pairs| pairs > 0)
+            .ok_or_else(|| {
+                RenderError::ParseError(format!("{name} 的列对数必须是正整数，收到 {count}"))
+            })?;
+        return Ok(Some(AlignKind::AlignAt(pairs)));
+    }
+    Ok(Some(kind))
+}
+
+/// `equation` 自动编号，`equation*` 不编号；其他环境返回 `None`
+fn equation_numbering(name: &str) -> Option<bool> {
+    match name {
+        "equation" => Some(true),
+        "equation*" => Some(false),
+        _ => None,
+    }
+}
+
+/// 顶层显示环境是否自动编号：不带星号的编号，带星号的只显示 `\tag`；内嵌环境返回 `None`
+fn display_numbering(name: &str) -> Option<bool> {
+    let base = name.strip_suffix('*').unwrap_or(name);
+    matches!(
+        base,
+        "align" | "alignat" | "gather" | "multline" | "flalign"
+    )
+    .then_some(base == name)
+}
+
+/// 编号环境只能独占整条公式，不能嵌套或与其他内容并列
+fn ensure_standalone(parser: &Parser, name: &str, start: usize) -> ParseResult<()> {
+    if parser.is_top_level() && parser.is_blank_outside(start, parser.position()) {
+        Ok(())
+    } else {
+        Err(RenderError::ParseError(format!(
+            "{name} 环境必须独占整条公式，内嵌时请改用 aligned、gathered 等环境"
+        )))
+    }
+}
+
+fn skip_position_argument(parser: &mut Parser, name: &str) -> ParseResult<()> {
+    parser.skip_whitespace();
+    if parser.peek_char() == Some('[') {
+        parser.consume_bracketed_content(&format!("{name} 位置参数"))?;
+        parser.skip_whitespace();
+    }
+    Ok(())
+}
+
+fn make_delimited(left: Option<&str>, right: Option<&str>, inner: AstNode) -> AstNode {
+    if left.is_none() && right.is_none() {
+        return inner;
+    }
     AstNode::Delimited {
         left: Delimiter {
-            glyph: Some(left.to_string()),
+            glyph: left.map(str::to_string),
         },
-        inner: Box::new(AstNode::Matrix(rows)),
+        inner: Box::new(inner),
         right: Delimiter {
-            glyph: right.map(|g| g.to_string()),
+            glyph: right.map(str::to_string),
         },
     }
 }
@@ -48,6 +308,7 @@ fn consume_environment_body(parser: &mut Parser, name: &str) -> ParseResult<Stri
     let mut body = String::new();
 
     while parser.peek_char().is_some() {
+        parser.check_deadline()?;
         if parser.starts_with_str("\\begin{") {
             parser.consume_char(); // '\'
             body.push('\\');
@@ -1,35 +1,149 @@
-use crate::ast::{AstNode, Delimiter, ParseResult};
+use crate::ast::{AstNode, Delimiter, FractionAlign, FractionNode, MathStyle, ParseResult};
+use crate::error::RenderError;
 
 use super::super::lexer::Parser;
+use super::delimiters::parse_delimiter_token;
+use super::spacing::parse_dimension;
 
 pub fn handle(parser: &mut Parser, command: &str) -> Option<ParseResult<AstNode>> {
     match command {
-        "frac" => Some(handle_frac(parser)),
-        "binom" => Some(handle_binom(parser)),
+        "frac" => Some(handle_frac(parser, None)),
+        "dfrac" => Some(handle_frac(parser, Some(MathStyle::Display))),
+        "tfrac" => Some(handle_frac(parser, Some(MathStyle::Text))),
+        "cfrac" => Some(handle_cfrac(parser)),
+        "genfrac" => Some(handle_genfrac(parser)),
+        "binom" => Some(handle_binom(parser, None)),
+        "dbinom" => Some(handle_binom(parser, Some(MathStyle::Display))),
+        "tbinom" => Some(handle_binom(parser, Some(MathStyle::Text))),
         _ => None,
     }
 }
 
-fn handle_frac(parser: &mut Parser) -> ParseResult<AstNode> {
+/// 是否为 `\over`、`\choose`、`\atop` 这类把所在分组一分为二的中缀命令
+pub fn is_infix(command: &str) -> bool {
+    matches!(command, "over" | "choose" | "atop")
+}
+
+/// 用中缀命令两侧的内容构造分数
+pub fn build_infix(command: &str, numerator: AstNode, denominator: AstNode) -> AstNode {
+    let fraction = FractionNode::new(numerator, denominator);
+    let fraction = match command {
+        "choose" => binomial(fraction),
+        "atop" => FractionNode {
+            rule_thickness: Some(0.0),
+            ..fraction
+        },
+        _ => fraction,
+    };
+    AstNode::Fraction(fraction)
+}
+
+fn handle_frac(parser: &mut Parser, style: Option<MathStyle>) -> ParseResult<AstNode> {
+    let numerator = parser.parse_block("分子")?;
+    let denominator = parser.parse_block("分母")?;
+    Ok(AstNode::Fraction(FractionNode {
+        style,
+        ..FractionNode::new(numerator, denominator)
+    }))
+}
+
+/// `\cfrac[l|r|c]{分子}{分母}`：连分数，分子分母保持展示样式，可选分子对齐方式
+fn handle_cfrac(parser: &mut Parser) -> ParseResult<AstNode> {
+    let align = match parser.peek_char() {
+        Some('[') => {
+            let content = parser.consume_bracketed_content("cfrac 对齐参数")?;
+            match content.trim() {
+                "l" => FractionAlign::Left,
+                "r" => FractionAlign::Right,
+                "c" | "" => FractionAlign::Center,
+                other => {
+                    return Err(RenderError::ParseError(format!(
+                        "cfrac 的对齐参数只能是 l、c 或 r，收到 {other}"
+                    )))
+                }
+            }
+        }
+        _ => FractionAlign::Center,
+    };
+    let numerator = parser.parse_block("分子")?;
+    let denominator = parser.parse_block("分母")?;
+    Ok(AstNode::Fraction(FractionNode {
+        style: Some(MathStyle::Display),
+        align,
+        ..FractionNode::new(numerator, denominator)
+    }))
+}
+
+/// `\genfrac{左}{右}{线宽}{样式}{分子}{分母}`，线宽与样式留空时取默认值
+fn handle_genfrac(parser: &mut Parser) -> ParseResult<AstNode> {
+    let left = parse_delimiter_argument(parser, "genfrac 左定界符")?;
+    let right = parse_delimiter_argument(parser, "genfrac 右定界符")?;
+    let thickness = parser.consume_braced_content("genfrac 线宽")?;
+    let rule_thickness =
+        match thickness.trim() {
+            "" => None,
+            text => Some(parse_dimension(text).ok_or_else(|| {
+                RenderError::ParseError(format!("无法识别的 genfrac 线宽 {text}"))
+            })?),
+        };
+    let style = match parser.consume_braced_content("genfrac 样式")?.trim() {
+        "" => None,
+        "0" => Some(MathStyle::Display),
+        "1" => Some(MathStyle::Text),
+        "2" => Some(MathStyle::Script),
+        "3" => Some(MathStyle::ScriptScript),
+        other => {
+            return Err(RenderError::ParseError(format!(
+                "genfrac 的样式参数只能是 0~3，收到 {other}"
+            )))
+        }
+    };
     let numerator = parser.parse_block("分子")?;
     let denominator = parser.parse_block("分母")?;
-    Ok(AstNode::Fraction {
-        numerator: Box::new(numerator),
-        denominator: Box::new(denominator),
-    })
+    Ok(AstNode::Fraction(FractionNode {
+        rule_thickness: rule_thickness.map(f32::abs),
+        style,
+        left,
+        right,
+        ..FractionNode::new(numerator, denominator)
+    }))
 }
 
-fn handle_binom(parser: &mut Parser) -> ParseResult<AstNode> {
+fn handle_binom(parser: &mut Parser, style: Option<MathStyle>) -> ParseResult<AstNode> {
     let top = parser.parse_block("binom 上部分")?;
     let bottom = parser.parse_block("binom 下部分")?;
-    let matrix = AstNode::Matrix(vec![vec![top], vec![bottom]]);
-    Ok(AstNode::Delimited {
+    Ok(AstNode::Fraction(FractionNode {
+        style,
+        ..binomial(FractionNode::new(top, bottom))
+    }))
+}
+
+/// 二项式系数：不画分数线并加圆括号
+fn binomial(fraction: FractionNode) -> FractionNode {
+    FractionNode {
+        rule_thickness: Some(0.0),
         left: Delimiter {
             glyph: Some("(".to_string()),
         },
-        inner: Box::new(matrix),
         right: Delimiter {
             glyph: Some(")".to_string()),
         },
-    })
+        ..fraction
+    }
+}
+
+/// 读取花括号中的单个定界符，留空表示不加定界符
+fn parse_delimiter_argument(parser: &mut Parser, context: &str) -> ParseResult<Delimiter> {
+    let content = parser.consume_braced_content(context)?;
+    if content.trim().is_empty() {
+        return Ok(Delimiter { glyph: None });
+    }
+    let mut nested = parser.nested(content.trim());
+    let delimiter = parse_delimiter_token(&mut nested)?;
+    if nested.peek_char().is_some() {
+        return Err(RenderError::ParseError(format!(
+            "{context}只能包含一个定界符"
+        )));
+    }
+    Ok(delimiter)
 }
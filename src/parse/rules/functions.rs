@@ -27,6 +27,7 @@ static FUNCTIONS: phf::Map<&'static str, &'static str> = phf_map! {
     "arcsch" => "arcsch",
     "arsech" => "arsech",
     "arcoth" => "arcoth",
+    "arg" => "arg",
     "log" => "log",
     "ln" => "ln",
     "exp" => "exp",
@@ -1,44 +1,720 @@
-use crate::ast::{AstNode, ParseResult};
+use crate::ast::{
+    ArrayNode, AstNode, ColumnAlign, ColumnSeparator, EquationMarks, MathStyle, ParseResult,
+    RowSeparator,
+};
 use crate::error::RenderError;
 
 use super::super::lexer::Parser;
+use super::spacing::parse_dimension;
+use super::symbols::map_symbol;
 
 pub fn handle(parser: &mut Parser, command: &str) -> Option<ParseResult<AstNode>> {
     match command {
         "matrix" => Some(parse_matrix_command(parser)),
+        "substack" => Some(parse_substack_command(parser)),
         _ => None,
     }
 }
 
 fn parse_matrix_command(parser: &mut Parser) -> ParseResult<AstNode> {
     let content = parser.consume_braced_content("matrix")?;
-    let rows = parse_rows(&content)?;
-    Ok(AstNode::Matrix(rows))
+    let matrix = parse_matrix(parser, "matrix", &content, ColumnAlign::Center, false)?;
+    Ok(AstNode::Array(matrix))
 }
 
-pub fn parse_rows(body: &str) -> ParseResult<Vec<Vec<AstNode>>> {
-    let mut rows = Vec::new();
-    for raw_row in body.split("\\\\") {
-        let trimmed_row = raw_row.trim();
-        if trimmed_row.is_empty() {
+/// `\substack{..}`：等同于居中的 `subarray`，常用于多行的运算符上下限
+fn parse_substack_command(parser: &mut Parser) -> ParseResult<AstNode> {
+    let content = parser.consume_braced_content("substack")?;
+    let stack = parse_subarray(parser, "substack", &content, ColumnAlign::Center)?;
+    Ok(AstNode::Array(stack))
+}
+
+/// `\arraycolsep`：`array` 每列两侧留 0.2em
+const ARRAY_COLUMN_PADDING: f32 = 0.2;
+/// 矩阵相邻两列相距 1em，即每侧 0.5em
+const MATRIX_COLUMN_PADDING: f32 = 0.5;
+/// `smallmatrix` 以脚标字号排版，列间距约为一个空格
+const SMALL_MATRIX_COLUMN_PADDING: f32 = 0.17;
+
+/// `subarray` 与 `\substack` 的行高倍数：行与行紧贴，适合放在脚标中
+const SUBARRAY_ROW_STRETCH: f32 = 0.8;
+
+/// `subarray`：单列、两侧不留白、行距收紧的表格。单元格沿用所在位置的字号，
+/// 放在脚标或运算符上下限中即为脚标大小
+pub fn parse_subarray(
+    parser: &mut Parser,
+    name: &str,
+    body: &str,
+    align: ColumnAlign,
+) -> ParseResult<ArrayNode> {
+    let (mut stack, row_marks) = build_array(
+        parser,
+        name,
+        vec![align],
+        vec![ColumnSeparator::default(); 2],
+        split_rows(body),
+        CellMode::Math,
+    )?;
+    stack.column_padding = 0.0;
+    stack.row_stretch = SUBARRAY_ROW_STRETCH;
+    merge_row_marks(parser, row_marks)?;
+    Ok(stack)
+}
+
+/// 矩阵类环境（`matrix`、`pmatrix` 等）：列数取最宽的行，所有列按 `align` 对齐，
+/// 首尾两列外侧不留间距；`small` 时按 `smallmatrix` 以脚标字号紧凑排版。矩阵本身不画括号
+pub fn parse_matrix(
+    parser: &mut Parser,
+    name: &str,
+    body: &str,
+    align: ColumnAlign,
+    small: bool,
+) -> ParseResult<ArrayNode> {
+    let (mut matrix, row_marks) = build_matrix(parser, name, body, align, CellMode::Math)?;
+    if small {
+        matrix.column_padding = SMALL_MATRIX_COLUMN_PADDING;
+        matrix.cell_style = Some(MathStyle::Script);
+    }
+    // 矩阵本身不编号，其中的 \tag 等归属外层
+    merge_row_marks(parser, row_marks)?;
+    Ok(matrix)
+}
+
+/// `cases` 的行高倍数：条件里常有分式与上下标，行距比矩阵略松
+const CASES_ROW_STRETCH: f32 = 1.2;
+
+/// cases 家族：各列靠左、列间留 1em；`text_column` 时第二列按文本模式解析（`cases*`、`numcases`），
+/// 非 `display` 时单元格按行内样式排版（分式缩小），`dcases` 则保持行间样式。
+/// 同时返回每行的编号命令，由调用方决定按行编号还是并入外层
+pub fn parse_cases(
+    parser: &Parser,
+    name: &str,
+    body: &str,
+    text_column: bool,
+    display: bool,
+) -> ParseResult<(ArrayNode, Vec<EquationMarks>)> {
+    let mode = if text_column {
+        CellMode::CasesText
+    } else {
+        CellMode::Math
+    };
+    let (mut cases, row_marks) = build_matrix(parser, name, body, ColumnAlign::Left, mode)?;
+    cases.row_stretch *= CASES_ROW_STRETCH;
+    if !display {
+        for row in &mut cases.rows {
+            for cell in row.iter_mut() {
+                *cell = with_text_style(std::mem::replace(cell, AstNode::Text(String::new())));
+            }
+        }
+    }
+    Ok((cases, row_marks))
+}
+
+/// 按矩阵的列格式（列数取最宽的行、首尾外侧不留白）构造表格
+fn build_matrix(
+    parser: &Parser,
+    name: &str,
+    body: &str,
+    align: ColumnAlign,
+    mode: CellMode,
+) -> ParseResult<(ArrayNode, Vec<EquationMarks>)> {
+    let raw_rows = split_rows(body);
+    let column_count = raw_rows
+        .iter()
+        .map(|row| row.cells.len())
+        .max()
+        .unwrap_or(1);
+    if column_count > parser.limits().max_matrix_cells {
+        return Err(RenderError::LimitExceeded(format!(
+            "矩阵单元格数超过 {} 个",
+            parser.limits().max_matrix_cells
+        )));
+    }
+    let mut column_separators = vec![ColumnSeparator::default(); column_count + 1];
+    for edge in [0, column_count] {
+        column_separators[edge].custom = Some(Box::new(AstNode::Group(Vec::new())));
+    }
+    let (mut matrix, row_marks) = build_array(
+        parser,
+        name,
+        vec![align; column_count],
+        column_separators,
+        raw_rows,
+        mode,
+    )?;
+    matrix.column_padding = MATRIX_COLUMN_PADDING;
+    Ok((matrix, row_marks))
+}
+
+/// 行内样式：未显式指定样式的分式按 `\tfrac` 排版
+fn with_text_style(node: AstNode) -> AstNode {
+    match node {
+        AstNode::Fraction(mut fraction) => {
+            fraction.style.get_or_insert(MathStyle::Text);
+            AstNode::Fraction(fraction)
+        }
+        AstNode::Group(children) => {
+            AstNode::Group(children.into_iter().map(with_text_style).collect())
+        }
+        other => other,
+    }
+}
+
+/// 解析单元格，并把其中的编号命令并入所在行的 `marks`
+pub(super) fn parse_cell(
+    parser: &Parser,
+    source: &str,
+    marks: &mut EquationMarks,
+) -> ParseResult<AstNode> {
+    let trimmed_cell = source.trim();
+    if trimmed_cell.is_empty() {
+        return Ok(AstNode::Text(String::new()));
+    }
+    let mut nested = parser.nested(trimmed_cell);
+    let cell_ast = nested.parse_group(None)?;
+    marks.merge(nested.take_marks())?;
+    Ok(Parser::normalize_group_static(cell_ast))
+}
+
+/// 文本模式的单元格：`$...$` 之间按数学公式解析，其余部分原样作为文字
+pub(super) fn parse_text_cell(
+    parser: &Parser,
+    source: &str,
+    marks: &mut EquationMarks,
+) -> ParseResult<AstNode> {
+    let segments: Vec<&str> = source.trim().split('$').collect();
+    if segments.len().is_multiple_of(2) {
+        return Err(RenderError::ParseError("文本单元格中的 $ 未配对".into()));
+    }
+    let last = segments.len() - 1;
+    let mut nodes = Vec::with_capacity(segments.len());
+    for (index, segment) in segments.into_iter().enumerate() {
+        if index % 2 == 1 {
+            nodes.push(parse_cell(parser, segment, marks)?);
             continue;
         }
-        let mut cells = Vec::new();
-        for cell_str in trimmed_row.split('&') {
-            let trimmed_cell = cell_str.trim();
-            if trimmed_cell.is_empty() {
-                cells.push(AstNode::Text(String::new()));
-            } else {
-                let mut nested = Parser::new(trimmed_cell);
-                let cell_ast = nested.parse_group(None)?;
-                cells.push(Parser::normalize_group_static(cell_ast));
+        let (mut text, commands) = take_numbering_commands(segment);
+        parse_cell(parser, &commands, marks)?;
+        if index == last {
+            // 摘掉行尾的 \label 等命令后，去掉其前面留下的空格
+            text.truncate(text.trim_end().len());
+        }
+        if !text.trim().is_empty() {
+            nodes.push(AstNode::Text(text));
+        }
+    }
+    Ok(Parser::normalize_group_static(AstNode::Group(nodes)))
+}
+
+/// 从文本中摘出 `\tag`、`\label`、`\notag` 等编号命令，返回剩余文字与这些命令的源码
+fn take_numbering_commands(segment: &str) -> (String, String) {
+    let mut text = String::new();
+    let mut commands = String::new();
+    let mut rest = segment;
+    while let Some(start) = rest.find('\\') {
+        text.push_str(&rest[..start]);
+        let after = &rest[start + 1..];
+        let name_len = after
+            .find(|ch: char| !ch.is_ascii_alphabetic())
+            .unwrap_or(after.len());
+        let name = &after[..name_len];
+        let mut end = start + 1 + name_len;
+        match name {
+            "notag" | "nonumber" => {}
+            "tag" | "label" => {
+                if rest[end..].starts_with('*') {
+                    end += 1;
+                }
+                if let Some(close) = rest[end..].find('}') {
+                    end += close + 1;
+                }
+            }
+            _ => {
+                // 其他命令（含 `\\`、`\%` 等转义）原样保留为文字
+                let skip = if name_len == 0 {
+                    after.chars().next().map_or(0, char::len_utf8)
+                } else {
+                    name_len
+                };
+                text.push_str(&rest[start..start + 1 + skip]);
+                rest = &rest[start + 1 + skip..];
+                continue;
+            }
+        }
+        commands.push_str(&rest[start..end]);
+        commands.push(' ');
+        rest = &rest[end..];
+    }
+    text.push_str(rest);
+    (text, commands)
+}
+
+/// 单元格的解析方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CellMode {
+    /// 普通数学单元格
+    Math,
+    /// 对齐环境：右列开头的关系符补上左侧的关系间距
+    Align,
+    /// `cases*` 与 `numcases`：第二列为文本模式
+    CasesText,
+}
+
+/// 表格的一行源码：按顶层 `&` 切分的单元格，以及行尾 `\\[..]` 中的额外行距
+pub(super) struct RawRow {
+    pub(super) cells: Vec<String>,
+    pub(super) extra_space: Option<String>,
+}
+
+impl RawRow {
+    pub(super) fn is_blank(&self) -> bool {
+        self.cells.len() == 1 && self.cells[0].trim().is_empty()
+    }
+
+    /// 行尾 `\\[..]` 给出的额外行距（以字号为单位），缺省为 0
+    pub(super) fn extra_space(&self) -> ParseResult<f32> {
+        match self.extra_space.as_deref().map(str::trim) {
+            None | Some("") => Ok(0.0),
+            Some(text) => parse_dimension(text)
+                .ok_or_else(|| RenderError::ParseError(format!("无法识别的行距 {text}"))),
+        }
+    }
+}
+
+/// 按顶层的 `\\` 与 `&` 切分表格内容；花括号与嵌套环境内部的分隔符保持原样
+pub(super) fn split_rows(body: &str) -> Vec<RawRow> {
+    let chars: Vec<char> = body.chars().collect();
+    let mut rows = Vec::new();
+    let mut cells = Vec::new();
+    let mut current = String::new();
+    let mut brace_depth = 0usize;
+    let mut env_depth = 0usize;
+    let mut index = 0;
+    while index < chars.len() {
+        let ch = chars[index];
+        index += 1;
+        match ch {
+            '{' => brace_depth += 1,
+            '}' => brace_depth = brace_depth.saturating_sub(1),
+            '&' if brace_depth == 0 && env_depth == 0 => {
+                cells.push(std::mem::take(&mut current));
+                continue;
+            }
+            '\\' if chars.get(index) == Some(&'\\') && brace_depth == 0 && env_depth == 0 => {
+                index += 1;
+                if chars.get(index) == Some(&'*') {
+                    index += 1;
+                }
+                let mut extra_space = None;
+                if chars.get(index) == Some(&'[') {
+                    if let Some(end) = chars[index..].iter().position(|&c| c == ']') {
+                        extra_space = Some(chars[index + 1..index + end].iter().collect());
+                        index += end + 1;
+                    }
+                }
+                cells.push(std::mem::take(&mut current));
+                rows.push(RawRow {
+                    cells: std::mem::take(&mut cells),
+                    extra_space,
+                });
+                continue;
             }
+            '\\' => {
+                // 整体复制命令名，转义字符（如 \& \{）不参与分隔与计数
+                current.push(ch);
+                let start = index;
+                while index < chars.len() && chars[index].is_ascii_alphabetic() {
+                    index += 1;
+                }
+                if index == start {
+                    if let Some(&escaped) = chars.get(index) {
+                        current.push(escaped);
+                        index += 1;
+                    }
+                    continue;
+                }
+                let name: String = chars[start..index].iter().collect();
+                match name.as_str() {
+                    "begin" => env_depth += 1,
+                    "end" => env_depth = env_depth.saturating_sub(1),
+                    _ => {}
+                }
+                current.push_str(&name);
+                continue;
+            }
+            _ => {}
+        }
+        current.push(ch);
+    }
+    cells.push(current);
+    rows.push(RawRow {
+        cells,
+        extra_space: None,
+    });
+    rows
+}
+
+/// 解析 `array` 环境：列格式 `spec` 与按行排列的单元格，`\hline`、`\cline` 需位于行首
+pub fn parse_array(parser: &mut Parser, spec: &str, body: &str) -> ParseResult<ArrayNode> {
+    let mut columns = Vec::new();
+    let mut column_separators = vec![ColumnSeparator::default()];
+    let mut spec_parser = parser.nested(spec);
+    parse_column_spec(&mut spec_parser, &mut columns, &mut column_separators)?;
+    if columns.is_empty() {
+        return Err(RenderError::ParseError("array 列格式至少需要一列".into()));
+    }
+    let (array, row_marks) = build_array(
+        parser,
+        "array",
+        columns,
+        column_separators,
+        split_rows(body),
+        CellMode::Math,
+    )?;
+    merge_row_marks(parser, row_marks)?;
+    Ok(array)
+}
+
+/// 对齐类环境的排版方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AlignKind {
+    /// `align`、`aligned`、`split`：右/左交替的列对，列对之间留 2em
+    Align,
+    /// `alignat{n}`、`alignedat{n}`：固定 n 个列对，列对之间不留空
+    AlignAt(usize),
+    /// `gather`、`gathered`：单列居中
+    Gather,
+    /// `multline`：单列，首行靠左、末行靠右、其余居中
+    Multline,
+}
+
+/// amsmath 在对齐环境的相邻行之间额外加入的 `\jot` 行距（以字号为单位）
+const JOT: f32 = 0.3;
+
+/// 解析 align 家族环境：列格式由环境决定，右列开头的关系符补上左侧的关系间距；
+/// 同时返回每行的编号命令，由调用方决定按行编号还是并入外层
+pub fn parse_align(
+    parser: &Parser,
+    name: &str,
+    body: &str,
+    kind: AlignKind,
+) -> ParseResult<(ArrayNode, Vec<EquationMarks>)> {
+    let raw_rows = split_rows(body);
+    let widest = raw_rows
+        .iter()
+        .map(|row| row.cells.len())
+        .max()
+        .unwrap_or(1);
+    let column_count = match kind {
+        AlignKind::Align => widest.max(1),
+        AlignKind::AlignAt(pairs) => pairs.saturating_mul(2),
+        AlignKind::Gather | AlignKind::Multline => 1,
+    };
+    if column_count > parser.limits().max_matrix_cells {
+        return Err(RenderError::LimitExceeded(format!(
+            "矩阵单元格数超过 {} 个",
+            parser.limits().max_matrix_cells
+        )));
+    }
+    let columns = (0..column_count)
+        .map(|index| match kind {
+            AlignKind::Gather | AlignKind::Multline => ColumnAlign::Center,
+            _ if index % 2 == 0 => ColumnAlign::Right,
+            _ => ColumnAlign::Left,
+        })
+        .collect();
+    // 相当于 @{}r@{}l@{\qquad}r@{}l@{}：列对内部紧贴，只有 align 的列对之间留空
+    let mut column_separators = Vec::with_capacity(column_count + 1);
+    for index in 0..=column_count {
+        let between_pairs = index % 2 == 0 && index != 0 && index != column_count;
+        let custom = if between_pairs && kind == AlignKind::Align {
+            AstNode::Kern(2.0)
+        } else {
+            AstNode::Group(Vec::new())
+        };
+        column_separators.push(ColumnSeparator {
+            rules: 0,
+            custom: Some(Box::new(custom)),
+        });
+    }
+
+    let (mut array, row_marks) = build_array(
+        parser,
+        name,
+        columns,
+        column_separators,
+        raw_rows,
+        CellMode::Align,
+    )?;
+    let row_count = array.rows.len();
+    for separator in &mut array.row_separators[1..row_count] {
+        separator.extra_space += JOT;
+    }
+    if kind == AlignKind::Multline && row_count > 1 {
+        array.row_aligns = vec![None; row_count];
+        array.row_aligns[0] = Some(ColumnAlign::Left);
+        array.row_aligns[row_count - 1] = Some(ColumnAlign::Right);
+    }
+    Ok((array, row_marks))
+}
+
+fn build_array(
+    parser: &Parser,
+    name: &str,
+    columns: Vec<ColumnAlign>,
+    column_separators: Vec<ColumnSeparator>,
+    raw_rows: Vec<RawRow>,
+    mode: CellMode,
+) -> ParseResult<(ArrayNode, Vec<EquationMarks>)> {
+    let max_cells = parser.limits().max_matrix_cells;
+    let mut cell_count = 0usize;
+    let mut rows = Vec::new();
+    let mut row_marks = Vec::new();
+    let mut row_separators = vec![RowSeparator::default()];
+    for mut raw_row in raw_rows {
+        parser.check_deadline()?;
+        let boundary = row_separators.last_mut().expect("行边界至少有一项");
+        raw_row.cells[0] = take_row_rules(&raw_row.cells[0], boundary, columns.len())?;
+        let extra_space = raw_row.extra_space()?;
+        if raw_row.is_blank() {
+            boundary.extra_space += extra_space;
+            continue;
+        }
+        if raw_row.cells.len() > columns.len() {
+            return Err(RenderError::ParseError(format!(
+                "{name} 第 {} 行有 {} 个单元格，超过 {} 列",
+                rows.len() + 1,
+                raw_row.cells.len(),
+                columns.len()
+            )));
         }
+        cell_count += columns.len();
+        if cell_count > max_cells {
+            return Err(RenderError::LimitExceeded(format!(
+                "矩阵单元格数超过 {max_cells} 个"
+            )));
+        }
+        let mut cells = Vec::with_capacity(columns.len());
+        let mut marks = EquationMarks::default();
+        for (index, cell_str) in raw_row.cells.iter().enumerate() {
+            let cell = match mode {
+                // 与 TeX 的 `&={}` 效果一致：右列开头的关系符前补粗间距
+                CellMode::Align if index % 2 == 1 && starts_with_relation(cell_str) => {
+                    parse_cell(parser, &format!("\\;{}", cell_str.trim_start()), &mut marks)?
+                }
+                CellMode::CasesText if index == 1 => parse_text_cell(parser, cell_str, &mut marks)?,
+                _ => parse_cell(parser, cell_str, &mut marks)?,
+            };
+            cells.push(cell);
+        }
+        cells.resize_with(columns.len(), || AstNode::Text(String::new()));
         rows.push(cells);
+        row_marks.push(marks);
+        row_separators.push(RowSeparator {
+            extra_space,
+            ..RowSeparator::default()
+        });
     }
     if rows.is_empty() {
-        Err(RenderError::ParseError("多行环境内容不能为空".into()))
-    } else {
-        Ok(rows)
+        return Err(RenderError::ParseError("多行环境内容不能为空".into()));
+    }
+    let array = ArrayNode {
+        rows,
+        columns,
+        column_separators,
+        row_separators,
+        row_stretch: parser.array_stretch(),
+        row_aligns: Vec::new(),
+        column_padding: ARRAY_COLUMN_PADDING,
+        cell_style: None,
+    };
+    Ok((array, row_marks))
+}
+
+/// 不自行编号的表格把各行读到的 `\tag`、`\label` 交给外层解析器
+pub(super) fn merge_row_marks(
+    parser: &mut Parser,
+    row_marks: Vec<EquationMarks>,
+) -> ParseResult<()> {
+    for marks in row_marks {
+        parser.marks_mut().merge(marks)?;
+    }
+    Ok(())
+}
+
+/// 单元格是否以关系符（`=`、`\leq`、`\to` 等）开头
+fn starts_with_relation(source: &str) -> bool {
+    let source = source.trim_start();
+    let first = match source.strip_prefix('\\') {
+        Some(rest) => {
+            let name: String = rest
+                .chars()
+                .take_while(|ch| ch.is_ascii_alphabetic())
+                .collect();
+            map_symbol(&name).and_then(|symbol| symbol.chars().next())
+        }
+        None => source.chars().next(),
+    };
+    first.is_some_and(|ch| {
+        matches!(
+            ch,
+            '=' | '<'
+                | '>'
+                | ':'
+                | '≤'
+                | '≥'
+                | '≠'
+                | '≈'
+                | '≡'
+                | '∼'
+                | '≃'
+                | '≅'
+                | '∝'
+                | '≪'
+                | '≫'
+                | '≺'
+                | '≻'
+                | '∈'
+                | '∉'
+                | '∋'
+                | '⊂'
+                | '⊃'
+                | '⊆'
+                | '⊇'
+                | '→'
+                | '←'
+                | '↔'
+                | '⇒'
+                | '⇐'
+                | '⇔'
+                | '⟶'
+                | '⟹'
+                | '⟸'
+                | '⟺'
+                | '↦'
+                | '∣'
+                | '∥'
+                | '⊥'
+        )
+    })
+}
+
+/// 解析列格式：`l`/`c`/`r` 列、`p{..}` 段落列（按左对齐处理）、`|` 竖线、`@{..}` 分隔内容与 `*{n}{..}` 重复
+fn parse_column_spec(
+    spec: &mut Parser,
+    columns: &mut Vec<ColumnAlign>,
+    separators: &mut Vec<ColumnSeparator>,
+) -> ParseResult<()> {
+    let max_columns = spec.limits().max_matrix_cells;
+    while let Some(ch) = spec.consume_char() {
+        let align = match ch {
+            'l' => ColumnAlign::Left,
+            'c' => ColumnAlign::Center,
+            'r' => ColumnAlign::Right,
+            'p' | 'm' | 'b' => {
+                spec.consume_braced_content("array 段落列宽度")?;
+                ColumnAlign::Left
+            }
+            '|' => {
+                separators.last_mut().expect("列边界至少有一项").rules += 1;
+                continue;
+            }
+            '@' => {
+                let content = spec.consume_braced_content("array @ 分隔内容")?;
+                let mut nested = spec.nested(&content);
+                let custom = Parser::normalize_group_static(nested.parse_group(None)?);
+                separators.last_mut().expect("列边界至少有一项").custom = Some(Box::new(custom));
+                continue;
+            }
+            '*' => {
+                let count = spec.consume_braced_content("array 重复次数")?;
+                let count: usize = count
+                    .trim()
+                    .parse()
+                    .ok()
+                    .filter(|&count| count > 0)
+                    .ok_or_else(|| {
+                        RenderError::ParseError(format!("无效的 array 重复次数 {count}"))
+                    })?;
+                let repeated = spec.consume_braced_content("array 重复内容")?;
+                // 先展开一次得到每轮新增的列数，再在循环前按总列数检查上限
+                let before = columns.len();
+                let mut nested = spec.nested(&repeated);
+                parse_column_spec(&mut nested, columns, separators)?;
+                let added = columns.len() - before;
+                if added == 0 {
+                    return Err(RenderError::ParseError(format!(
+                        "array 重复内容 {{{repeated}}} 不含任何列"
+                    )));
+                }
+                if added
+                    .checked_mul(count)
+                    .is_none_or(|total| before + total > max_columns)
+                {
+                    return Err(RenderError::LimitExceeded(format!(
+                        "矩阵单元格数超过 {max_columns} 个"
+                    )));
+                }
+                for _ in 1..count {
+                    spec.check_deadline()?;
+                    let mut nested = spec.nested(&repeated);
+                    parse_column_spec(&mut nested, columns, separators)?;
+                }
+                continue;
+            }
+            ch if ch.is_whitespace() => continue,
+            other => {
+                return Err(RenderError::ParseError(format!(
+                    "无法识别的 array 列格式 {other}"
+                )))
+            }
+        };
+        if columns.len() >= max_columns {
+            return Err(RenderError::LimitExceeded(format!(
+                "矩阵单元格数超过 {max_columns} 个"
+            )));
+        }
+        columns.push(align);
+        separators.push(ColumnSeparator::default());
     }
+    Ok(())
+}
+
+/// 取出行首的 `\hline` 与 `\cline{i-j}`，记入该行上方的边界，返回剩余的单元格源码
+fn take_row_rules(
+    cell: &str,
+    boundary: &mut RowSeparator,
+    column_count: usize,
+) -> ParseResult<String> {
+    let mut rest = cell.trim_start();
+    loop {
+        if let Some(after) = command_rest(rest, "hline") {
+            boundary.hlines += 1;
+            rest = after.trim_start();
+        } else if let Some(after) = command_rest(rest, "cline") {
+            let after = after.trim_start();
+            let range = after
+                .strip_prefix('{')
+                .and_then(|inner| inner.split_once('}'))
+                .ok_or_else(|| RenderError::ParseError("\\cline 需要 {i-j} 参数".into()))?;
+            let (start, end) = range
+                .0
+                .split_once('-')
+                .and_then(|(a, b)| Some((a.trim().parse().ok()?, b.trim().parse().ok()?)))
+                .filter(|&(a, b): &(usize, usize)| 1 <= a && a <= b && b <= column_count)
+                .ok_or_else(|| {
+                    RenderError::ParseError(format!("无效的 \\cline 列范围 {}", range.0))
+                })?;
+            boundary.clines.push((start - 1, end - 1));
+            rest = range.1.trim_start();
+        } else {
+            return Ok(rest.to_string());
+        }
+    }
+}
+
+/// 若 `text` 以命令 `\name` 开头（且其后不是字母），返回命令之后的部分
+fn command_rest<'a>(text: &'a str, name: &str) -> Option<&'a str> {
+    let after = text.strip_prefix('\\')?.strip_prefix(name)?;
+    (!after.starts_with(|ch: char| ch.is_ascii_alphabetic())).then_some(after)
 }
@@ -1,15 +1,22 @@
 mod basic;
+mod boxes;
+mod cd;
+mod chemistry;
 mod decorations;
 mod delimiters;
+mod enclosures;
 mod environments;
 mod fractions;
 mod functions;
 mod matrix;
 mod operators;
+mod physics;
 mod roots;
+mod siunitx;
 mod spacing;
 mod styles;
 mod symbols;
+mod tags;
 
 use crate::ast::{AstNode, ParseResult};
 
@@ -18,20 +25,26 @@ use super::lexer::Parser;
 pub fn handle_command(parser: &mut Parser, command: &str) -> Option<ParseResult<AstNode>> {
     basic::handle(parser, command)
         .or_else(|| environments::handle(parser, command))
+        .or_else(|| tags::handle(parser, command))
         .or_else(|| fractions::handle(parser, command))
         .or_else(|| roots::handle(parser, command))
         .or_else(|| delimiters::handle(parser, command))
         .or_else(|| matrix::handle(parser, command))
         .or_else(|| decorations::handle(parser, command))
+        .or_else(|| enclosures::handle(parser, command))
+        .or_else(|| boxes::handle(parser, command))
+        .or_else(|| spacing::handle(parser, command))
         .or_else(|| styles::handle(parser, command))
         .or_else(|| operators::handle(parser, command))
+        .or_else(|| physics::handle(parser, command))
+        .or_else(|| chemistry::handle(parser, command))
+        .or_else(|| siunitx::handle(parser, command))
 }
 
 pub fn handle_text_command(command: &str) -> Option<&'static str> {
     functions::map_text_command(command)
         .or_else(|| operators::map_function_name(command))
         .or_else(|| symbols::map_symbol(command))
-        .or_else(|| spacing::map_spacing(command))
 }
 
 pub fn is_large_operator(command: &str) -> bool {
@@ -41,3 +54,11 @@ pub fn is_large_operator(command: &str) -> bool {
 pub fn build_large_operator(command: &str) -> AstNode {
     operators::build_large_operator(command)
 }
+
+pub fn is_infix_fraction(command: &str) -> bool {
+    fractions::is_infix(command)
+}
+
+pub fn build_infix_fraction(command: &str, numerator: AstNode, denominator: AstNode) -> AstNode {
+    fractions::build_infix(command, numerator, denominator)
+}
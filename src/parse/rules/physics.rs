@@ -0,0 +1,212 @@
+//! physics 与 braket 宏包的常用命令：狄拉克记号、导数、绝对值与范数、期望值和对易子。
+//! 括号类命令生成自动伸缩的 `\left...\right` 定界节点，带星号的形式保持正文字号；
+//! 导数生成普通分数节点
+
+use crate::ast::{AstNode, Delimiter, FractionNode, ParseResult};
+
+use super::super::lexer::Parser;
+
+pub fn handle(parser: &mut Parser, command: &str) -> Option<ParseResult<AstNode>> {
+    let result = match command {
+        "bra" | "Bra" => bracket(parser, "⟨", "|", 1),
+        "ket" | "Ket" => bracket(parser, "|", "⟩", 1),
+        "braket" | "Braket" => handle_braket(parser),
+        "ketbra" | "dyad" => handle_ketbra(parser),
+        "expval" | "ev" => handle_expval(parser),
+        "mel" | "matrixel" => bracket(parser, "⟨", "⟩", 3),
+        "abs" | "absolutevalue" => bracket(parser, "|", "|", 1),
+        "norm" => bracket(parser, "‖", "‖", 1),
+        "comm" | "commutator" => commutator(parser, "[", "]"),
+        "acomm" | "anticommutator" | "poissonbracket" | "pb" => commutator(parser, "{", "}"),
+        "dv" | "derivative" => derivative(parser, "d", false),
+        "pdv" | "partialderivative" => derivative(parser, "∂", true),
+        _ => return None,
+    };
+    Some(result)
+}
+
+/// 命令名后的 `*`：physics 中表示不自动伸缩定界符
+fn take_star(parser: &mut Parser) -> bool {
+    if parser.peek_char() == Some('*') {
+        parser.consume_char();
+        true
+    } else {
+        false
+    }
+}
+
+/// 读取 `count` 个花括号参数，各参数之间以竖线分隔，外层包上左右定界符
+fn bracket(parser: &mut Parser, left: &str, right: &str, count: usize) -> ParseResult<AstNode> {
+    let fixed = take_star(parser);
+    let mut parts = Vec::with_capacity(count);
+    for _ in 0..count {
+        parts.push(parser.parse_block("physics 参数")?);
+    }
+    Ok(delimited(left, separated(parts, "|", fixed), right, fixed))
+}
+
+/// `\braket{a|b}`（braket 宏包，顶层竖线处分隔）与 `\braket{a}{b}`（physics 宏包）
+fn handle_braket(parser: &mut Parser) -> ParseResult<AstNode> {
+    let fixed = take_star(parser);
+    let content = parser.consume_braced_content("braket")?;
+    let mut parts = split_bars(&content)
+        .iter()
+        .map(|part| parse_part(parser, part))
+        .collect::<ParseResult<Vec<_>>>()?;
+    if parts.len() == 1 && parser.peek_char() == Some('{') {
+        parts.push(parser.parse_block("braket 参数")?);
+    }
+    Ok(delimited("⟨", separated(parts, "|", fixed), "⟩", fixed))
+}
+
+/// `\ketbra{a}{b}`：外积 |a⟩⟨b|
+fn handle_ketbra(parser: &mut Parser) -> ParseResult<AstNode> {
+    let fixed = take_star(parser);
+    let ket = parser.parse_block("ketbra 参数")?;
+    let bra = parser.parse_block("ketbra 参数")?;
+    Ok(AstNode::Group(vec![
+        delimited("|", ket, "⟩", fixed),
+        delimited("⟨", bra, "|", fixed),
+    ]))
+}
+
+/// `\expval{A}` 为 ⟨A⟩，`\expval{A}{\psi}` 为 ⟨ψ|A|ψ⟩
+fn handle_expval(parser: &mut Parser) -> ParseResult<AstNode> {
+    let fixed = take_star(parser);
+    let operator = parser.parse_block("expval 参数")?;
+    let inner = if parser.peek_char() == Some('{') {
+        let state = parser.parse_block("expval 参数")?;
+        separated(vec![state.clone(), operator, state], "|", fixed)
+    } else {
+        operator
+    };
+    Ok(delimited("⟨", inner, "⟩", fixed))
+}
+
+/// `\comm{A}{B}` 为 [A,B]，`\acomm{A}{B}` 为 {A,B}
+fn commutator(parser: &mut Parser, left: &str, right: &str) -> ParseResult<AstNode> {
+    let fixed = take_star(parser);
+    let first = parser.parse_block("对易子参数")?;
+    let second = parser.parse_block("对易子参数")?;
+    let inner = AstNode::Group(vec![first, AstNode::Text(",".into()), second]);
+    Ok(delimited(left, inner, right, fixed))
+}
+
+/// `\dv[n]{f}{x}` 与 `\pdv[n]{f}{x}`；只给一个参数时为算子 d/dx，
+/// `\pdv{f}{x}{y}` 为混合偏导 ∂²f/∂x∂y
+fn derivative(parser: &mut Parser, symbol: &str, partial: bool) -> ParseResult<AstNode> {
+    take_star(parser);
+    let order = parser.parse_optional_block("导数阶数")?;
+    let mut arguments = vec![parser.parse_block("导数参数")?];
+    let max_arguments = if partial { 3 } else { 2 };
+    while arguments.len() < max_arguments && parser.peek_char() == Some('{') {
+        arguments.push(parser.parse_block("导数参数")?);
+    }
+    let differential = || AstNode::Text(symbol.to_string());
+    let (function, variables) = match arguments.len() {
+        1 => (None, arguments),
+        _ => {
+            let function = arguments.remove(0);
+            (Some(function), arguments)
+        }
+    };
+    let (numerator_order, variable_order) = if variables.len() > 1 {
+        (Some(AstNode::Text(variables.len().to_string())), None)
+    } else {
+        (order.clone(), order)
+    };
+    let mut numerator = vec![with_power(differential(), numerator_order)];
+    numerator.extend(function);
+    let mut denominator = Vec::with_capacity(variables.len() * 2);
+    for variable in variables {
+        denominator.push(differential());
+        denominator.push(with_power(variable, variable_order.clone()));
+    }
+    Ok(AstNode::Fraction(FractionNode::new(
+        Parser::normalize_group_static(AstNode::Group(numerator)),
+        Parser::normalize_group_static(AstNode::Group(denominator)),
+    )))
+}
+
+fn with_power(base: AstNode, power: Option<AstNode>) -> AstNode {
+    match power {
+        Some(power) => AstNode::Scripts {
+            base: Box::new(base),
+            superscript: Some(Box::new(power)),
+            subscript: None,
+        },
+        None => base,
+    }
+}
+
+/// 自动伸缩时生成 `\left...\right` 节点，带星号时以正文字号的字形直接拼接
+fn delimited(left: &str, inner: AstNode, right: &str, fixed: bool) -> AstNode {
+    if fixed {
+        return AstNode::Group(vec![
+            AstNode::Text(left.to_string()),
+            inner,
+            AstNode::Text(right.to_string()),
+        ]);
+    }
+    AstNode::Delimited {
+        left: Delimiter {
+            glyph: Some(left.to_string()),
+        },
+        inner: Box::new(inner),
+        right: Delimiter {
+            glyph: Some(right.to_string()),
+        },
+    }
+}
+
+/// 以分隔符连接各部分；自动伸缩时分隔符为 `\middle`，与外层定界符等高
+fn separated(parts: Vec<AstNode>, separator: &str, fixed: bool) -> AstNode {
+    let mut nodes = Vec::with_capacity(parts.len() * 2);
+    for part in parts {
+        if !nodes.is_empty() {
+            nodes.push(if fixed {
+                AstNode::Text(separator.to_string())
+            } else {
+                AstNode::Middle(Delimiter {
+                    glyph: Some(separator.to_string()),
+                })
+            });
+        }
+        nodes.push(part);
+    }
+    Parser::normalize_group_static(AstNode::Group(nodes))
+}
+
+/// 按顶层的 `|` 切分 braket 参数；花括号内的竖线与 `\|` 保持原样
+fn split_bars(source: &str) -> Vec<String> {
+    let mut parts = Vec::new();
+    let mut current = String::new();
+    let mut depth = 0usize;
+    let mut chars = source.chars();
+    while let Some(ch) = chars.next() {
+        match ch {
+            '{' => depth += 1,
+            '}' => depth = depth.saturating_sub(1),
+            '\\' => {
+                current.push(ch);
+                current.extend(chars.next());
+                continue;
+            }
+            '|' if depth == 0 => {
+                parts.push(std::mem::take(&mut current));
+                continue;
+            }
+            _ => {}
+        }
+        current.push(ch);
+    }
+    parts.push(current);
+    parts
+}
+
+fn parse_part(parser: &mut Parser, source: &str) -> ParseResult<AstNode> {
+    let mut nested = parser.nested(source.trim());
+    let ast = nested.parse_group(None)?;
+    parser.marks_mut().merge(nested.take_marks())?;
+    Ok(Parser::normalize_group_static(ast))
+}
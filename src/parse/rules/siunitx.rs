@@ -0,0 +1,586 @@
+//! siunitx 风格的数值与单位：`\num` 排版数值，`\si`/`\unit` 排版单位，`\SI`/`\qty` 为二者的组合。
+//! 整数与小数部分达到五位时每三位以细空格分组，指数写成 ×10 的幂，单位保持直立
+
+use crate::ast::{AstNode, FractionNode, ParseResult};
+use crate::error::RenderError;
+
+use super::super::lexer::Parser;
+use super::chemistry::Cursor;
+use super::spacing::{MEDIUM_SPACE, THIN_SPACE};
+
+pub fn handle(parser: &mut Parser, command: &str) -> Option<ParseResult<AstNode>> {
+    match command {
+        "num" => Some(handle_num(parser)),
+        "si" | "unit" => Some(handle_unit(parser)),
+        "SI" | "qty" => Some(handle_quantity(parser)),
+        _ => None,
+    }
+}
+
+fn handle_num(parser: &mut Parser) -> ParseResult<AstNode> {
+    let settings = Settings::parse(parser)?;
+    let number = parser.consume_braced_content("num")?;
+    format_number(&number, &settings)
+}
+
+fn handle_unit(parser: &mut Parser) -> ParseResult<AstNode> {
+    let settings = Settings::parse(parser)?;
+    let unit = parser.consume_braced_content("si")?;
+    parse_units(parser, &unit, &settings)
+}
+
+/// `\SI{数值}{单位}`：数值与单位之间为细空格，角度的 °、′、″ 紧贴数值
+fn handle_quantity(parser: &mut Parser) -> ParseResult<AstNode> {
+    let settings = Settings::parse(parser)?;
+    let number = parser.consume_braced_content("SI 数值")?;
+    let unit = parser.consume_braced_content("SI 单位")?;
+    let number = format_number(&number, &settings)?;
+    let unit = parse_units(parser, &unit, &settings)?;
+    let mut nodes = vec![number];
+    if !starts_with_angle(&unit) && !matches!(&unit, AstNode::Group(units) if units.is_empty()) {
+        nodes.push(thin_space());
+    }
+    nodes.push(unit);
+    Ok(AstNode::Group(nodes))
+}
+
+fn thin_space() -> AstNode {
+    AstNode::Kern(THIN_SPACE)
+}
+
+fn starts_with_angle(node: &AstNode) -> bool {
+    match node {
+        AstNode::Text(text) => text.starts_with(['°', '′', '″']),
+        AstNode::Group(nodes) => nodes.first().is_some_and(starts_with_angle),
+        _ => false,
+    }
+}
+
+/// 单位中 `\per` 与 `/` 之后的部分如何排版
+#[derive(Copy, Clone, PartialEq)]
+enum PerMode {
+    /// 写成负幂次，如 m s⁻¹
+    Power,
+    /// 写成分式
+    Fraction,
+    /// 写成斜线，如 m/s
+    Symbol,
+}
+
+/// 命令可选参数 `[key=value, ...]` 中支持的 siunitx 选项
+struct Settings {
+    decimal_marker: char,
+    group_digits: bool,
+    exponent_product: &'static str,
+    /// `None` 时 `\per` 写成负幂次而 `/` 原样保留为斜线
+    per_mode: Option<PerMode>,
+}
+
+impl Settings {
+    fn parse(parser: &mut Parser) -> ParseResult<Self> {
+        let mut settings = Settings {
+            decimal_marker: parser.decimal_marker().symbol(),
+            group_digits: true,
+            exponent_product: "×",
+            per_mode: None,
+        };
+        if parser.peek_char() != Some('[') {
+            return Ok(settings);
+        }
+        let options = parser.consume_bracketed_content("siunitx 选项")?;
+        for option in split_options(&options) {
+            let (key, value) = option.split_once('=').unwrap_or((option, "true"));
+            let value = value.trim().trim_start_matches('{').trim_end_matches('}');
+            let invalid = || {
+                RenderError::ParseError(format!("siunitx 选项 {} 的取值无效：{value}", key.trim()))
+            };
+            match key.trim() {
+                "output-decimal-marker" => {
+                    settings.decimal_marker = match value.trim() {
+                        "." => '.',
+                        "," => ',',
+                        _ => return Err(invalid()),
+                    }
+                }
+                "group-digits" => {
+                    settings.group_digits = match value.trim() {
+                        "true" | "all" => true,
+                        "false" | "none" => false,
+                        _ => return Err(invalid()),
+                    }
+                }
+                "exponent-product" => {
+                    settings.exponent_product = match value.trim() {
+                        "\\times" => "×",
+                        "\\cdot" => "·",
+                        _ => return Err(invalid()),
+                    }
+                }
+                "per-mode" => {
+                    settings.per_mode = Some(match value.trim() {
+                        "power" => PerMode::Power,
+                        "fraction" => PerMode::Fraction,
+                        "symbol" => PerMode::Symbol,
+                        _ => return Err(invalid()),
+                    })
+                }
+                other => {
+                    return Err(RenderError::ParseError(format!(
+                        "不支持的 siunitx 选项 {other}"
+                    )))
+                }
+            }
+        }
+        Ok(settings)
+    }
+}
+
+/// 按顶层逗号切分选项，`output-decimal-marker={,}` 花括号内的逗号不参与切分
+fn split_options(source: &str) -> Vec<&str> {
+    let mut options = Vec::new();
+    let mut depth = 0usize;
+    let mut start = 0;
+    for (index, ch) in source.char_indices() {
+        match ch {
+            '{' => depth += 1,
+            '}' => depth = depth.saturating_sub(1),
+            ',' if depth == 0 => {
+                options.push(&source[start..index]);
+                start = index + 1;
+            }
+            _ => {}
+        }
+    }
+    options.push(&source[start..]);
+    options.retain(|option| !option.trim().is_empty());
+    options
+}
+
+/// 数值：可用 `x` 连接多个因子（如 `2x3`），每个因子为
+/// 符号、整数、小数（`.` 或 `,` 均可输入）、`+-`/`\pm` 不确定度与 `e`/`d` 指数
+fn format_number(source: &str, settings: &Settings) -> ParseResult<AstNode> {
+    let compact: String = source
+        .replace("\\pm", "+-")
+        .chars()
+        .filter(|ch| !ch.is_whitespace())
+        .collect();
+    if compact.is_empty() {
+        return Err(RenderError::ParseError("siunitx 数值不能为空".into()));
+    }
+    let mut nodes = Vec::new();
+    for (index, factor) in compact.split('x').enumerate() {
+        if index > 0 {
+            push_spaced(&mut nodes, "×");
+        }
+        format_factor(factor, settings, &mut nodes)?;
+    }
+    Ok(Parser::normalize_group_static(AstNode::Group(
+        Parser::merge_text_nodes(nodes),
+    )))
+}
+
+fn format_factor(source: &str, settings: &Settings, nodes: &mut Vec<AstNode>) -> ParseResult<()> {
+    let invalid = || RenderError::ParseError(format!("无法解析的 siunitx 数值 {source}"));
+    let (value, exponent) = match source.find(['e', 'E', 'd', 'D']) {
+        Some(split) => (&source[..split], Some(&source[split + 1..])),
+        None => (source, None),
+    };
+    let (value, uncertainty) = match value.split_once("+-") {
+        Some((value, uncertainty)) => (value, Some(uncertainty)),
+        None => (value, None),
+    };
+    let (sign, value) = match value.strip_prefix(['+', '-']) {
+        Some(rest) => (&value[..1], rest),
+        None => ("", value),
+    };
+    let mut mantissa = Vec::new();
+    match sign {
+        "" => {}
+        "-" => mantissa.push(AstNode::Text("−".into())),
+        sign => mantissa.push(AstNode::Text(sign.into())),
+    }
+    if !value.is_empty() {
+        format_decimal(value, settings, &mut mantissa).ok_or_else(invalid)?;
+    }
+    if let Some(uncertainty) = uncertainty {
+        if value.is_empty() {
+            return Err(invalid());
+        }
+        push_spaced(&mut mantissa, "±");
+        format_decimal(uncertainty, settings, &mut mantissa).ok_or_else(invalid)?;
+        if exponent.is_some() {
+            mantissa.insert(0, AstNode::Text("(".into()));
+            mantissa.push(AstNode::Text(")".into()));
+        }
+    }
+    let Some(exponent) = exponent else {
+        if value.is_empty() {
+            return Err(invalid());
+        }
+        nodes.extend(mantissa);
+        return Ok(());
+    };
+    let (exponent_sign, digits) = match exponent.strip_prefix(['+', '-']) {
+        Some(rest) => (if exponent.starts_with('-') { "−" } else { "" }, rest),
+        None => ("", exponent),
+    };
+    if digits.is_empty() || !digits.chars().all(|ch| ch.is_ascii_digit()) {
+        return Err(invalid());
+    }
+    let digits = match digits.trim_start_matches('0') {
+        "" => "0",
+        trimmed => trimmed,
+    };
+    // 省略尾数（如 `e3`、`-e3`）时只写 10 的幂
+    if !value.is_empty() {
+        push_spaced(&mut mantissa, settings.exponent_product);
+    }
+    nodes.extend(mantissa);
+    nodes.push(AstNode::Text("10".into()));
+    Parser::attach_script(
+        nodes,
+        '^',
+        AstNode::Text(format!("{exponent_sign}{digits}")),
+    )
+}
+
+/// 无符号的十进制数：整数部分为空时补 0，按设置替换小数点并分组后追加到 `nodes`
+fn format_decimal(source: &str, settings: &Settings, nodes: &mut Vec<AstNode>) -> Option<()> {
+    let (integer, fraction) = match source.split_once(['.', ',']) {
+        Some((integer, fraction)) => (integer, Some(fraction)),
+        None => (source, None),
+    };
+    let digits = |part: &str| part.chars().all(|ch| ch.is_ascii_digit());
+    if !digits(integer) || !fraction.is_none_or(digits) {
+        return None;
+    }
+    if integer.is_empty() && fraction.is_none_or(str::is_empty) {
+        return None;
+    }
+    let integer = if integer.is_empty() { "0" } else { integer };
+    group(integer, settings.group_digits, true, nodes);
+    if let Some(fraction) = fraction.filter(|fraction| !fraction.is_empty()) {
+        nodes.push(AstNode::Text(settings.decimal_marker.to_string()));
+        group(fraction, settings.group_digits, false, nodes);
+    }
+    Some(())
+}
+
+/// 五位及以上的数字串每三位插入细空格：整数部分从右往左数，小数部分从左往右数
+fn group(digits: &str, enabled: bool, integer: bool, nodes: &mut Vec<AstNode>) {
+    let count = digits.chars().count();
+    if !enabled || count < 5 {
+        nodes.push(AstNode::Text(digits.to_string()));
+        return;
+    }
+    let mut chunk = String::with_capacity(3);
+    for (index, ch) in digits.chars().enumerate() {
+        let position = if integer { count - index } else { index };
+        if index > 0 && position % 3 == 0 {
+            nodes.push(AstNode::Text(std::mem::take(&mut chunk)));
+            nodes.push(AstNode::Kern(THIN_SPACE));
+        }
+        chunk.push(ch);
+    }
+    nodes.push(AstNode::Text(chunk));
+}
+
+/// 两侧加中等空格的二元运算符，如 `×`、`±`
+fn push_spaced(nodes: &mut Vec<AstNode>, operator: &str) {
+    nodes.extend([
+        AstNode::Kern(MEDIUM_SPACE),
+        AstNode::Text(operator.to_string()),
+        AstNode::Kern(MEDIUM_SPACE),
+    ]);
+}
+
+/// 单位在分式中的位置
+#[derive(Copy, Clone, PartialEq)]
+enum Position {
+    Numerator,
+    /// 由 `\per` 引入，默认写成负幂次
+    Per,
+    /// 位于 `/` 之后，默认保留斜线
+    Slash,
+}
+
+struct Unit {
+    text: String,
+    power: Option<String>,
+    position: Position,
+}
+
+/// 词头命令，如 `\kilo\gram`
+fn prefix_symbol(name: &str) -> Option<&'static str> {
+    Some(match name {
+        "yocto" => "y",
+        "zepto" => "z",
+        "atto" => "a",
+        "femto" => "f",
+        "pico" => "p",
+        "nano" => "n",
+        "micro" => "µ",
+        "milli" => "m",
+        "centi" => "c",
+        "deci" => "d",
+        "deca" | "deka" => "da",
+        "hecto" => "h",
+        "kilo" => "k",
+        "mega" => "M",
+        "giga" => "G",
+        "tera" => "T",
+        "peta" => "P",
+        "exa" => "E",
+        "zetta" => "Z",
+        "yotta" => "Y",
+        _ => return None,
+    })
+}
+
+/// 单位命令，如 `\meter`、`\joule`
+fn unit_symbol(name: &str) -> Option<&'static str> {
+    Some(match name {
+        "ampere" => "A",
+        "candela" => "cd",
+        "kelvin" => "K",
+        "gram" => "g",
+        "kilogram" => "kg",
+        "metre" | "meter" => "m",
+        "mole" => "mol",
+        "second" => "s",
+        "becquerel" => "Bq",
+        "degreeCelsius" => "°C",
+        "coulomb" => "C",
+        "farad" => "F",
+        "gray" => "Gy",
+        "hertz" => "Hz",
+        "henry" => "H",
+        "joule" => "J",
+        "katal" => "kat",
+        "lumen" => "lm",
+        "lux" => "lx",
+        "newton" => "N",
+        "ohm" => "Ω",
+        "pascal" => "Pa",
+        "radian" => "rad",
+        "siemens" => "S",
+        "sievert" => "Sv",
+        "steradian" => "sr",
+        "tesla" => "T",
+        "volt" => "V",
+        "watt" => "W",
+        "weber" => "Wb",
+        "astronomicalunit" => "au",
+        "bel" => "B",
+        "dalton" => "Da",
+        "day" => "d",
+        "decibel" => "dB",
+        "degree" => "°",
+        "electronvolt" => "eV",
+        "hectare" => "ha",
+        "hour" => "h",
+        "litre" | "liter" => "L",
+        "arcminute" => "′",
+        "minute" => "min",
+        "arcsecond" => "″",
+        "neper" => "Np",
+        "tonne" => "t",
+        "angstrom" => "Å",
+        "bar" => "bar",
+        "byte" => "B",
+        "percent" => "%",
+        _ => return None,
+    })
+}
+
+/// 单位：字母串或 `\kilo\gram` 等命令为单个单位，`.`、`~` 与空白分隔单位，
+/// `^` 与 `\squared`、`\cubed`、`\tothe{n}`（前置的 `\square`、`\cubic`、`\raiseto{n}`）给出幂次，
+/// `\per` 把下一个单位放到分母，`/` 把其后的全部单位放到分母
+fn parse_units(parser: &Parser, source: &str, settings: &Settings) -> ParseResult<AstNode> {
+    let mut scope = parser.nested(source);
+    scope.enter_nested()?;
+    let mut cursor = Cursor::new(source);
+    let mut list = UnitList::default();
+    while let Some(ch) = cursor.peek() {
+        scope.check_deadline()?;
+        match ch {
+            _ if ch.is_whitespace() => cursor.skip_whitespace(),
+            '.' | '~' | '{' | '}' => {
+                cursor.bump();
+            }
+            '/' => {
+                cursor.bump();
+                list.after_slash = true;
+            }
+            '^' => {
+                cursor.bump();
+                let power = match cursor.peek() {
+                    Some('{') => cursor.take_group('{', '}', "单位幂次")?,
+                    Some('-') => {
+                        cursor.bump();
+                        format!("-{}", cursor.take_while(|next| next.is_ascii_digit()))
+                    }
+                    _ => cursor.take_while(|next| next.is_ascii_digit()),
+                };
+                list.set_power(&power)?;
+            }
+            '\\' => {
+                let (name, command) = cursor.take_command()?;
+                let argument = || {
+                    command[name.len() + 1..]
+                        .trim_start_matches('{')
+                        .trim_end_matches('}')
+                        .to_string()
+                };
+                match name.as_str() {
+                    "per" => list.per = true,
+                    "squared" => list.set_power("2")?,
+                    "cubed" => list.set_power("3")?,
+                    "tothe" => list.set_power(&argument())?,
+                    "square" => list.pending_power = Some("2".into()),
+                    "cubic" => list.pending_power = Some("3".into()),
+                    "raiseto" => list.pending_power = Some(argument()),
+                    _ => {
+                        if let Some(symbol) = prefix_symbol(&name) {
+                            list.prefix.push_str(symbol);
+                        } else if let Some(symbol) = unit_symbol(&name) {
+                            list.push(symbol);
+                        } else {
+                            return Err(RenderError::ParseError(format!(
+                                "siunitx 不支持的单位命令 \\{name}"
+                            )));
+                        }
+                    }
+                }
+            }
+            _ => {
+                let symbol = cursor.take_while(|next| {
+                    !next.is_whitespace()
+                        && !matches!(next, '.' | '~' | '/' | '^' | '\\' | '{' | '}')
+                });
+                list.push(&symbol);
+            }
+        }
+    }
+    if !list.prefix.is_empty() || list.per || list.pending_power.is_some() {
+        return Err(RenderError::ParseError(
+            "siunitx 单位以词头、幂次或 \\per 结尾，缺少单位".into(),
+        ));
+    }
+    Ok(build_units(list.units, settings))
+}
+
+/// 逐个读入的单位，以及尚未落到单位上的词头、前置幂次与 `\per`
+#[derive(Default)]
+struct UnitList {
+    units: Vec<Unit>,
+    prefix: String,
+    pending_power: Option<String>,
+    per: bool,
+    after_slash: bool,
+}
+
+impl UnitList {
+    fn push(&mut self, symbol: &str) {
+        let position = if self.per {
+            Position::Per
+        } else if self.after_slash {
+            Position::Slash
+        } else {
+            Position::Numerator
+        };
+        self.units.push(Unit {
+            text: std::mem::take(&mut self.prefix) + symbol,
+            power: self.pending_power.take(),
+            position,
+        });
+        self.per = false;
+    }
+
+    fn set_power(&mut self, power: &str) -> ParseResult<()> {
+        let unit = self
+            .units
+            .last_mut()
+            .ok_or_else(|| RenderError::ParseError("siunitx 幂次缺少前导单位".into()))?;
+        if power.trim().is_empty() {
+            return Err(RenderError::ParseError("siunitx 幂次不能为空".into()));
+        }
+        unit.power = Some(power.trim().to_string());
+        Ok(())
+    }
+}
+
+fn build_units(units: Vec<Unit>, settings: &Settings) -> AstNode {
+    let (numerator, denominator): (Vec<Unit>, Vec<Unit>) = units
+        .into_iter()
+        .partition(|unit| unit.position == Position::Numerator);
+    let negate = |unit: &Unit| match settings.per_mode {
+        Some(PerMode::Power) => true,
+        Some(_) => false,
+        None => unit.position == Position::Per,
+    };
+    let (negated, divided): (Vec<Unit>, Vec<Unit>) = denominator.into_iter().partition(negate);
+    let mut upper: Vec<AstNode> = numerator
+        .iter()
+        .map(|unit| unit_node(unit, false))
+        .collect();
+    upper.extend(negated.iter().map(|unit| unit_node(unit, true)));
+    if divided.is_empty() {
+        return product(upper);
+    }
+    let lower: Vec<AstNode> = divided.iter().map(|unit| unit_node(unit, false)).collect();
+    if settings.per_mode == Some(PerMode::Fraction) {
+        let upper = if upper.is_empty() {
+            AstNode::Text("1".into())
+        } else {
+            product(upper)
+        };
+        return AstNode::Fraction(FractionNode::new(upper, product(lower)));
+    }
+    // 斜线后有多个单位时加括号，如 J/(mol K)
+    let multiple = lower.len() > 1;
+    let mut nodes = vec![if upper.is_empty() {
+        AstNode::Text("1".into())
+    } else {
+        product(upper)
+    }];
+    nodes.push(AstNode::Text(if multiple { "/(" } else { "/" }.into()));
+    nodes.push(product(lower));
+    if multiple {
+        nodes.push(AstNode::Text(")".into()));
+    }
+    AstNode::Group(nodes)
+}
+
+/// 单位之间以细空格相连
+fn product(units: Vec<AstNode>) -> AstNode {
+    let mut nodes = Vec::with_capacity(units.len() * 2);
+    for unit in units {
+        if !nodes.is_empty() {
+            nodes.push(thin_space());
+        }
+        nodes.push(unit);
+    }
+    Parser::normalize_group_static(AstNode::Group(nodes))
+}
+
+fn unit_node(unit: &Unit, negative: bool) -> AstNode {
+    let power = match (&unit.power, negative) {
+        (None, false) => None,
+        (None, true) => Some("-1".to_string()),
+        (Some(power), false) => Some(power.clone()),
+        (Some(power), true) => Some(match power.strip_prefix('-') {
+            Some(positive) => positive.to_string(),
+            None => format!("-{power}"),
+        }),
+    };
+    let base = AstNode::Text(unit.text.clone());
+    match power {
+        Some(power) => AstNode::Scripts {
+            base: Box::new(base),
+            superscript: Some(Box::new(AstNode::Text(power.replace('-', "−")))),
+            subscript: None,
+        },
+        None => base,
+    }
+}
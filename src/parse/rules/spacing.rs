@@ -1,24 +1,131 @@
-//! 间距命令映射，将 `\,` 等转换为实际的 Unicode 空白字符
+//! 间距命令：`\,`、`\quad` 等固定间距与 `\kern`、`\hspace` 等显式长度都解析为
+//! [`AstNode::Kern`]，排版时只作为水平偏移，不会以空白字符出现在 SVG 中
 
 use phf::phf_map;
 
-const THIN_SPACE: &str = "\u{2009}";
-const MEDIUM_SPACE: &str = "\u{2004}";
-const THICK_SPACE: &str = "\u{2005}";
-const HAIR_SPACE: &str = "\u{200A}";
-const EM_SPACE: &str = "\u{2003}";
-const EN_SPACE: &str = "\u{2002}";
+use crate::ast::{AstNode, ParseResult};
+use crate::error::RenderError;
 
-static SPACING: phf::Map<&'static str, &'static str> = phf_map! {
+use super::super::lexer::Parser;
+
+/// 1mu 为 1/18 em，以下间距均以字号为单位
+const MU: f32 = 1.0 / 18.0;
+pub(super) const THIN_SPACE: f32 = 3.0 * MU;
+pub(super) const MEDIUM_SPACE: f32 = 4.0 * MU;
+pub(super) const THICK_SPACE: f32 = 5.0 * MU;
+/// `\ ` 取 Latin Modern 的词间距
+const WORD_SPACE: f32 = 0.333;
+/// 显式长度的绝对值上限（以字号为单位），避免 `\rule{99999in}{..}` 之类撑出超大画布
+const MAX_DIMENSION: f32 = 100.0;
+
+static SPACING: phf::Map<&'static str, f32> = phf_map! {
     "," => THIN_SPACE,
-    ";" => THICK_SPACE,
+    "thinspace" => THIN_SPACE,
     ":" => MEDIUM_SPACE,
-    "!" => HAIR_SPACE,
-    "quad" => EM_SPACE,
-    "qquad" => "\u{2003}\u{2003}",
-    " " => EN_SPACE,
+    ">" => MEDIUM_SPACE,
+    "medspace" => MEDIUM_SPACE,
+    ";" => THICK_SPACE,
+    "thickspace" => THICK_SPACE,
+    "!" => -THIN_SPACE,
+    "negthinspace" => -THIN_SPACE,
+    "negmedspace" => -MEDIUM_SPACE,
+    "negthickspace" => -THICK_SPACE,
+    "enspace" => 0.5,
+    "quad" => 1.0,
+    "qquad" => 2.0,
+    " " => WORD_SPACE,
 };
 
-pub fn map_spacing(command: &str) -> Option<&'static str> {
-    SPACING.get(command).copied()
+pub fn handle(parser: &mut Parser, command: &str) -> Option<ParseResult<AstNode>> {
+    let result = match command {
+        "kern" => inline_length(parser, command, false),
+        "mkern" => inline_length(parser, command, true),
+        "hspace" => {
+            if parser.peek_char() == Some('*') {
+                parser.consume_char();
+            }
+            braced_length(parser, command, false)
+        }
+        "mspace" => braced_length(parser, command, true),
+        _ => {
+            let width = *SPACING.get(command)?;
+            // 与 TeX 一致，`\quad` 等控制词之后的空白不再额外占位
+            if command.starts_with(|ch: char| ch.is_ascii_alphabetic()) {
+                parser.skip_whitespace();
+            }
+            Ok(width)
+        }
+    };
+    Some(result.map(AstNode::Kern))
+}
+
+/// `\kern` 与 `\mkern` 的长度直接跟在命令之后，如 `\kern-2pt`、`\mkern 3mu`，也接受花括号包裹
+fn inline_length(parser: &mut Parser, command: &str, mu_only: bool) -> ParseResult<f32> {
+    parser.skip_whitespace();
+    if parser.peek_char() == Some('{') {
+        return braced_length(parser, command, mu_only);
+    }
+    let mut text = String::new();
+    while let Some(ch) = parser
+        .peek_char()
+        .filter(|ch| ch.is_ascii_digit() || matches!(ch, '+' | '-' | '.' | ' '))
+    {
+        text.push(ch);
+        parser.consume_char();
+    }
+    for _ in 0..2 {
+        if let Some(ch) = parser.peek_char().filter(char::is_ascii_alphabetic) {
+            text.push(ch);
+            parser.consume_char();
+        }
+    }
+    // 与 TeX 一致，单位之后的一个空格属于长度本身
+    if parser.peek_char() == Some(' ') {
+        parser.consume_char();
+    }
+    parse_length(&text, command, mu_only)
+}
+
+fn braced_length(parser: &mut Parser, command: &str, mu_only: bool) -> ParseResult<f32> {
+    let text = parser.consume_braced_content(command)?;
+    parse_length(&text, command, mu_only)
+}
+
+/// 把命令参数中的长度换算为以字号为单位的数值；`mu_only` 时只接受 mu 单位
+pub(super) fn parse_length(text: &str, command: &str, mu_only: bool) -> ParseResult<f32> {
+    let text = text.trim();
+    if mu_only && !text.ends_with("mu") {
+        return Err(RenderError::ParseError(format!(
+            "\\{command} 的长度必须以 mu 为单位，收到 {text}"
+        )));
+    }
+    parse_dimension(text)
+        .ok_or_else(|| RenderError::ParseError(format!("无法识别的 \\{command} 长度 {text}")))
+}
+
+/// 把 `1.5pt`、`-0.2em`、`3mu` 这类 TeX 长度换算为以字号为单位的数值；
+/// 按 10pt 字号换算，`ex` 取 Latin Modern 的 x 高度，结果限制在 ±[`MAX_DIMENSION`] 以内，
+/// 无法识别时返回 `None`
+pub fn parse_dimension(text: &str) -> Option<f32> {
+    let text = text.trim();
+    let split = text
+        .find(|ch: char| ch.is_ascii_alphabetic())
+        .unwrap_or(text.len());
+    let (number, unit) = text.split_at(split);
+    let value: f32 = number.trim().parse().ok()?;
+    let per_unit = match unit.trim() {
+        "em" => 1.0,
+        "ex" => 0.431,
+        "mu" => 1.0 / 18.0,
+        "pt" => 0.1,
+        "bp" => 0.1 * 72.27 / 72.0,
+        "mm" => 0.1 * 72.27 / 25.4,
+        "cm" => 0.1 * 72.27 / 2.54,
+        "in" => 0.1 * 72.27,
+        "px" => 0.1 * 72.27 / 96.0,
+        _ => return None,
+    };
+    value
+        .is_finite()
+        .then(|| (value * per_unit).clamp(-MAX_DIMENSION, MAX_DIMENSION))
 }
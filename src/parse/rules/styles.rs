@@ -32,15 +32,14 @@ fn apply_style(node: AstNode, style: TextStyle) -> AstNode {
                 .map(|child| apply_style(child, style))
                 .collect(),
         ),
-        AstNode::Fraction {
-            numerator,
-            denominator,
-        } => AstNode::Fraction {
-            numerator: Box::new(apply_style(*numerator, style)),
-            denominator: Box::new(apply_style(*denominator, style)),
-        },
-        AstNode::Sqrt { value } => AstNode::Sqrt {
+        AstNode::Fraction(mut fraction) => {
+            fraction.numerator = Box::new(apply_style(*fraction.numerator, style));
+            fraction.denominator = Box::new(apply_style(*fraction.denominator, style));
+            AstNode::Fraction(fraction)
+        }
+        AstNode::Sqrt { value, index } => AstNode::Sqrt {
             value: Box::new(apply_style(*value, style)),
+            index: index.map(|node| Box::new(apply_style(*node, style))),
         },
         AstNode::Delimited { left, inner, right } => AstNode::Delimited {
             left,
@@ -49,15 +48,22 @@ fn apply_style(node: AstNode, style: TextStyle) -> AstNode {
         },
         AstNode::LargeOperator(op) => AstNode::LargeOperator(op),
         AstNode::Symbol(sym) => AstNode::Symbol(sym),
-        AstNode::Matrix(rows) => AstNode::Matrix(
-            rows.into_iter()
+        AstNode::Middle(delimiter) => AstNode::Middle(delimiter),
+        AstNode::SizedDelimiter(node) => AstNode::SizedDelimiter(node),
+        AstNode::Kern(width) => AstNode::Kern(width),
+        AstNode::Rule(rule) => AstNode::Rule(rule),
+        AstNode::Array(mut array) => {
+            array.rows = array
+                .rows
+                .into_iter()
                 .map(|row| {
                     row.into_iter()
                         .map(|cell| apply_style(cell, style))
                         .collect()
                 })
-                .collect(),
-        ),
+                .collect();
+            AstNode::Array(array)
+        }
         AstNode::Decorated { base, decoration } => AstNode::Decorated {
             base: Box::new(apply_style(*base, style)),
             decoration,
@@ -71,6 +77,27 @@ fn apply_style(node: AstNode, style: TextStyle) -> AstNode {
             superscript: superscript.map(|node| Box::new(apply_style(*node, style))),
             subscript: subscript.map(|node| Box::new(apply_style(*node, style))),
         },
+        AstNode::UnderOver { base, over, under } => AstNode::UnderOver {
+            base: Box::new(apply_style(*base, style)),
+            over: over.map(|node| Box::new(apply_style(*node, style))),
+            under: under.map(|node| Box::new(apply_style(*node, style))),
+        },
+        AstNode::CdArrow(mut arrow) => {
+            arrow.before = arrow.before.map(|node| Box::new(apply_style(*node, style)));
+            arrow.after = arrow.after.map(|node| Box::new(apply_style(*node, style)));
+            AstNode::CdArrow(arrow)
+        }
+        AstNode::Enclosed(mut enclosure) => {
+            enclosure.body = Box::new(apply_style(*enclosure.body, style));
+            enclosure.target = enclosure
+                .target
+                .map(|node| Box::new(apply_style(*node, style)));
+            AstNode::Enclosed(enclosure)
+        }
+        AstNode::Adjusted { body, adjust } => AstNode::Adjusted {
+            body: Box::new(apply_style(*body, style)),
+            adjust,
+        },
     }
 }
 
@@ -0,0 +1,43 @@
+use crate::ast::{AstNode, ParseResult};
+use crate::error::RenderError;
+
+use super::super::lexer::Parser;
+
+/// 公式编号命令：只记录到解析器上，由所在行或整条公式统一结算，本身不产生内容
+pub fn handle(parser: &mut Parser, command: &str) -> Option<ParseResult<AstNode>> {
+    match command {
+        "tag" => Some(handle_tag(parser)),
+        "notag" | "nonumber" => {
+            parser.marks_mut().suppressed = true;
+            Some(Ok(AstNode::Text(String::new())))
+        }
+        "label" => Some(handle_label(parser)),
+        _ => None,
+    }
+}
+
+/// `\tag{..}` 显示为带括号的编号，`\tag*{..}` 原样显示
+fn handle_tag(parser: &mut Parser) -> ParseResult<AstNode> {
+    let starred = parser.peek_char() == Some('*');
+    if starred {
+        parser.consume_char();
+    }
+    let text = parser.consume_braced_content("tag")?.trim().to_string();
+    if parser.marks_mut().tag.is_some() {
+        return Err(RenderError::ParseError("同一行只能有一个 \\tag".into()));
+    }
+    parser.marks_mut().tag = Some((text, starred));
+    Ok(AstNode::Text(String::new()))
+}
+
+fn handle_label(parser: &mut Parser) -> ParseResult<AstNode> {
+    let key = parser.consume_braced_content("label")?.trim().to_string();
+    if key.is_empty() {
+        return Err(RenderError::ParseError("\\label 的名称不能为空".into()));
+    }
+    if parser.marks_mut().label.is_some() {
+        return Err(RenderError::ParseError("同一行只能有一个 \\label".into()));
+    }
+    parser.marks_mut().label = Some(key);
+    Ok(AstNode::Text(String::new()))
+}
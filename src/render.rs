@@ -19,9 +19,11 @@ pub fn render_svg_document(plan: &LayoutPlan) -> Result<String, RenderError> {
         return Ok(base_svg);
     }
 
-    let mut opts = UsvgOptions::default();
-    opts.font_family = init::primary_font_family().to_string();
-    opts.font_size = init::default_font_size();
+    let opts = UsvgOptions {
+        font_family: init::primary_font_family().to_string(),
+        font_size: init::default_font_size(),
+        ..UsvgOptions::default()
+    };
 
     let mut tree = usvg::Tree::from_str(&base_svg, &opts)
         .map_err(|err| RenderError::RenderFailure(format!("usvg 解析失败: {err}")))?;
@@ -37,7 +39,7 @@ pub fn render_svg_document(plan: &LayoutPlan) -> Result<String, RenderError> {
     Ok(svg)
 }
 
-fn build_base_svg(plan: &LayoutPlan) -> String {
+pub(crate) fn build_base_svg(plan: &LayoutPlan) -> String {
     let safe_width = plan.width.max(1.0);
     let safe_height = plan.height.max(1.0);
     let estimated = (plan.items.len() + plan.lines.len() + plan.paths.len()) * 96 + 256;
@@ -53,18 +55,7 @@ fn build_base_svg(plan: &LayoutPlan) -> String {
         embed_font_face(&mut svg, plan.font_family);
     }
 
-    if !plan.lines.is_empty() {
-        svg.push_str("<g stroke=\"#000000\" fill=\"none\">");
-        for line in &plan.lines {
-            let _ = write!(
-                &mut svg,
-                r#"<line x1="{:.2}" y1="{:.2}" x2="{:.2}" y2="{:.2}" stroke-width="{:.2}" stroke-linecap="round"/>"#,
-                line.x1, line.y1, line.x2, line.y2, line.stroke_width
-            );
-        }
-        svg.push_str("</g>");
-    }
-
+    // 路径先于直线输出，使 \colorbox 等底色位于分数线、表格线之下
     if !plan.paths.is_empty() {
         svg.push_str("<g>");
         for path in &plan.paths {
@@ -96,6 +87,18 @@ fn build_base_svg(plan: &LayoutPlan) -> String {
         svg.push_str("</g>");
     }
 
+    if !plan.lines.is_empty() {
+        svg.push_str("<g stroke=\"#000000\" fill=\"none\">");
+        for line in &plan.lines {
+            let _ = write!(
+                &mut svg,
+                r#"<line x1="{:.2}" y1="{:.2}" x2="{:.2}" y2="{:.2}" stroke-width="{:.2}" stroke-linecap="round"/>"#,
+                line.x1, line.y1, line.x2, line.y2, line.stroke_width
+            );
+        }
+        svg.push_str("</g>");
+    }
+
     if !plan.items.is_empty() {
         svg.push_str("<g fill=\"#000000\">");
         for item in &plan.items {
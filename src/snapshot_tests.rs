@@ -0,0 +1,529 @@
+//! 快照测试：把各条解析规则的排版几何与 SVG 输出和仓库内的基准文件比对
+//!
+//! 基准文件位于 `tests/snapshots/<模块>/<用例>.{layout,svg}`，数值按容差比较。
+//! 有意修改排版后，运行 `UPDATE_SNAPSHOTS=1 cargo test snapshot` 重新生成基准。
+
+use std::fmt::Write as _;
+use std::fs;
+use std::path::PathBuf;
+
+use crate::config::{EquationOptions, RenderBudget, RenderLimits};
+use crate::error::RenderError;
+use crate::layout::{self, LayoutPlan, RenderItem};
+use crate::{normalize_escaped_commands, numbering, parse, render};
+
+/// 数值比较容差（像素）
+const TOLERANCE: f32 = 0.05;
+
+fn snapshot_dir(module: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("snapshots")
+        .join(module)
+}
+
+pub(crate) fn should_bless() -> bool {
+    std::env::var("UPDATE_SNAPSHOTS").is_ok_and(|value| !value.is_empty() && value != "0")
+}
+
+/// 运行解析与排版，返回布局结果；不经过全局输出模式配置
+pub(crate) fn layout_formula(tex: &str) -> LayoutPlan {
+    layout_formula_with_options(tex, &EquationOptions::default())
+}
+
+/// 同 [`layout_formula`]，按指定设置解析数值、结算并摆放公式编号
+pub(crate) fn layout_formula_with_options(tex: &str, options: &EquationOptions) -> LayoutPlan {
+    let budget = RenderBudget::start(RenderLimits::default());
+    let normalized = normalize_escaped_commands(tex);
+    let parsed = parse::parse(normalized.as_ref(), &budget, options)
+        .unwrap_or_else(|err| panic!("解析 {tex:?} 失败: {err}"));
+    let numbering = numbering::assign(&parsed.tags, options.first_number);
+    layout::layout(&parsed, &numbering.rows, options, &budget)
+        .unwrap_or_else(|err| panic!("排版 {tex:?} 失败: {err}"))
+}
+
+/// 按文本查找布局结果中的文字片段，找不到时让测试失败
+pub(crate) fn find_item(plan: &LayoutPlan, text: &str) -> RenderItem {
+    plan.items
+        .iter()
+        .find(|item| item.text == text)
+        .cloned()
+        .unwrap_or_else(|| panic!("缺少文本 {text}"))
+}
+
+/// 断言每个输入都在解析或排版阶段返回 [`RenderError::ParseError`]
+pub(crate) fn assert_parse_errors(inputs: &[&str]) {
+    let options = EquationOptions::default();
+    for tex in inputs {
+        let budget = RenderBudget::start(RenderLimits::default());
+        let normalized = normalize_escaped_commands(tex);
+        let result = parse::parse(normalized.as_ref(), &budget, &options).and_then(|parsed| {
+            let numbering = numbering::assign(&parsed.tags, options.first_number);
+            layout::layout(&parsed, &numbering.rows, &options, &budget)
+        });
+        assert!(
+            matches!(result, Err(RenderError::ParseError(_))),
+            "{tex} 应返回解析错误"
+        );
+    }
+}
+
+/// 把布局结果序列化成逐行文本，便于审阅 diff
+fn normalize_layout(plan: &LayoutPlan) -> String {
+    let mut out = String::new();
+    let _ = writeln!(out, "size {:.2} {:.2}", plan.width, plan.height);
+    for item in &plan.items {
+        let _ = writeln!(
+            out,
+            "text {:?} {:.2} {:.2} {:.2}",
+            item.text, item.x, item.y, item.font_size
+        );
+    }
+    for line in &plan.lines {
+        let _ = writeln!(
+            out,
+            "line {:.2} {:.2} {:.2} {:.2} {:.2}",
+            line.x1, line.y1, line.x2, line.y2, line.stroke_width
+        );
+    }
+    for path in &plan.paths {
+        let _ = writeln!(
+            out,
+            "path {:?} {:.2} {:.2} fill={} stroke={} width={:.2}",
+            path.d,
+            path.x,
+            path.y,
+            path.fill.unwrap_or("none"),
+            path.stroke.unwrap_or("none"),
+            path.stroke_width.unwrap_or(0.0)
+        );
+    }
+    out
+}
+
+#[derive(Debug, PartialEq)]
+enum Token<'a> {
+    Number(f32),
+    Other(&'a str),
+}
+
+/// 把文本切分成数字与非数字片段，数字片段单独做容差比较
+fn tokenize(input: &str) -> Vec<Token<'_>> {
+    let bytes = input.as_bytes();
+    let mut tokens = Vec::new();
+    let mut idx = 0;
+    let mut other_start = 0;
+    while idx < bytes.len() {
+        let starts_number = bytes[idx].is_ascii_digit()
+            || (bytes[idx] == b'-' && bytes.get(idx + 1).is_some_and(u8::is_ascii_digit));
+        if !starts_number {
+            idx += 1;
+            continue;
+        }
+        let mut end = idx + 1;
+        while end < bytes.len() && (bytes[end].is_ascii_digit() || bytes[end] == b'.') {
+            end += 1;
+        }
+        if let Ok(value) = input[idx..end].parse::<f32>() {
+            if other_start < idx {
+                tokens.push(Token::Other(&input[other_start..idx]));
+            }
+            tokens.push(Token::Number(value));
+            other_start = end;
+        }
+        idx = end;
+    }
+    if other_start < bytes.len() {
+        tokens.push(Token::Other(&input[other_start..]));
+    }
+    tokens
+}
+
+/// 按容差比较两段文本，返回首个差异的描述
+fn compare_with_tolerance(expected: &str, actual: &str) -> Option<String> {
+    for (line_no, (exp_line, act_line)) in expected.lines().zip(actual.lines()).enumerate() {
+        let exp_tokens = tokenize(exp_line);
+        let act_tokens = tokenize(act_line);
+        let matches = exp_tokens.len() == act_tokens.len()
+            && exp_tokens.iter().zip(&act_tokens).all(|pair| match pair {
+                (Token::Number(a), Token::Number(b)) => (a - b).abs() <= TOLERANCE,
+                (a, b) => a == b,
+            });
+        if !matches {
+            return Some(format!(
+                "第 {} 行不同\n  基准: {exp_line}\n  实际: {act_line}",
+                line_no + 1
+            ));
+        }
+    }
+    let (exp_count, act_count) = (expected.lines().count(), actual.lines().count());
+    if exp_count != act_count {
+        return Some(format!(
+            "行数不同：基准 {exp_count} 行，实际 {act_count} 行"
+        ));
+    }
+    None
+}
+
+/// SVG 单行输出，按标签拆行后再比较，失败时更容易定位
+fn split_svg_tags(svg: &str) -> String {
+    svg.replace("><", ">\n<") + "\n"
+}
+
+/// 对一组用例执行快照比对；任一失败时汇总后统一报错
+fn check_snapshots(module: &str, cases: &[(&str, &str)]) {
+    let dir = snapshot_dir(module);
+    let bless = should_bless();
+    let mut failures = Vec::new();
+
+    for (name, tex) in cases {
+        let plan = layout_formula(tex);
+        let outputs = [
+            ("layout", normalize_layout(&plan)),
+            ("svg", split_svg_tags(&render::build_base_svg(&plan))),
+        ];
+        for (extension, actual) in outputs {
+            let path = dir.join(format!("{name}.{extension}"));
+            if bless {
+                fs::create_dir_all(&dir).expect("创建快照目录失败");
+                fs::write(&path, &actual).expect("写入快照失败");
+                continue;
+            }
+            match fs::read_to_string(&path) {
+                Ok(expected) => {
+                    if let Some(diff) = compare_with_tolerance(&expected, &actual) {
+                        failures.push(format!("{module}/{name}.{extension} ({tex}): {diff}"));
+                    }
+                }
+                Err(_) => failures.push(format!(
+                    "{module}/{name}.{extension} 缺少基准文件，请运行 UPDATE_SNAPSHOTS=1 cargo test snapshot"
+                )),
+            }
+        }
+    }
+
+    assert!(
+        failures.is_empty(),
+        "快照不一致（确认是预期改动后运行 UPDATE_SNAPSHOTS=1 cargo test snapshot）：\n{}",
+        failures.join("\n")
+    );
+}
+
+#[test]
+fn snapshot_tolerance_should_ignore_tiny_differences() {
+    assert!(compare_with_tolerance("text \"a\" 1.00 2.00", "text \"a\" 1.02 1.99").is_none());
+    assert!(compare_with_tolerance("text \"a\" 1.00 2.00", "text \"a\" 1.20 2.00").is_some());
+    assert!(compare_with_tolerance("text \"a\" 1.00", "text \"b\" 1.00").is_some());
+    assert!(
+        compare_with_tolerance("size 1.00 2.00\n", "size 1.00 2.00\nline 0 0 1 1 1\n").is_some()
+    );
+}
+
+#[test]
+fn snapshot_basic() {
+    check_snapshots(
+        "basic",
+        &[
+            ("text", r"a + b"),
+            ("scripts", r"x_i^2 + E=mc^2"),
+            ("text_command", r"\text{if } x"),
+            ("symbols", r"\alpha \leq \beta \rightarrow \infty"),
+        ],
+    );
+}
+
+#[test]
+fn snapshot_fractions() {
+    check_snapshots(
+        "fractions",
+        &[
+            ("frac", r"\frac{a}{b}"),
+            ("nested", r"\frac{1}{1 + \frac{1}{x}}"),
+            ("binom", r"\binom{n}{k}"),
+            ("dfrac_tfrac", r"\dfrac{a}{b} + \tfrac{a}{b}"),
+            ("cfrac", r"\cfrac{1}{1 + \cfrac[l]{1}{x + y}}"),
+            ("genfrac", r"\genfrac{[}{]}{0pt}{1}{n}{k}"),
+            ("tbinom", r"\tbinom{n}{k} \dbinom{n}{k}"),
+            ("infix", r"{a \over b} + {n \choose k} + {x \atop y}"),
+        ],
+    );
+}
+
+#[test]
+fn snapshot_roots() {
+    check_snapshots(
+        "roots",
+        &[
+            ("sqrt", r"\sqrt{x}"),
+            ("index", r"\sqrt[3]{x + 1}"),
+            ("tall", r"\sqrt{\frac{a}{b}}"),
+            (
+                "assembly",
+                r"\sqrt{\frac{\frac{a}{b}}{\frac{\frac{c}{d}}{e}}}",
+            ),
+            ("tall_index", r"\sqrt[n]{\frac{1}{1 + x}}"),
+        ],
+    );
+}
+
+#[test]
+fn snapshot_delimiters() {
+    check_snapshots(
+        "delimiters",
+        &[
+            ("paren", r"\left( x \right)"),
+            ("tall", r"\left( \frac{1}{2} \right)"),
+            ("angle", r"\left\langle a, b \right\rangle"),
+            ("open", r"\left. \frac{a}{b} \right|"),
+            (
+                "big_sizes",
+                r"\bigl( \Bigl[ \biggl\{ \Biggl\langle x \Biggr\rangle \biggr\} \Bigr] \bigr)",
+            ),
+            ("bigm", r"a \bigm| b \Bigm\| c"),
+            ("middle", r"\left\{ x \middle| \frac{x}{2} > 0 \right\}"),
+        ],
+    );
+}
+
+#[test]
+fn snapshot_environments() {
+    check_snapshots(
+        "environments",
+        &[
+            ("matrix", r"\begin{matrix} 1 & 2 \\ 3 & 4 \end{matrix}"),
+            ("pmatrix", r"\begin{pmatrix} a & b \\ c & d \end{pmatrix}"),
+            (
+                "cases",
+                r"\begin{cases} x & x > 0 \\ -x & x \leq 0 \end{cases}",
+            ),
+            (
+                "starred_matrix",
+                r"\begin{bmatrix*}[r] -1 & 2 \\ 3 & -40 \end{bmatrix*}",
+            ),
+            (
+                "smallmatrix",
+                r"\left( \begin{smallmatrix} a & b \\ c & d \end{smallmatrix} \right)",
+            ),
+            ("vmatrix", r"\begin{vmatrix} a & b \\ c & d \end{vmatrix}"),
+            (
+                "cases_text",
+                r"|x| = \begin{cases*} x & if $x \geq 0$ \\ -x & otherwise \end{cases*}",
+            ),
+            (
+                "dcases",
+                r"\begin{dcases} \frac{1}{x} & x > 0 \\ 0 & x \leq 0 \end{dcases}",
+            ),
+            (
+                "rcases",
+                r"\begin{rcases} a & x > 0 \\ b & x < 0 \end{rcases} \Rightarrow c",
+            ),
+            (
+                "commutative_diagram",
+                r"\begin{CD} A @>f>> B \\ @VgVV @VV\psi V \\ C @>>h> D \end{CD}",
+            ),
+            (
+                "numcases",
+                r"\begin{numcases}{f(x)=} x & for $x \geq 0$ \\ -x & for $x < 0$ \nonumber \end{numcases}",
+            ),
+            ("aligned", r"\begin{aligned} a &= b \\ c &= d \end{aligned}"),
+            (
+                "align_pairs",
+                r"\begin{align*} f(x) &= x^2 & g(x) &\leq 1 \\[2pt] &= \frac{1}{2} & h &\to 0 \end{align*}",
+            ),
+            (
+                "alignat",
+                r"\begin{alignedat}{2} a &= b &\quad c &= d \\ aa &= bb & cc &= dd \end{alignedat}",
+            ),
+            (
+                "gather",
+                r"\begin{gather} a + b \\ c = d + e + f \end{gather}",
+            ),
+            (
+                "align_tags",
+                r"\begin{align} a &= b + c \\ &\leq d \notag \\ e &= \frac{1}{2} \tag{*} \label{eq:e} \end{align}",
+            ),
+            (
+                "equation_split",
+                r"\begin{equation} \begin{split} a &= b \\ &= c \end{split} \end{equation}",
+            ),
+            (
+                "multline",
+                r"\begin{multline} a + b + c \\ d + e \\ f + g + h + i \end{multline}",
+            ),
+            (
+                "array_rules",
+                r"\begin{array}{|l|c||r|} \hline a & b & c \\ \hline\hline dd & ee & ff \\ \cline{2-3} g & h & i \\ \hline \end{array}",
+            ),
+            (
+                "array_separators",
+                r"\renewcommand{\arraystretch}{1.5} \begin{array}{r@{\,:\,}l} a & b \\[4pt] ccc & \frac{1}{2} \end{array}",
+            ),
+        ],
+    );
+}
+
+#[test]
+fn snapshot_decorations() {
+    check_snapshots(
+        "decorations",
+        &[
+            ("overline", r"\overline{AB}"),
+            ("underline", r"\underline{x}"),
+            ("hat", r"\hat{x}"),
+            ("vec", r"\vec{v}"),
+            ("ddot", r"\ddot{x}"),
+            ("dddot", r"\dddot{y}"),
+            ("acute", r"\acute{e}"),
+            ("check", r"\check{C}"),
+            ("mathring", r"\mathring{A}"),
+            ("overbrace", r"\overbrace{a + b}^{n}"),
+            ("underbrace", r"\underbrace{x + y + z}_{3}"),
+            ("overbracket", r"\overbracket{abc}"),
+            ("underparen", r"\underparen{AB}"),
+            ("widehat", r"\widehat{xyz}"),
+            ("widetilde", r"\widetilde{AB}"),
+            ("overrightarrow", r"\overrightarrow{AB}"),
+            ("overleftrightarrow", r"\overleftrightarrow{x + y}"),
+            ("underleftarrow", r"\underleftarrow{AB}"),
+            ("utilde", r"\utilde{ab}"),
+            ("xrightarrow", r"\xrightarrow{f}"),
+        ],
+    );
+}
+
+#[test]
+fn snapshot_stacking() {
+    check_snapshots(
+        "stacking",
+        &[
+            ("overset", r"\overset{a}{b}"),
+            ("underset", r"\underset{x}{\arg\max} f(x)"),
+            ("stackrel", r"\stackrel{\text{def}}{=}"),
+            ("overunderset", r"\overunderset{n}{i=1}{\bigcup}"),
+            ("arrow_below", r"\xrightarrow[g]{f}"),
+        ],
+    );
+}
+
+#[test]
+fn snapshot_styles() {
+    check_snapshots(
+        "styles",
+        &[
+            ("mathbf", r"\mathbf{AB}"),
+            ("mathbb", r"\mathbb{R}"),
+            ("mathcal", r"\mathcal{F}"),
+        ],
+    );
+}
+
+#[test]
+fn snapshot_operators() {
+    check_snapshots(
+        "operators",
+        &[
+            ("sum", r"\sum_{i=1}^{n} i"),
+            ("int", r"\int_0^1 f(x)"),
+            ("lim", r"\lim_{x \to 0} x"),
+            ("function", r"\sin x + \log y"),
+            (
+                "substack",
+                r"\sum_{\substack{0 \le i \le m \\ 0 < j < n}} P(i, j)",
+            ),
+            (
+                "subarray",
+                r"\prod_{\begin{subarray}{l} p \\ p < N \end{subarray}} p",
+            ),
+        ],
+    );
+}
+
+#[test]
+fn snapshot_physics() {
+    check_snapshots(
+        "physics",
+        &[
+            ("braket", r"\braket{\phi|\frac{A}{2}|\psi}"),
+            ("ket", r"\ket{0} + \bra{1}"),
+            ("expval", r"\expval{H}{\psi} = \mel{n}{H}{m}"),
+            ("norm", r"\norm{v} \le \abs{\frac{a}{b}}"),
+            ("commutator", r"\comm{A}{B} = \acomm{A}{B}"),
+            ("derivative", r"\dv[2]{f}{x} + \pdv{f}{x}{y}"),
+        ],
+    );
+}
+
+#[test]
+fn snapshot_siunitx() {
+    check_snapshots(
+        "siunitx",
+        &[
+            ("grouping", r"\num{1234567.891011}"),
+            ("exponent", r"\num{-1.5e-03} + \num{1.2+-0.1e3}"),
+            ("literal_units", r"\SI{3.0e8}{m/s} = \si{kg.m^2/s^2}"),
+            ("macro_units", r"\qty{9.81}{\metre\per\second\squared}"),
+            (
+                "fraction",
+                r"\si[per-mode=fraction]{\joule\per\mole\per\kelvin}",
+            ),
+            (
+                "angle",
+                r"\SI{30}{\degree} + \num[output-decimal-marker={,}]{3.14}",
+            ),
+        ],
+    );
+}
+
+#[test]
+fn snapshot_enclosures() {
+    check_snapshots(
+        "enclosures",
+        &[
+            ("boxed", r"E = \boxed{mc^2}"),
+            ("fbox", r"\fbox{answer: $x = \frac{1}{2}$}"),
+            (
+                "colorbox",
+                r"\colorbox{yellow}{x} + \fcolorbox{red}{lightgray}{y}",
+            ),
+            ("ovalbox", r"\ovalbox{x + y}"),
+            (
+                "cancel",
+                r"\cancel{a} + \bcancel{b} + \xcancel{c} + \sout{d}",
+            ),
+            ("cancelto", r"\cancelto{0}{x^2}"),
+        ],
+    );
+}
+
+#[test]
+fn snapshot_boxes() {
+    check_snapshots(
+        "boxes",
+        &[
+            ("phantom", r"\phantom{ab}c + \hphantom{\dfrac{1}{2}}x"),
+            (
+                "strut",
+                r"\sqrt{x} \sqrt{\mathstrut y} \sqrt{\vphantom{\dfrac{1}{2}} z}",
+            ),
+            ("smash", r"x + \smash{\dfrac{a}{b}} + \smash[b]{y}"),
+            ("kern", r"a\kern1em b\mkern-3mu c\hspace{2pt}d\mspace{9mu}e"),
+            ("raisebox", r"x\raisebox{0.5em}{up}\raisebox{-0.3em}{down}"),
+            ("rule", r"a\rule{1em}{0.4pt}b\rule[-2pt]{0.5em}{1ex}c"),
+        ],
+    );
+}
+
+#[test]
+fn snapshot_chemistry() {
+    check_snapshots(
+        "chemistry",
+        &[
+            ("reaction", r"\ce{2H2 + O2 -> 2H2O}"),
+            ("ions", r"\ce{[Cu(NH3)4]^2+ + SO4^2-}"),
+            ("isotope", r"\ce{^{14}_{6}C}"),
+            ("equilibrium", r"\ce{N2 + 3H2 <=>[\text{Fe}] 2NH3}"),
+            ("bonds", r"\ce{CH2=CH-C#N}"),
+            ("units", r"\pu{1.2e-3 mol L-1}"),
+        ],
+    );
+}
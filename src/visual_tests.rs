@@ -0,0 +1,273 @@
+//! 像素级回归测试：用 resvg 把渲染结果栅格化，与仓库内的参考 PNG 做感知差异比较
+//!
+//! 参考图位于 `tests/visual/<用例>.png`，失败时在 `target/visual-diff/` 下输出实际图与差异图。
+//! 有意修改排版后，运行 `UPDATE_SNAPSHOTS=1 cargo test visual` 重新生成参考图。
+
+use std::fs;
+use std::path::PathBuf;
+
+use resvg::tiny_skia::{Color, Pixmap, PremultipliedColorU8, Transform};
+use usvg::{Options as UsvgOptions, TreeParsing, TreeTextToPath};
+
+use crate::init;
+use crate::render;
+use crate::snapshot_tests::{layout_formula, should_bless};
+
+/// 亮度差超过该比例的像素视为不同（0~1）
+const PIXEL_THRESHOLD: f32 = 0.1;
+/// 允许不同像素占总像素的最大比例
+const MAX_DIFF_RATIO: f32 = 0.002;
+
+fn reference_path(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("visual")
+        .join(format!("{name}.png"))
+}
+
+fn diff_dir() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("target")
+        .join("visual-diff")
+}
+
+/// 走与路径模式相同的 usvg 文字转路径流程，再用 resvg 栅格化到白底画布
+fn rasterize(tex: &str) -> Pixmap {
+    let plan = layout_formula(tex);
+    let svg = render::build_base_svg(&plan);
+    let opts = UsvgOptions {
+        font_family: init::primary_font_family().to_string(),
+        font_size: init::default_font_size(),
+        ..UsvgOptions::default()
+    };
+    let mut tree = usvg::Tree::from_str(&svg, &opts).expect("usvg 解析失败");
+    tree.convert_text(init::font_database().expect("字体数据库加载失败"));
+    let render_tree = resvg::Tree::from_usvg(&tree);
+
+    let size = render_tree.size.to_int_size();
+    let mut pixmap = Pixmap::new(size.width(), size.height()).expect("画布尺寸非法");
+    pixmap.fill(Color::WHITE);
+    render_tree.render(Transform::identity(), &mut pixmap.as_mut());
+    pixmap
+}
+
+/// 把预乘 RGBA 像素合成到白底后取亮度
+fn luminance(pixmap: &Pixmap, x: u32, y: u32) -> f32 {
+    let pixel = pixmap.pixels()[(y * pixmap.width() + x) as usize];
+    let white = 255 - pixel.alpha();
+    let r = f32::from(pixel.red().saturating_add(white));
+    let g = f32::from(pixel.green().saturating_add(white));
+    let b = f32::from(pixel.blue().saturating_add(white));
+    (0.299 * r + 0.587 * g + 0.114 * b) / 255.0
+}
+
+/// 邻域内存在亮度相近的像素时视为抗锯齿抖动，不计入差异
+fn has_close_neighbor(source: f32, other: &Pixmap, x: u32, y: u32) -> bool {
+    let x_range = x.saturating_sub(1)..=(x + 1).min(other.width() - 1);
+    x_range.into_iter().any(|nx| {
+        let y_range = y.saturating_sub(1)..=(y + 1).min(other.height() - 1);
+        y_range
+            .into_iter()
+            .any(|ny| (luminance(other, nx, ny) - source).abs() <= PIXEL_THRESHOLD)
+    })
+}
+
+/// 比较两张图，返回不同像素数与标红的差异图
+fn diff_images(expected: &Pixmap, actual: &Pixmap) -> (usize, Pixmap) {
+    let mut diff = Pixmap::new(actual.width(), actual.height()).expect("画布尺寸非法");
+    let mut mismatched = 0;
+    for y in 0..actual.height() {
+        for x in 0..actual.width() {
+            let act = luminance(actual, x, y);
+            let exp = luminance(expected, x, y);
+            let differs = (act - exp).abs() > PIXEL_THRESHOLD
+                && !has_close_neighbor(act, expected, x, y)
+                && !has_close_neighbor(exp, actual, x, y);
+            let (r, g, b) = if differs {
+                mismatched += 1;
+                (255, 0, 0)
+            } else {
+                // 相同像素淡化显示，方便定位差异
+                let faded = (200.0 + act * 55.0) as u8;
+                (faded, faded, faded)
+            };
+            diff.pixels_mut()[(y * actual.width() + x) as usize] =
+                PremultipliedColorU8::from_rgba(r, g, b, 255).expect("不透明像素总是合法");
+        }
+    }
+    (mismatched, diff)
+}
+
+fn write_failure_artifacts(name: &str, actual: &Pixmap, diff: Option<&Pixmap>) -> PathBuf {
+    let dir = diff_dir();
+    let _ = fs::create_dir_all(&dir);
+    if let Ok(png) = actual.encode_png() {
+        let _ = fs::write(dir.join(format!("{name}.actual.png")), png);
+    }
+    if let Some(Ok(png)) = diff.map(Pixmap::encode_png) {
+        let _ = fs::write(dir.join(format!("{name}.diff.png")), png);
+    }
+    dir
+}
+
+/// 对一组用例执行像素比对；任一失败时汇总后统一报错
+fn check_visuals(cases: &[(&str, &str)]) {
+    let bless = should_bless();
+    let mut failures = Vec::new();
+
+    for (name, tex) in cases {
+        let actual = rasterize(tex);
+        let path = reference_path(name);
+        if bless {
+            fs::create_dir_all(path.parent().expect("参考图目录缺失")).expect("创建参考图目录失败");
+            fs::write(&path, actual.encode_png().expect("PNG 编码失败")).expect("写入参考图失败");
+            continue;
+        }
+
+        let expected = match fs::read(&path)
+            .ok()
+            .and_then(|data| Pixmap::decode_png(&data).ok())
+        {
+            Some(image) => image,
+            None => {
+                failures.push(format!(
+                    "{name} ({tex}) 缺少参考图，请运行 UPDATE_SNAPSHOTS=1 cargo test visual"
+                ));
+                continue;
+            }
+        };
+
+        if (expected.width(), expected.height()) != (actual.width(), actual.height()) {
+            let dir = write_failure_artifacts(name, &actual, None);
+            failures.push(format!(
+                "{name} ({tex}) 尺寸不同：参考 {}x{}，实际 {}x{}，实际图已写入 {}",
+                expected.width(),
+                expected.height(),
+                actual.width(),
+                actual.height(),
+                dir.display()
+            ));
+            continue;
+        }
+
+        let (mismatched, diff) = diff_images(&expected, &actual);
+        let total = (actual.width() * actual.height()) as f32;
+        if mismatched as f32 / total > MAX_DIFF_RATIO {
+            let dir = write_failure_artifacts(name, &actual, Some(&diff));
+            failures.push(format!(
+                "{name} ({tex}) 有 {mismatched} 个像素不同，差异图已写入 {}",
+                dir.display()
+            ));
+        }
+    }
+
+    assert!(
+        failures.is_empty(),
+        "像素比对失败（确认是预期改动后运行 UPDATE_SNAPSHOTS=1 cargo test visual）：\n{}",
+        failures.join("\n")
+    );
+}
+
+#[test]
+fn visual_diff_should_flag_changed_pixels() {
+    let base = rasterize(r"x + y");
+    let (same, _) = diff_images(&base, &base);
+    assert_eq!(same, 0, "同一张图不应有差异像素");
+
+    let mut blank = base.clone();
+    blank.fill(Color::WHITE);
+    let (changed, _) = diff_images(&blank, &base);
+    assert!(changed > 0, "空白图与公式图之间应检测到差异");
+}
+
+#[test]
+fn visual_regressions() {
+    check_visuals(&[
+        ("scripts", r"x_i^2 + y_{j}^{k+1}"),
+        ("fraction", r"\frac{a + b}{c}"),
+        ("nested_fraction", r"\frac{1}{1 + \frac{1}{x}}"),
+        ("sqrt", r"\sqrt[3]{x^2 + 1}"),
+        (
+            "tall_radical",
+            r"\sqrt{\frac{\frac{a}{b}}{\frac{\frac{c}{d}}{e}}} + \sqrt[n]{\frac{1}{2}}",
+        ),
+        ("delimiters", r"\left( \frac{1}{2} \right)"),
+        ("matrix", r"\begin{pmatrix} a & b \\ c & d \end{pmatrix}"),
+        (
+            "cases",
+            r"\begin{cases} x & x > 0 \\ -x & x \leq 0 \end{cases}",
+        ),
+        ("decorations", r"\hat{x} + \vec{v} + \overline{AB}"),
+        ("sum", r"\sum_{i=1}^{n} i^2"),
+        ("integral", r"\int_0^1 f(x) dx"),
+        ("arrow", r"\xrightarrow{f}"),
+        (
+            "accents",
+            r"\acute{e} \grave{a} \check{c} \breve{u} \mathring{A} \bar{x} \dot{x} \ddot{x} \dddot{x}",
+        ),
+        (
+            "wide_accents",
+            r"\widehat{xyz} + \widetilde{AB} + \overrightarrow{AB} + \underleftarrow{pq}",
+        ),
+        (
+            "array",
+            r"\left( \begin{array}{c|cc} 1 & 2 & 3 \\ \hline 4 & 5 & 6 \\ \cline{2-3} 7 & 8 & 9 \end{array} \right)",
+        ),
+        (
+            "align",
+            r"x = \left\{ \begin{aligned} a &= b + c \\ &\leq d & y &\to 0 \end{aligned} \right.",
+        ),
+        (
+            "matrix_variants",
+            r"\begin{matrix} 1 & 2 \\ 3 & 4 \end{matrix} \begin{Bmatrix*}[l] x & -1 \\ -yy & 2 \end{Bmatrix*} \begin{smallmatrix} a & b \\ c & d \end{smallmatrix}",
+        ),
+        (
+            "cases_variants",
+            r"\begin{dcases} \frac{1}{x} & x > 0 \\ 0 & \text{else} \end{dcases} \begin{rcases} a & \text{if } x \\ b & \text{if } y \end{rcases} \begin{cases*} \tfrac{1}{2} & when $n$ is even \\ 0 & otherwise \end{cases*}",
+        ),
+        (
+            "commutative_diagram",
+            r"\begin{CD} A @>f>> B @<<< C \\ @VgVV @AA\beta A @| \\ D @>>h> E @= F \end{CD}",
+        ),
+        (
+            "chemistry",
+            r"\ce{2H2 + O2 ->[\Delta] 2H2O} \quad \ce{[Cu(NH3)4]^2+ + SO4^2- <=> CuSO4*5H2O v} \quad \ce{^{227}_{90}Th + e- -> Na+ + Cl- (aq)} \quad \ce{CH3-CH=CH2 + H-C#C-H} \quad \pu{1.2e3 kJ mol-1} \quad \pu{9.81 m/s^2}",
+        ),
+        (
+            "physics",
+            r"\braket{\phi|\frac{A}{2}|\psi} + \ketbra{0}{1} \quad \expval{\hat{H}}{\psi} \quad \norm{\vec{v}} \le \abs*{x} \quad \comm{\hat{x}}{\hat{p}} = i\hbar \quad \dv[2]{f}{x} = \pdv{f}{x}{y}",
+        ),
+        (
+            "siunitx",
+            r"\num{1234567.891011} \quad \num{1.2+-0.1e3} \quad \SI{3.0e8}{m/s} \quad \si{kg.m^2/s^2} \quad \qty{9.81}{\metre\per\second\squared} \quad \si[per-mode=fraction]{\joule\per\mole\per\kelvin} \quad \SI{30}{\degree}",
+        ),
+        (
+            "enclosures",
+            r"E = \boxed{mc^2} \quad \fbox{area $\frac{1}{2}bh$} \quad \colorbox{yellow}{x} + \fcolorbox{red}{lightgray}{y} \quad \ovalbox{x + y} \quad \frac{\cancel{2}x}{\cancel{2}} + \bcancel{b} + \xcancel{c} + \sout{d} \quad \cancelto{0}{x^2}",
+        ),
+        (
+            "boxes",
+            r"\sqrt{\mathstrut a} + \sqrt{\mathstrut b} \quad \sqrt{\smash[b]{y}} + \sqrt{y} \quad a\kern1em b\mkern-3mu c \quad \phantom{xx}| \quad x\raisebox{0.5em}{up} \quad a\rule[-2pt]{1em}{1ex}b",
+        ),
+        (
+            "substack",
+            r"\sum_{\substack{0 \le i \le m \\ 0 < j < n}} P(i, j) + \prod_{\begin{subarray}{l} p \\ p < N \end{subarray}} p",
+        ),
+        (
+            "equation_tags",
+            r"\begin{align} a &= b + c \\ &\leq d \notag \\ e &= \frac{1}{2} \tag*{[A]} \end{align}",
+        ),
+        (
+            "fraction_variants",
+            r"\cfrac{1}{1 + \cfrac{1}{x}} = \tfrac{1}{2} + \binom{n}{k} + {a \atop b}",
+        ),
+        (
+            "sized_delimiters",
+            r"\Bigl( \bigl[ x \bigr] \Bigr) \bigm| \left\{ x \middle| \frac{x}{2} > 0 \right\}",
+        ),
+        (
+            "braces",
+            r"\overbrace{a + b}^{n} + \underbrace{x_1 + \cdots + x_k}_{k}",
+        ),
+    ]);
+}